@@ -0,0 +1,155 @@
+use crate::{Access, ComponentRegistry, Events, Local, Schedule, SubWorld, ECS};
+use alloc::vec::Vec;
+use core::any::TypeId;
+
+/// Registers a feature's Components, resources, and systems into a host world
+pub trait Plugin: 'static {
+	fn build(&self, ecs: &mut ECS, schedule: &mut Schedule);
+}
+
+/// An `ECS` together with the `Schedule` that runs on it, for the usual setup of one world driven once per frame
+pub struct App {
+	ecs: ECS,
+	schedule: Schedule,
+	plugins: Vec<TypeId>,
+}
+
+impl App {
+	pub fn new(capacity: usize) -> Self {
+		App { ecs: ECS::new(capacity), schedule: Schedule::new(), plugins: Vec::new() }
+	}
+
+	/// Start from the Components in `registry`, so scenes built from the same registry share ids
+	pub fn from_registry(registry: &ComponentRegistry, capacity: usize) -> Self {
+		App { ecs: ECS::from_registry(registry, capacity), schedule: Schedule::new(), plugins: Vec::new() }
+	}
+
+	pub fn ecs(&self) -> &ECS { &self.ecs }
+
+	pub fn ecs_mut(&mut self) -> &mut ECS { &mut self.ecs }
+
+	pub fn schedule(&self) -> &Schedule { &self.schedule }
+
+	pub fn schedule_mut(&mut self) -> &mut Schedule { &mut self.schedule }
+
+	pub fn insert_resource<R: Send + Sync + 'static>(&mut self, resource: R) -> &mut Self {
+		self.ecs.insert_resource(resource);
+		self
+	}
+
+	/// Insert an empty `Events<E>` and have the schedule update it every frame
+	pub fn add_event<E: Send + Sync + 'static>(&mut self) -> &mut Self {
+		if !self.ecs.has_resource::<Events<E>>() {
+			self.ecs.insert_resource(Events::<E>::new());
+		}
+		self.schedule.add_event::<E>();
+		self
+	}
+
+	pub fn add_system<F: FnMut(&mut SubWorld) + 'static>(&mut self, stage: &str, access: Access, system: F) -> &mut Self {
+		self.schedule.add_system(stage, access, system);
+		self
+	}
+
+	pub fn add_exclusive_system<F: FnMut(&mut ECS) + 'static>(&mut self, stage: &str, system: F) -> &mut Self {
+		self.schedule.add_exclusive_system(stage, system);
+		self
+	}
+
+	pub fn add_exclusive_system_with_local<T, F>(&mut self, stage: &str, system: F) -> &mut Self
+	where T: Default + 'static, F: FnMut(&mut ECS, &mut Local<T>) + 'static {
+		self.schedule.add_exclusive_system_with_local(stage, system);
+		self
+	}
+
+	pub fn add_startup_system<F: FnMut(&mut SubWorld) + 'static>(&mut self, access: Access, system: F) -> &mut Self {
+		self.schedule.add_startup_system(access, system);
+		self
+	}
+
+	pub fn add_startup_exclusive_system<F: FnMut(&mut ECS) + 'static>(&mut self, system: F) -> &mut Self {
+		self.schedule.add_startup_exclusive_system(system);
+		self
+	}
+
+	/// Run the schedule once
+	pub fn run_frame(&mut self) {
+		self.schedule.run(&mut self.ecs);
+	}
+
+	/// Build `plugin` into this app. Adding a plugin of a type already added does nothing,
+	/// so plugins can add the plugins they depend on.
+	pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+		if self.plugins.contains(&TypeId::of::<P>()) { return self; }
+		self.plugins.push(TypeId::of::<P>());
+		plugin.build(&mut self.ecs, &mut self.schedule);
+		self
+	}
+
+	pub fn has_plugin<P: Plugin>(&self) -> bool {
+		self.plugins.contains(&TypeId::of::<P>())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Access, App, Component, ComponentRegistry, Plugin, Schedule, ECS};
+
+	struct Body(u32);
+	impl Component for Body {}
+
+	struct Gravity(u32);
+
+	struct Physics;
+	impl Plugin for Physics {
+		fn build(&self, ecs: &mut ECS, schedule: &mut Schedule) {
+			ecs.register::<Body>();
+			ecs.insert_resource(Gravity(0));
+			schedule.add_exclusive_system(Schedule::UPDATE, |ecs| ecs.get_resource_mut::<Gravity>().unwrap().0 += 1);
+		}
+	}
+
+	#[test]
+	fn frames() {
+		let mut registry = ComponentRegistry::new();
+		registry.register::<Body>();
+		let mut app = App::from_registry(&registry, 4);
+		app.insert_resource(Gravity(0))
+			.add_startup_exclusive_system(|ecs| {
+				ecs.commands().spawn(|ecs, entity| ecs.add_component(entity, Body(0)));
+			})
+			.add_system(Schedule::UPDATE, Access::new().write::<Body>(), |world| {
+				for (_, body) in world.query_mut::<Body>() {
+					body.0 += 1;
+				}
+			})
+			.add_exclusive_system(Schedule::LATE_UPDATE, |ecs| ecs.get_resource_mut::<Gravity>().unwrap().0 += 1);
+
+		app.run_frame();
+		app.run_frame();
+		assert_eq!(app.ecs().query::<Body>().map(|(_, body)| body.0).collect::<alloc::vec::Vec<_>>(), [2]);
+		assert_eq!(app.ecs().get_resource::<Gravity>().unwrap().0, 2);
+	}
+
+	#[test]
+	fn tick() {
+		let mut app = App::new(4);
+		app.run_frame();
+		app.run_frame();
+		assert_eq!(app.ecs().current_tick(), 2);
+		assert_eq!(app.ecs_mut().advance_tick(), 3);
+	}
+
+	#[test]
+	fn plugins() {
+		let mut app = App::new(4);
+		app.add_plugin(Physics).add_plugin(Physics);
+		assert!(app.has_plugin::<Physics>());
+
+		let entity = app.ecs_mut().create_entity().unwrap();
+		app.ecs_mut().add_component(&entity, Body(1));
+		app.run_frame();
+		assert_eq!(app.ecs().get_resource::<Gravity>().unwrap().0, 1);
+		assert_eq!(app.ecs().get_component::<Body>(&entity).unwrap().0, 1);
+	}
+}
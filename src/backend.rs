@@ -0,0 +1,343 @@
+use crate::component::{ComponentID, ComponentInfo};
+use crate::{Component, EntityID, ECS};
+use alloc::alloc::{alloc, dealloc};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::ptr::copy_nonoverlapping;
+
+/// Where a column keeps its values, chosen per Component at registration with `ComponentInfo::with_storage`.
+/// Components default to pages spanning the capacity, or allocated as values arrive if registered as paged.
+///
+/// Storage only decides where each index lives. The column tracks which indices are filled and its stats, and
+/// writes, moves and drops values through the pointers storage hands out, so queries walk occupancy a word at
+/// a time whatever backend holds the values. It keeps no change ticks for custom storage, so `query_changed`
+/// only reports such Components after a rollback.
+///
+/// # Safety
+/// Pointers from `insert` and `get` must be aligned for `info.layout()` and valid for `info.stride()` bytes.
+/// The pointer of a filled index must keep pointing at its value until the storage is next called through
+/// `&mut self`, and must not overlap that of any other filled index. Every index `contiguous_end` covers
+/// must be laid out `info.stride()` bytes after the one before it.
+pub unsafe trait ComponentStorage: 'static {
+	/// Storage for indices `0..length` of the Component described by `info`, all empty
+	fn new(info: &ComponentInfo, length: usize) -> Self where Self: Sized;
+
+	/// Memory for a value about to be written at `index`, which is empty
+	fn insert(&mut self, index: usize) -> *mut u8;
+
+	/// Memory of the value at `index`, which is filled
+	fn get(&self, index: usize) -> *mut u8;
+
+	/// The value at `index` has been dropped or moved out, leaving it empty
+	fn remove(&mut self, index: usize);
+
+	/// Grow to cover indices `0..new_length`, the new ones empty
+	fn resize(&mut self, new_length: usize);
+
+	/// Index one past the last stored contiguously with `index`, which bounds the slices query runs hand out
+	fn contiguous_end(&self, index: usize) -> usize { index + 1 }
+
+	/// Heap memory held, for `ECS::component_stats`
+	fn allocated_bytes(&self) -> usize { 0 }
+
+	/// Every value laid out `info.stride()` apart from the pointer, with the index each belongs to, for backends
+	/// that keep them dense. Lets `ECS::query_packed` walk them in memory order.
+	fn packed(&self) -> Option<(*mut u8, &[usize])> { None }
+}
+
+pub(crate) type StorageFn = fn(&ComponentInfo, usize) -> Box<dyn ComponentStorage>;
+
+pub(crate) fn new_storage<S: ComponentStorage>(info: &ComponentInfo, length: usize) -> Box<dyn ComponentStorage> {
+	Box::new(S::new(info, length))
+}
+
+/// Storage for zero sized markers that holds nothing at all: the column's occupancy is the whole of it, so
+/// `has_component` and queries cost a bit per entity where the default storage also keeps a change tick.
+pub struct NullStorage {
+	align: usize,
+}
+
+unsafe impl ComponentStorage for NullStorage {
+	fn new(info: &ComponentInfo, _length: usize) -> Self {
+		assert!(info.layout().size() == 0, "NullStorage only holds zero sized Components");
+		NullStorage { align: info.layout().align() }
+	}
+
+	fn insert(&mut self, index: usize) -> *mut u8 { self.get(index) }
+
+	/// Dangling but aligned, never dereferenced for more than zero bytes
+	fn get(&self, _index: usize) -> *mut u8 { core::ptr::without_provenance_mut(self.align) }
+
+	fn remove(&mut self, _index: usize) {}
+
+	fn resize(&mut self, _new_length: usize) {}
+
+	/// Every index sits at the same address, so any run of them is contiguous
+	fn contiguous_end(&self, _index: usize) -> usize { usize::MAX }
+}
+
+/// Storage packing values back to back, found through the position of each index, so `ECS::query_packed` walks
+/// nothing but values, as for transforms updated every frame. Lookups by entity pay one extra indirection, and
+/// removing moves the last value into the gap, so unlike the default values don't keep their address.
+pub struct DenseVecStorage {
+	values: *mut u8,
+	/// Values `values` has room for
+	capacity: usize,
+	/// Index each packed value belongs to
+	indices: Vec<usize>,
+	/// Packed position of the value at each index, `EMPTY` if there is none
+	positions: Vec<usize>,
+	/// Of one value, padded to its stride
+	layout: Layout,
+}
+
+const EMPTY: usize = usize::MAX;
+
+impl DenseVecStorage {
+	fn value(&self, position: usize) -> *mut u8 {
+		self.values.wrapping_add(position * self.layout.size())
+	}
+
+	/// Double the room for values, moving them to a new allocation
+	fn grow(&mut self) {
+		let capacity = (self.capacity * 2).max(4);
+		if self.layout.size() != 0 {
+			let values = unsafe { alloc(Self::array_layout(self.layout, capacity)) };
+			assert!(!values.is_null());
+			unsafe { copy_nonoverlapping(self.values, values, self.indices.len() * self.layout.size()) };
+			self.free();
+			self.values = values;
+		}
+		self.capacity = capacity;
+	}
+
+	fn array_layout(layout: Layout, capacity: usize) -> Layout {
+		Layout::from_size_align(layout.size() * capacity, layout.align()).unwrap()
+	}
+
+	fn free(&mut self) {
+		if self.layout.size() != 0 && self.capacity != 0 {
+			unsafe { dealloc(self.values, Self::array_layout(self.layout, self.capacity)) };
+		}
+	}
+}
+
+unsafe impl ComponentStorage for DenseVecStorage {
+	fn new(info: &ComponentInfo, length: usize) -> Self {
+		let layout = Layout::from_size_align(info.stride(), info.layout().align()).unwrap();
+		// Dangling but aligned until the first value arrives
+		let values = core::ptr::without_provenance_mut(layout.align());
+		DenseVecStorage { values, capacity: 0, indices: Vec::new(), positions: alloc::vec![EMPTY; length], layout }
+	}
+
+	fn insert(&mut self, index: usize) -> *mut u8 {
+		if self.indices.len() == self.capacity { self.grow(); }
+		self.positions[index] = self.indices.len();
+		self.indices.push(index);
+		self.value(self.positions[index])
+	}
+
+	fn get(&self, index: usize) -> *mut u8 { self.value(self.positions[index]) }
+
+	fn remove(&mut self, index: usize) {
+		let position = self.positions[index];
+		let last = self.indices.len() - 1;
+		if position != last {
+			unsafe { copy_nonoverlapping(self.value(last), self.value(position), self.layout.size()) };
+			self.indices[position] = self.indices[last];
+			self.positions[self.indices[last]] = position;
+		}
+		self.indices.pop();
+		self.positions[index] = EMPTY;
+	}
+
+	fn resize(&mut self, new_length: usize) {
+		self.positions.resize(new_length, EMPTY);
+	}
+
+	fn allocated_bytes(&self) -> usize {
+		self.capacity * self.layout.size() + (self.indices.capacity() + self.positions.capacity()) * size_of::<usize>()
+	}
+
+	fn packed(&self) -> Option<(*mut u8, &[usize])> {
+		Some((self.values, &self.indices))
+	}
+}
+
+impl Drop for DenseVecStorage {
+	fn drop(&mut self) {
+		self.free();
+	}
+}
+
+impl ECS {
+	/// Every `C` in memory order rather than index order, for Components whose storage packs values together
+	/// such as `DenseVecStorage`. Empty for any other.
+	pub fn query_packed<C: Component>(&self) -> impl Iterator<Item = (EntityID, &C)> + '_ {
+		let (indices, values) = self.components.get_array::<C>().and_then(|array| unsafe { array.packed::<C>() }).unwrap_or_default();
+		indices.iter().zip(values).filter_map(|(index, value)| Some((self.get_index(*index)?, value)))
+	}
+
+	/// `query_packed` with mutable access
+	pub fn query_packed_mut<C: Component>(&mut self) -> impl Iterator<Item = (EntityID, &mut C)> + '_ {
+		self.unshare(&[ComponentID::of::<C>()]);
+		let ecs: &ECS = self;
+		let array = ecs.components.get_array::<C>();
+		let (indices, values) = array.and_then(|array| unsafe { array.packed_mut::<C>() }).unwrap_or_default();
+		indices.iter().zip(values).filter_map(move |(index, value)| {
+			let entity = ecs.get_index(*index)?;
+			array?.stamp(*index, ecs.change_tick);
+			Some((entity, value))
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ComponentStorage, DenseVecStorage, NullStorage};
+	use crate::component::ComponentInfo;
+	use crate::{Component, ECS};
+	use alloc::alloc::{alloc, dealloc};
+	use alloc::vec::Vec;
+	use core::alloc::Layout;
+
+	/// Every value in its own allocation
+	struct Boxed {
+		values: Vec<*mut u8>,
+		layout: Layout,
+	}
+
+	unsafe impl ComponentStorage for Boxed {
+		fn new(info: &ComponentInfo, length: usize) -> Self {
+			Boxed { values: alloc::vec![core::ptr::null_mut(); length], layout: info.layout() }
+		}
+
+		fn insert(&mut self, index: usize) -> *mut u8 {
+			self.values[index] = unsafe { alloc(self.layout) };
+			self.values[index]
+		}
+
+		fn get(&self, index: usize) -> *mut u8 { self.values[index] }
+
+		fn remove(&mut self, index: usize) {
+			unsafe { dealloc(self.values[index], self.layout) };
+			self.values[index] = core::ptr::null_mut();
+		}
+
+		fn resize(&mut self, new_length: usize) {
+			self.values.resize(new_length, core::ptr::null_mut());
+		}
+
+		fn allocated_bytes(&self) -> usize {
+			self.values.iter().filter(|value| !value.is_null()).count() * self.layout.size()
+		}
+	}
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Health(u64);
+	impl Component for Health {}
+
+	#[test]
+	fn custom() {
+		let mut ecs = ECS::new(4);
+		ecs.register_with_storage::<Health, Boxed>();
+		let entities: Vec<_> = (0..4).map(|_| ecs.create_entity().unwrap()).collect();
+		for (health, entity) in entities.iter().enumerate() {
+			ecs.add_component(entity, Health(health as u64));
+		}
+		ecs.add_component(&entities[0], Health(10));
+		assert_eq!(ecs.take_component::<Health>(&entities[1]), Some(Health(1)));
+		for (_, health) in ecs.query_mut::<Health>() {
+			health.0 += 1;
+		}
+		assert_eq!(ecs.query::<Health>().map(|(_, health)| health.0).collect::<Vec<_>>(), [11, 3, 4]);
+		assert_eq!(ecs.component_stats()[0].allocated_bytes, 3 * 8);
+
+		ecs.swap_component::<Health>(&entities[0], &entities[3]);
+		assert_eq!(ecs.query::<Health>().map(|(_, health)| health.0).collect::<Vec<_>>(), [4, 3, 11]);
+		ecs.grow_capacity_to_size(8);
+		assert_eq!(ecs.query::<Health>().count(), 3);
+	}
+
+	#[derive(Clone, Copy)]
+	struct Frozen;
+	impl Component for Frozen {}
+
+	#[test]
+	fn null() {
+		let mut ecs = ECS::new(300);
+		ecs.register_with_storage::<Frozen, NullStorage>();
+		let entities: Vec<_> = (0..300).map(|_| ecs.create_entity().unwrap()).collect();
+		for entity in entities.iter().step_by(2) {
+			ecs.add_component(entity, Frozen);
+		}
+		assert!(ecs.has_component::<Frozen>(&entities[0]) && !ecs.has_component::<Frozen>(&entities[1]));
+		assert_eq!(ecs.query::<Frozen>().count(), 150);
+		assert_eq!(ecs.query::<Frozen>().runs().map(|(_, run)| run.len()).sum::<usize>(), 150);
+		assert!(ecs.take_component::<Frozen>(&entities[0]).is_some());
+		assert_eq!(ecs.component_stats()[0].allocated_bytes, 0);
+		assert_eq!(ecs.component_stats()[0].count, 149);
+	}
+
+	#[test]
+	fn null_footprint() {
+		let mut ecs = ECS::new(1024);
+		ecs.register::<Frozen>();
+		let default = ecs.component_stats()[0];
+		let mut ecs = ECS::new(1024);
+		ecs.register_with_storage::<Frozen, NullStorage>();
+		let null = ecs.component_stats()[0];
+		assert_eq!((default.allocated_bytes, null.allocated_bytes), (0, 0));
+		assert_eq!(default.page_bytes - null.page_bytes, 1024 * core::mem::size_of::<u64>());
+	}
+
+	#[derive(Clone, Copy)]
+	#[repr(align(16))]
+	struct Aligned;
+	impl Component for Aligned {}
+
+	#[test]
+	fn null_aligned() {
+		let mut ecs = ECS::new(4);
+		ecs.register_with_storage::<Aligned, NullStorage>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Aligned);
+		let marker: *const Aligned = ecs.get_component::<Aligned>(&entity).unwrap();
+		assert!(marker.is_aligned());
+		assert_eq!(ecs.query::<Aligned>().count(), 1);
+	}
+
+	#[test]
+	#[should_panic(expected = "zero sized")]
+	fn null_sized() {
+		ECS::new(1).register_with_storage::<Health, NullStorage>();
+	}
+
+	#[test]
+	fn dense() {
+		let mut ecs = ECS::new(8);
+		ecs.register_with_storage::<Health, DenseVecStorage>();
+		let entities: Vec<_> = (0..8).map(|_| ecs.create_entity().unwrap()).collect();
+		for index in [5, 1, 6, 2, 7] {
+			ecs.add_component(&entities[index], Health(index as u64));
+		}
+		assert_eq!(ecs.query_packed::<Health>().map(|(_, health)| health.0).collect::<Vec<_>>(), [5, 1, 6, 2, 7]);
+		assert_eq!(ecs.query::<Health>().map(|(_, health)| health.0).collect::<Vec<_>>(), [1, 2, 5, 6, 7]);
+
+		// The last value fills the gap
+		assert_eq!(ecs.take_component::<Health>(&entities[1]), Some(Health(1)));
+		for (entity, health) in ecs.query_packed_mut::<Health>() {
+			assert!(entity == entities[health.0 as usize]);
+			health.0 *= 10;
+		}
+		assert_eq!(ecs.query_packed::<Health>().map(|(_, health)| health.0).collect::<Vec<_>>(), [50, 70, 60, 20]);
+		assert_eq!(ecs.get_component::<Health>(&entities[6]), Some(&Health(60)));
+		assert!(ecs.query_packed::<Frozen>().next().is_none());
+
+		ecs.destroy_entity(entities[5]);
+		ecs.grow_capacity_to_size(16);
+		assert_eq!(ecs.query::<Health>().map(|(_, health)| health.0).collect::<Vec<_>>(), [20, 60, 70]);
+		assert_eq!(ecs.component_stats()[0].count, 3);
+	}
+}
@@ -0,0 +1,73 @@
+use alloc::sync::Arc;
+
+/// Keeps the `ECS` it came from from growing while it lives. Every query iterator holds one, and code keeping raw
+/// pointers or slices into Component storage past the borrow they came from should too, see `ECS::borrow_storage`.
+/// Growing while one is live panics, or with `panic-free` leaves the capacity unchanged.
+#[derive(Clone)]
+pub struct StorageBorrow {
+	_count: Arc<()>,
+}
+
+/// Counts the `StorageBorrow`s of one world through the reference count they share
+#[derive(Default)]
+pub(crate) struct StorageBorrows(Arc<()>);
+
+impl StorageBorrows {
+	pub(crate) fn borrow(&self) -> StorageBorrow {
+		StorageBorrow { _count: self.0.clone() }
+	}
+
+	pub(crate) fn is_borrowed(&self) -> bool {
+		Arc::strong_count(&self.0) > 1
+	}
+}
+
+impl crate::ECS {
+	/// Guard storage against growth for as long as the result lives, making it an error instead of a dangling pointer
+	/// to grow while raw access is outstanding
+	pub fn borrow_storage(&self) -> StorageBorrow {
+		self.storage_borrows.borrow()
+	}
+
+	/// Whether any query or `StorageBorrow` still holds this world's storage
+	pub fn is_storage_borrowed(&self) -> bool {
+		self.storage_borrows.is_borrowed()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+
+	struct Health;
+	impl Component for Health {}
+
+	#[test]
+	#[cfg(not(feature = "panic-free"))]
+	#[should_panic(expected = "while storage is borrowed")]
+	fn grow_while_borrowed() {
+		let mut ecs = ECS::new(1);
+		ecs.set_grow_fn(Some(|capacity| capacity * 2));
+		let _borrow = ecs.borrow_storage();
+		ecs.create_entity();
+		ecs.create_entity();
+	}
+
+	#[test]
+	fn borrows() {
+		let mut ecs = ECS::new(1);
+		ecs.set_grow_fn(Some(|capacity| capacity * 2));
+		ecs.register::<Health>();
+		let query = ecs.query::<Health>();
+		let chunks: alloc::vec::Vec<_> = query.chunks(1).collect();
+		assert!(ecs.is_storage_borrowed());
+		drop(chunks);
+		assert!(!ecs.is_storage_borrowed());
+
+		let borrow = ecs.borrow_storage();
+		drop(borrow);
+		ecs.create_entity();
+		ecs.create_entity();
+		assert_eq!(ecs.get_capacity(), 2);
+	}
+}
@@ -0,0 +1,157 @@
+use crate::component::ComponentID;
+use crate::{Access, Query, QueryIter, QueryMut, QueryMutIter, ECS};
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use hashbrown::HashMap;
+
+/// Access to an `ECS` checked per Component at runtime instead of by borrowing the whole world, so queries over
+/// different Components can be held at once, e.g. iterating targets mutably inside a loop over turrets.
+/// Overlapping a mutable query with any other query of the same Component panics, like a `RefCell`.
+pub struct WorldCell<'a> {
+	ecs: &'a ECS,
+	/// Live queries per Component, positive for that many readers and -1 for a writer
+	borrows: RefCell<HashMap<ComponentID, isize>>,
+}
+
+impl<'a> WorldCell<'a> {
+	fn new(ecs: &'a mut ECS) -> Self {
+		ecs.flush();
+		// Queries only borrow the cell, so every column must already be writable without copying
+		let ids: alloc::vec::Vec<_> = ecs.components.entries().map(|(id, _)| id).collect();
+		ecs.unshare(&ids);
+		WorldCell { ecs, borrows: RefCell::new(HashMap::new()) }
+	}
+
+	/// Panics if another live query of this cell writes any Component of `Q`
+	pub fn query<Q: Query>(&self) -> CellQuery<'_, Q> {
+		let access = Access::of_query::<Q>();
+		self.acquire(&access);
+		CellQuery { cell: self, access, _marker: PhantomData }
+	}
+
+	/// Panics if another live query of this cell touches any Component of `Q`
+	pub fn query_mut<Q: QueryMut>(&self) -> CellQueryMut<'_, Q> {
+		let access = Access::of_query_mut::<Q>();
+		self.acquire(&access);
+		CellQueryMut { cell: self, access, _marker: PhantomData }
+	}
+
+	fn acquire(&self, access: &Access) {
+		let mut borrows = self.borrows.borrow_mut();
+		for id in access.reads() {
+			let count = borrows.get(id).copied().unwrap_or(0);
+			let conflict = if access.can_write(*id) { count != 0 } else { count < 0 };
+			assert!(!conflict, "Cannot Query a Component mutably alongside another Query for it");
+		}
+		for id in access.reads() {
+			let count = borrows.entry(*id).or_insert(0);
+			*count = if access.can_write(*id) { -1 } else { *count + 1 };
+		}
+	}
+
+	fn release(&self, access: &Access) {
+		let mut borrows = self.borrows.borrow_mut();
+		for id in access.reads() {
+			let count = borrows.get_mut(id).unwrap();
+			*count = if access.can_write(*id) { 0 } else { *count - 1 };
+		}
+	}
+}
+
+/// Shared query of a `WorldCell`, holding its Components until dropped.
+/// Iterators borrow it, so nothing they yield can outlive the hold.
+pub struct CellQuery<'c, Q: Query> {
+	cell: &'c WorldCell<'c>,
+	access: Access,
+	_marker: PhantomData<fn() -> Q>,
+}
+
+impl<Q: Query> CellQuery<'_, Q> {
+	pub fn iter(&self) -> QueryIter<'_, Q> {
+		let ecs: &ECS = self.cell.ecs;
+		QueryIter::new(ecs)
+	}
+}
+
+impl<Q: Query> Drop for CellQuery<'_, Q> {
+	fn drop(&mut self) {
+		self.cell.release(&self.access);
+	}
+}
+
+/// Mutable query of a `WorldCell`, holding its Components until dropped
+pub struct CellQueryMut<'c, Q: QueryMut> {
+	cell: &'c WorldCell<'c>,
+	access: Access,
+	_marker: PhantomData<fn() -> Q>,
+}
+
+impl<Q: QueryMut> CellQueryMut<'_, Q> {
+	/// One iterator at a time, since each yields mutable references
+	pub fn iter(&mut self) -> QueryMutIter<'_, Q> {
+		// The flags this holds keep every other query of the cell off these columns, which are no longer shared
+		unsafe { QueryMutIter::new_unchecked(self.cell.ecs) }
+	}
+}
+
+impl<Q: QueryMut> Drop for CellQueryMut<'_, Q> {
+	fn drop(&mut self) {
+		self.cell.release(&self.access);
+	}
+}
+
+impl ECS {
+	/// Borrow the world once to hold several queries over disjoint Components, see `WorldCell`.
+	/// Stops sharing every column with forks up front.
+	pub fn world_cell(&mut self) -> WorldCell<'_> { WorldCell::new(self) }
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+
+	struct Turret(u32);
+	impl Component for Turret {}
+	struct Health(u32);
+	impl Component for Health {}
+
+	#[test]
+	fn disjoint() {
+		let mut ecs = ECS::new(4);
+		crate::register_components!(ecs, [Turret, Health]);
+		for index in 0..4 {
+			let entity = ecs.create_entity().unwrap();
+			if index < 2 {
+				ecs.add_component(&entity, Turret(index + 1));
+			} else {
+				ecs.add_component(&entity, Health(10));
+			}
+		}
+
+		let cell = ecs.world_cell();
+		let mut turrets = cell.query_mut::<Turret>();
+		let mut targets = cell.query_mut::<Health>();
+		for (_, turret) in turrets.iter() {
+			for (_, health) in targets.iter() {
+				health.0 -= turret.0;
+				turret.0 += 1;
+			}
+		}
+		drop(turrets);
+		let (first, second) = (cell.query::<Turret>(), cell.query::<Turret>());
+		assert_eq!(first.iter().count() + second.iter().count(), 4);
+		drop(targets);
+		let healths: alloc::vec::Vec<_> = cell.query::<Health>().iter().map(|(_, health)| health.0).collect();
+		assert_eq!(healths, [10 - 1 - 2, 10 - 2 - 3]);
+	}
+
+	#[test]
+	#[should_panic(expected = "mutably alongside")]
+	fn overlapping() {
+		let mut ecs = ECS::new(1);
+		ecs.register::<Health>();
+		let cell = ecs.world_cell();
+		let _reading = cell.query::<Health>();
+		cell.query_mut::<Health>();
+	}
+}
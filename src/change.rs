@@ -0,0 +1,132 @@
+use crate::component::ComponentID;
+use crate::storage::ComponentArray;
+use crate::{Component, EntityID, ECS};
+use core::marker::PhantomData;
+
+/// How far a reader has seen, see `ECS::query_changed`. The default has seen nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChangeCursor(u64);
+
+/// `(EntityID, &C)` for every `C` written after a cursor, in index order
+pub struct ChangedIter<'a, C: Component> {
+	ecs: &'a ECS,
+	array: Option<&'a ComponentArray>,
+	since: u64,
+	index: usize,
+	phantom: PhantomData<fn() -> C>,
+}
+
+impl<'a, C: Component> Iterator for ChangedIter<'a, C> {
+	type Item = (EntityID, &'a C);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let array = self.array?;
+		while self.index < self.ecs.capacity {
+			let index = self.index;
+			self.index += 1;
+			if self.ecs.components.get_tick(array, index) <= self.since { continue; }
+			if let (Some(entity), Some(component)) = (self.ecs.get_index(index), unsafe { array.get::<C>(index) }) {
+				return Some((entity, component));
+			}
+		}
+		None
+	}
+}
+
+impl ECS {
+	/// A cursor that has seen everything written so far
+	pub fn change_cursor(&self) -> ChangeCursor { ChangeCursor(self.change_tick) }
+
+	/// A cursor that has seen everything written before the current frame, see `advance_tick`
+	pub fn frame_cursor(&self) -> ChangeCursor { ChangeCursor(self.frame_change_tick) }
+
+	/// Every `C` inserted, overwritten, or borrowed mutably since `since` was last advanced, mutable queries
+	/// included, then advance it to now. Each reader keeps its own cursor, so render extraction and network
+	/// sync don't consume each other's changes.
+	pub fn query_changed<C: Component>(&self, since: &mut ChangeCursor) -> ChangedIter<'_, C> {
+		let seen = core::mem::replace(since, self.change_cursor());
+		ChangedIter { ecs: self, array: self.components.get_array::<C>(), since: seen.0, index: 0, phantom: PhantomData }
+	}
+
+	/// Report a write to `C` on `entity` to replication, watches, and its on-set hook. Mutable queries only
+	/// stamp change ticks, so writes through them reach those three once flagged here. `add_component`,
+	/// `remove_component` and `get_component_mut` already report themselves.
+	pub fn mark_changed<C: Component>(&mut self, entity: &EntityID) {
+		if !self.is_valid(entity) { return; }
+		self.on_change(ComponentID::of::<C>(), entity.index);
+		if self.has_at::<C>(entity.index) {
+			self.set_hooks.mark(ComponentID::of::<C>(), entity.index);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{ChangeCursor, Component, ECS};
+
+	#[derive(Clone)]
+	struct Position(u32);
+	impl Component for Position {}
+
+	#[test]
+	fn query_changed() {
+		let mut ecs = ECS::new(8);
+		ecs.register_cloneable::<Position>();
+		let entities: alloc::vec::Vec<_> = (0..4).map(|value| {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(value));
+			entity
+		}).collect();
+
+		let mut render = ChangeCursor::default();
+		let mut network = ecs.change_cursor();
+		assert_eq!(ecs.query_changed::<Position>(&mut render).count(), 4);
+		assert_eq!(ecs.query_changed::<Position>(&mut render).count(), 0);
+		assert_eq!(ecs.query_changed::<Position>(&mut network).count(), 0);
+
+		let checkpoint = ecs.save_checkpoint();
+		ecs.get_component_mut::<Position>(&entities[2]).unwrap().0 = 20;
+		ecs.add_component(&entities[0], Position(10));
+		ecs.destroy_entity(entities[3]);
+		let changed: alloc::vec::Vec<_> = ecs.query_changed::<Position>(&mut render).map(|(_, position)| position.0).collect();
+		assert_eq!(changed, [10, 20]);
+		assert_eq!(ecs.query_changed::<Position>(&mut network).count(), 2);
+
+		ecs.rollback_to(checkpoint);
+		assert_eq!(ecs.query_changed::<Position>(&mut render).count(), 4);
+	}
+
+	#[test]
+	fn frame_cursor() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Position>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Position(1));
+		ecs.advance_tick();
+		assert_eq!(ecs.query_changed::<Position>(&mut ecs.frame_cursor()).count(), 0);
+		ecs.get_component_mut::<Position>(&entity).unwrap().0 = 2;
+		assert_eq!(ecs.query_changed::<Position>(&mut ecs.frame_cursor()).count(), 1);
+	}
+
+	#[test]
+	fn mutable_queries() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<Position>();
+		for value in 0..4 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(value));
+		}
+
+		let mut cursor = ecs.change_cursor();
+		ecs.query_mut::<Position>().filter(|(_, position)| position.0 % 2 == 0).for_each(|(_, position)| position.0 += 10);
+		let changed: alloc::vec::Vec<_> = ecs.query_changed::<Position>(&mut cursor).map(|(_, position)| position.0).collect();
+		assert_eq!(changed, [10, 1, 12, 3]);
+
+		ecs.query_mut::<Position>().runs().for_each(|(_, run)| run[0].0 = 0);
+		assert_eq!(ecs.query_changed::<Position>(&mut cursor).count(), 4);
+		let cell = ecs.world_cell();
+		cell.query_mut::<Position>().iter().take(1).for_each(|(_, position)| position.0 = 5);
+		drop(cell);
+		assert_eq!(ecs.query_changed::<Position>(&mut cursor).count(), 1);
+	}
+}
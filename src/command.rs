@@ -0,0 +1,153 @@
+use crate::{Component, EntityID, ECS};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+type CommandFn = Box<dyn FnOnce(&mut ECS) + Send>;
+
+struct CommandNode {
+	command: CommandFn,
+	next: *mut CommandNode,
+}
+
+/// Lock-free stack of pending commands. Nodes are only ever pushed one at a time
+/// and taken all at once, so there is no ABA hazard.
+pub(crate) struct CommandQueue {
+	head: AtomicPtr<CommandNode>,
+}
+
+impl CommandQueue {
+	pub(crate) fn new() -> Self {
+		CommandQueue { head: AtomicPtr::new(null_mut()) }
+	}
+
+	fn push(&self, command: CommandFn) {
+		let node = Box::into_raw(Box::new(CommandNode { command, next: null_mut() }));
+		let mut head = self.head.load(Ordering::Relaxed);
+		loop {
+			unsafe { (*node).next = head };
+			match self.head.compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed) {
+				Ok(_) => return,
+				Err(current) => head = current,
+			}
+		}
+	}
+
+	/// Take every pending command, oldest first
+	pub(crate) fn take(&self) -> Vec<CommandFn> {
+		let mut node = self.head.swap(null_mut(), Ordering::Acquire);
+		let mut commands = Vec::new();
+		while !node.is_null() {
+			let boxed = unsafe { Box::from_raw(node) };
+			node = boxed.next;
+			commands.push(boxed.command);
+		}
+		commands.reverse();
+		commands
+	}
+}
+
+impl Drop for CommandQueue {
+	fn drop(&mut self) {
+		drop(self.take());
+	}
+}
+
+/// Handle for recording structural changes from any thread, applied later by `ECS::apply_commands`.
+#[derive(Clone)]
+pub struct Commands {
+	queue: Arc<CommandQueue>,
+}
+
+impl Commands {
+	pub(crate) fn new(queue: Arc<CommandQueue>) -> Self {
+		Commands { queue }
+	}
+
+	pub fn add<F: FnOnce(&mut ECS) + Send + 'static>(&self, command: F) {
+		self.queue.push(Box::new(command));
+	}
+
+	/// Create an entity when applied, `build` runs immediately after with the new id
+	pub fn spawn<F: FnOnce(&mut ECS, &EntityID) + Send + 'static>(&self, build: F) {
+		self.add(move |ecs| {
+			if let Some(entity) = ecs.create_entity() {
+				build(ecs, &entity);
+			}
+		});
+	}
+
+	pub fn destroy(&self, entity: EntityID) {
+		self.add(move |ecs| ecs.destroy_entity(entity));
+	}
+
+	pub fn insert<C: Component + Send>(&self, entity: EntityID, component: C) {
+		self.add(move |ecs| ecs.add_component(&entity, component));
+	}
+
+	pub fn remove<C: Component>(&self, entity: EntityID) {
+		self.add(move |ecs| ecs.remove_component::<C>(&entity));
+	}
+}
+
+#[cfg(test)]
+mod test {
+	extern crate std;
+	use crate::{Component, ECS};
+	use std::vec::Vec;
+
+	struct TestComponent(usize);
+	impl Component for TestComponent {}
+
+	#[test]
+	fn threaded() {
+		const THREADS: usize = 8;
+		const PER_THREAD: usize = 16;
+
+		let mut ecs = ECS::new(THREADS * PER_THREAD);
+		ecs.register::<TestComponent>();
+
+		let handles: Vec<_> = (0..THREADS).map(|thread| {
+			let commands = ecs.commands();
+			std::thread::spawn(move || {
+				for index in 0..PER_THREAD {
+					let value = thread * PER_THREAD + index;
+					commands.spawn(move |ecs, entity| ecs.add_component(entity, TestComponent(value)));
+				}
+			})
+		}).collect();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+
+		assert_eq!(ecs.get_entity_count(), 0);
+		ecs.apply_commands();
+		assert_eq!(ecs.get_entity_count(), THREADS * PER_THREAD);
+
+		let mut sum = 0;
+		for (_, component) in ecs.query::<TestComponent>() {
+			sum += component.0;
+		}
+		assert_eq!(sum, (0..THREADS * PER_THREAD).sum());
+	}
+
+	#[test]
+	fn ordering() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<TestComponent>();
+		let entity = ecs.create_entity().unwrap();
+
+		let commands = ecs.commands();
+		commands.insert(entity, TestComponent(1));
+		commands.remove::<TestComponent>(entity);
+		commands.insert(entity, TestComponent(2));
+		ecs.apply_commands();
+		assert_eq!(ecs.get_component::<TestComponent>(&entity).unwrap().0, 2);
+
+		commands.destroy(entity);
+		ecs.apply_commands();
+		assert!(!ecs.is_valid(&entity));
+	}
+}
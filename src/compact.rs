@@ -0,0 +1,184 @@
+use crate::ecs::Entity;
+use crate::{EntityID, GenerationPolicy, WeakEntity, ECS};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+pub(crate) type RemapListener = Box<dyn FnMut(&[(usize, usize)])>;
+
+/// Handle to a listener registered with `add_remap_listener`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RemapListenerID(usize);
+
+impl ECS {
+	/// Call `listener` with every `(old_index, new_index)` pair whenever entities are relocated, so
+	/// structures kept in parallel with the index space can follow. Called once per relocation pass,
+	/// after the world has been updated.
+	pub fn add_remap_listener<F: FnMut(&[(usize, usize)]) + 'static>(&mut self, listener: F) -> RemapListenerID {
+		let listener = Some(Box::new(listener) as RemapListener);
+		let listeners = &mut self.remap_listeners;
+		match listeners.iter().position(|slot| slot.is_none()) {
+			Some(index) => {
+				listeners[index] = listener;
+				RemapListenerID(index)
+			},
+			None => {
+				listeners.push(listener);
+				RemapListenerID(listeners.len() - 1)
+			},
+		}
+	}
+
+	pub fn remove_remap_listener(&mut self, listener: RemapListenerID) {
+		if let Some(slot) = self.remap_listeners.get_mut(listener.0) {
+			*slot = None;
+		}
+	}
+
+	/// Whether a dead slot may be handed out again, as opposed to retired
+	fn is_reusable(&self, index: usize) -> bool {
+		let entity = self.entities[index];
+		!entity.alive && (entity.generation != usize::MAX || self.get_generation_policy() == GenerationPolicy::Wrap)
+	}
+
+	/// Move alive entities from the back of the index space into free slots at the front, so queries stop
+	/// walking long dead stretches after heavy churn. Each moved entity gets a new `EntityID` in a new
+	/// generation, `remap` is called with `(old, new)` for it, `Relation` targets are rewritten, and
+	/// every remap listener is notified.
+	/// Other stored ids go stale. Returns how many entities moved.
+	pub fn compact(&mut self, mut remap: impl FnMut(EntityID, EntityID)) -> usize {
+		self.flush();
+		let mut moved = Vec::new();
+		let (mut low, mut high) = (0, self.capacity);
+		loop {
+			while low < high && !self.is_reusable(low) { low += 1; }
+			while high > low && !self.entities[high - 1].alive { high -= 1; }
+			if high <= low + 1 { break; }
+			high -= 1;
+			moved.push(self.relocate(high, low));
+			low += 1;
+		}
+		if moved.is_empty() { return 0; }
+		debug!(moved = moved.len(), "compact");
+
+		self.free = (0..self.capacity).rev().filter(|index| self.is_reusable(*index)).collect();
+		*self.free_cursor.get_mut() = self.free.len() as isize;
+		self.finish_relocation(&moved);
+		for (old, new) in &moved {
+			remap(*old, *new);
+		}
+		moved.len()
+	}
+
+	/// Bring everything derived from entity indices up to date once `moved` have been relocated
+	fn finish_relocation(&mut self, moved: &[(EntityID, EntityID)]) {
+		let entities = &self.entities;
+		self.groups.rebuild(&mut self.components, self.capacity, |index| entities[index].alive);
+		self.rebuild_indexes();
+		let moves: HashMap<WeakEntity, EntityID> = moved.iter().map(|(old, new)| (old.downgrade(), *new)).collect();
+		self.retarget_relations(&moves);
+		self.watches.remap(&moves);
+		let indices: Vec<(usize, usize)> = moved.iter().map(|(old, new)| (old.index, new.index)).collect();
+		for listener in self.remap_listeners.iter_mut().flatten() {
+			listener(&indices);
+		}
+	}
+
+	fn relocate(&mut self, from: usize, to: usize) -> (EntityID, EntityID) {
+		let old = self.get_index(from).unwrap();
+		let generation = self.entities[to].generation.wrapping_add(1);
+		self.entities[to] = Entity { alive: true, generation };
+		self.entities[from].alive = false;
+		let new = self.get_index(to).unwrap();
+		trace!(from, to, generation, "relocate entity");
+
+		self.components.swap_index(from, to);
+		self.guids.move_index(from, to);
+		self.set_hooks.swap_index(from, to);
+		self.tags.swap(from, to);
+		self.replication.on_destroy(from, old.generation);
+		self.replication.on_create(to, generation);
+		for id in self.components.arrays_filled(to) {
+			self.replication.on_change(id, to);
+		}
+		(old, new)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, Guid, Relation, RelationPolicy, ECS};
+
+	struct Position(usize);
+	impl Component for Position {}
+	struct ChildOf;
+
+	#[test]
+	fn compact() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<Position>();
+		ecs.register_relation::<ChildOf>(RelationPolicy::Remove);
+		ecs.create_group::<Position>();
+		ecs.enable_guids(1);
+
+		let entities: alloc::vec::Vec<_> = (0..8).map(|index| {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(index));
+			entity
+		}).collect();
+		for index in [0, 2, 3, 5] {
+			ecs.destroy_entity(entities[index]);
+		}
+		let parent = entities[7];
+		let guid: Guid = ecs.get_guid(&parent).unwrap();
+		ecs.add_component(&entities[6], Relation::new(parent, ChildOf));
+
+		let mut remapped = alloc::vec::Vec::new();
+		assert_eq!(ecs.compact(|old, new| remapped.push((old.downgrade(), new))), 3);
+		assert_eq!(remapped.len(), 3);
+		assert!(!ecs.is_valid(&parent) && !ecs.is_valid(&entities[6]));
+		assert_eq!(ecs.get_entity_count(), 4);
+
+		let mut indices: alloc::vec::Vec<_> = ecs.query::<Position>().map(|(entity, position)| (entity.index, position.0)).collect();
+		indices.sort();
+		assert_eq!(indices, [(0, 7), (1, 1), (2, 6), (3, 4)]);
+		assert_eq!(ecs.query_group::<Position>().count(), 4);
+
+		let new_parent = ecs.find_by_guid(guid).unwrap();
+		assert_eq!(new_parent.index, 0);
+		let (_, relation) = ecs.query::<Relation<ChildOf>>().next().unwrap();
+		assert!(relation.target() == new_parent);
+
+		assert_eq!(ecs.create_entity().unwrap().index, 4);
+		assert_eq!(ecs.compact(|_, _| unreachable!()), 0);
+	}
+
+	#[test]
+	fn listeners() {
+		use std::sync::{Arc, Mutex};
+		extern crate std;
+
+		let mut ecs = ECS::new(8);
+		let entities: alloc::vec::Vec<_> = (0..4).map(|_| ecs.create_entity().unwrap()).collect();
+		let mut buffer: alloc::vec::Vec<usize> = (0..8).collect();
+		let shared = Arc::new(Mutex::new(alloc::vec::Vec::new()));
+		let pairs = shared.clone();
+		let listener = ecs.add_remap_listener(move |moved| pairs.lock().unwrap().extend_from_slice(moved));
+		let ignored = ecs.add_remap_listener(|_| unreachable!());
+		ecs.remove_remap_listener(ignored);
+
+		ecs.destroy_entity(entities[0]);
+		ecs.destroy_entity(entities[1]);
+		ecs.compact(|_, _| {});
+		for (old, new) in shared.lock().unwrap().iter() {
+			buffer[*new] = buffer[*old];
+		}
+		assert_eq!(*shared.lock().unwrap(), [(3, 0), (2, 1)]);
+		assert_eq!(buffer[..2], [3, 2]);
+
+		ecs.remove_remap_listener(listener);
+		ecs.destroy_entity(ecs.get_index(0).unwrap());
+		ecs.compact(|_, _| {});
+		assert_eq!(shared.lock().unwrap().len(), 2);
+	}
+}
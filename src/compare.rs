@@ -0,0 +1,135 @@
+use crate::component::{ComponentID, ComponentInfo, EqFn};
+use crate::{Component, ECS};
+use alloc::vec::Vec;
+
+/// First way two worlds differ, see `ECS::compare`. Entities are identified by index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorldDifference {
+	/// Alive in only one world, or alive in both with different generations
+	Entity(usize),
+	/// Component registered in only one world
+	Registered(&'static str),
+	/// Entity holds the Component in only one world
+	Presence(usize, &'static str),
+	/// Entity holds values of the Component that compare unequal
+	Value(usize, &'static str),
+}
+
+impl ECS {
+	/// Let `compare` check values of `C`, registering it first if needed. Existing values are kept.
+	pub fn register_eq<C: Component + PartialEq>(&mut self) {
+		if !self.components.set_eq(ComponentID::of::<C>(), ComponentInfo::eq::<C>()) {
+			let mut info = ComponentInfo::new::<C>();
+			info.eq = Some(ComponentInfo::eq::<C>());
+			self.components.register(ComponentID::of::<C>(), info, self.capacity);
+		}
+	}
+
+	fn slot(&self, index: usize) -> Option<usize> {
+		self.get_index(index).map(|entity| entity.generation)
+	}
+
+	/// True if every entity alive in either world holds equal `C`s in both, or none in both
+	pub fn eq_components<C: Component + PartialEq>(&self, other: &ECS) -> bool {
+		(0..self.capacity.max(other.capacity)).all(|index| {
+			let value = self.slot(index).and_then(|_| self.get_at::<C>(index));
+			let other_value = other.slot(index).and_then(|_| other.get_at::<C>(index));
+			value == other_value
+		})
+	}
+
+	/// Check that both worlds have the same entities at the same indices and generations, the same
+	/// Components registered and held by each, and equal values for those registered with `register_eq`.
+	/// Capacities may differ.
+	pub fn compare(&self, other: &ECS) -> Result<(), WorldDifference> {
+		let capacity = self.capacity.max(other.capacity);
+		if let Some(index) = (0..capacity).find(|index| self.slot(*index) != other.slot(*index)) {
+			return Err(WorldDifference::Entity(index));
+		}
+
+		// Matched by id, the name only orders the check and reports the difference
+		let mut arrays: Vec<_> = self.components.entries().collect();
+		arrays.sort_by_key(|(_, array)| (array.info().name)());
+		for (id, array) in other.components.entries() {
+			if self.components.get_array_by_id(id).is_none() {
+				return Err(WorldDifference::Registered((array.info().name)()));
+			}
+		}
+		for (id, array) in arrays {
+			let name = (array.info().name)();
+			let Some(other_array) = other.components.get_array_by_id(id) else {
+				return Err(WorldDifference::Registered(name));
+			};
+			let eq: Option<EqFn> = array.info().eq.or(other_array.info().eq);
+			for index in (0..capacity).filter(|index| self.slot(*index).is_some()) {
+				match (array.get_ptr(index), other_array.get_ptr(index)) {
+					(None, None) => {},
+					(Some(value), Some(other_value)) => {
+						if eq.is_some_and(|eq| !unsafe { eq(value, other_value) }) {
+							return Err(WorldDifference::Value(index, name));
+						}
+					},
+					_ => return Err(WorldDifference::Presence(index, name)),
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Panic describing the first difference between `a` and `b`, for tests asserting two simulations
+/// produced the same world
+#[track_caller]
+pub fn assert_worlds_equal(a: &ECS, b: &ECS) {
+	if let Err(difference) = a.compare(b) {
+		panic!("worlds differ: {:?}", difference);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{assert_worlds_equal, Component, WorldDifference, ECS};
+
+	#[derive(Clone, PartialEq, Debug)]
+	struct Position(i32);
+	impl Component for Position {}
+	#[derive(Clone)]
+	struct Tag;
+	impl Component for Tag {}
+
+	fn simulate(steps: i32) -> ECS {
+		let mut ecs = ECS::new(4);
+		ecs.register_eq::<Position>();
+		ecs.register::<Tag>();
+		for index in 0..3 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(index * steps));
+			if index == 1 {
+				ecs.add_component(&entity, Tag);
+			}
+		}
+		ecs
+	}
+
+	#[test]
+	fn compare() {
+		let (a, mut b) = (simulate(2), simulate(2));
+		assert_worlds_equal(&a, &b);
+		assert!(a.eq_components::<Position>(&b));
+		assert!(!a.eq_components::<Position>(&simulate(3)));
+		assert_eq!(a.compare(&simulate(3)), Err(WorldDifference::Value(1, core::any::type_name::<Position>())));
+
+		let entity = b.get_index(2).unwrap();
+		b.add_component(&entity, Tag);
+		assert_eq!(a.compare(&b), Err(WorldDifference::Presence(2, core::any::type_name::<Tag>())));
+		b.destroy_entity(entity);
+		assert_eq!(a.compare(&b), Err(WorldDifference::Entity(2)));
+		assert!(!a.eq_components::<Position>(&b));
+	}
+
+	#[test]
+	#[should_panic]
+	fn unequal() {
+		assert_worlds_equal(&simulate(1), &ECS::new(4));
+	}
+}
@@ -1,35 +1,183 @@
 
+use crate::backend::{new_storage, ComponentStorage, StorageFn};
 use core::alloc::Layout;
+use core::fmt;
 use hashbrown::HashMap;
-use crate::storage::Slot;
 
 pub trait Component: Sized + 'static {}
 
+/// Construction with access to the ECS, for Components built by `ECS::add_from_world`.
+/// Anything `Default` already implements it.
+pub trait FromWorld {
+	fn from_world(ecs: &mut crate::ECS) -> Self;
+}
+
+impl<T: Default> FromWorld for T {
+	fn from_world(_: &mut crate::ECS) -> Self {
+		T::default()
+	}
+}
+
 pub(crate) type ComponentID = core::any::TypeId;
 
+/// How a Component type is stored and what it supports, as registered
 #[derive(Clone, Copy)]
-pub(crate) struct ComponentInfo {
+pub struct ComponentInfo {
 	pub(crate) layout: Layout,
+	/// Distance between consecutive values in a column
 	pub(crate) stride: usize,
 	pub(crate) drop: unsafe fn(*mut u8),
+	/// Clone from the first pointer into the uninitialized second, for Components registered as cloneable
+	pub(crate) clone: Option<unsafe fn(*const u8, *mut u8)>,
+	pub(crate) name: fn() -> &'static str,
+	/// Identifies the Component in `schema_hash` in place of `name`, see `register_stable_name`
+	pub(crate) stable_name: Option<&'static str>,
+	/// Format the pointed to value, for Components registered with `register_debug`
+	pub(crate) debug: Option<DebugFn>,
+	/// Compare two pointed to values, for Components registered with `register_eq`
+	pub(crate) eq: Option<EqFn>,
+	/// `Reflect::field_names`, for Components registered with `register_reflect`
+	pub(crate) fields: Option<fn() -> &'static [&'static str]>,
+	/// Store values in pages allocated on first use rather than one block spanning the capacity
+	pub(crate) paged: bool,
+	/// Builds the column's `ComponentStorage`, pages if `None`
+	pub(crate) storage: Option<StorageFn>,
+}
+
+pub(crate) type DebugFn = unsafe fn(*const u8, &mut fmt::Formatter<'_>) -> fmt::Result;
+pub(crate) type EqFn = unsafe fn(*const u8, *const u8) -> bool;
+
+unsafe fn eq_component<C: PartialEq>(a: *const u8, b: *const u8) -> bool {
+	*(a as *const C) == *(b as *const C)
+}
+
+unsafe fn debug_component<C: fmt::Debug>(source: *const u8, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+	(*(source as *const C)).fmt(f)
+}
+
+unsafe fn drop_component<C>(pointer: *mut u8) {
+	core::ptr::drop_in_place(pointer as *mut C);
+}
+
+unsafe fn clone_component<C: Clone>(source: *const u8, destination: *mut u8) {
+	(destination as *mut C).write((*(source as *const C)).clone());
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+pub(crate) fn fnv(hash: u64, bytes: &[u8]) -> u64 {
+	bytes.iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// FNV-1a over one Component's stable name and layout. Components without a stable name are hashed by
+/// `type_name`, which isn't guaranteed to stay the same across compiler versions or after moving the type.
+pub(crate) fn component_hash(info: &ComponentInfo) -> u64 {
+	let hash = fnv(FNV_OFFSET, info.stable_name.unwrap_or_else(info.name).as_bytes());
+	let hash = fnv(hash, &(info.layout.size() as u64).to_le_bytes());
+	fnv(hash, &(info.layout.align() as u64).to_le_bytes())
+}
+
+/// Combine per Component hashes independently of their order
+pub(crate) fn combine_hashes(mut hashes: alloc::vec::Vec<u64>) -> u64 {
+	hashes.sort_unstable();
+	hashes.iter().fold(FNV_OFFSET, |hash, component| fnv(hash, &component.to_le_bytes()))
+}
+
+/// `component_hash` of every Component, independent of registration order
+pub(crate) fn schema_hash<'a>(infos: impl Iterator<Item = &'a ComponentInfo>) -> u64 {
+	combine_hashes(infos.map(component_hash).collect())
 }
 
 impl ComponentInfo {
-	pub(crate) const fn new<C: Component>() -> Self {
-		let layout = Layout::new::<Slot<C>>();
-		let stride = layout.size() + (layout.size() % layout.align());
-		let drop = unsafe { core::mem::transmute(core::ptr::drop_in_place::<Slot<C>> as *mut u8) };
-		ComponentInfo { layout, stride, drop }
+	pub const fn new<C: Component>() -> Self {
+		let layout = Layout::new::<C>();
+		let stride = layout.pad_to_align().size();
+		ComponentInfo { layout, stride, drop: drop_component::<C>, clone: None, name: core::any::type_name::<C>, stable_name: None, debug: None, eq: None, fields: None, paged: false, storage: None }
+	}
+
+	pub const fn cloneable<C: Component + Clone>() -> Self {
+		let mut info = ComponentInfo::new::<C>();
+		info.clone = Some(clone_component::<C>);
+		info
+	}
+
+	/// Same Component with its pages allocated as values arrive and freed once empty, rather than all up front,
+	/// so a column mostly empty allocates mostly nothing
+	pub const fn paged(mut self) -> Self {
+		self.paged = true;
+		self
+	}
+
+	/// Same Component with its values kept in `S` rather than the default pages
+	pub const fn with_storage<S: ComponentStorage>(mut self) -> Self {
+		self.storage = Some(new_storage::<S>);
+		self
+	}
+
+	pub const fn layout(&self) -> Layout { self.layout }
+
+	pub const fn stride(&self) -> usize { self.stride }
+
+	pub fn name(&self) -> &'static str { (self.name)() }
+
+	/// The name given to `register_stable_name`, if any
+	pub const fn stable_name(&self) -> Option<&'static str> { self.stable_name }
+
+	pub const fn is_cloneable(&self) -> bool { self.clone.is_some() }
+
+	pub const fn is_debuggable(&self) -> bool { self.debug.is_some() }
+
+	pub const fn is_comparable(&self) -> bool { self.eq.is_some() }
+
+	pub const fn is_paged(&self) -> bool { self.paged }
+
+	/// Whether values live in a `ComponentStorage` given at registration rather than the default pages
+	pub const fn has_custom_storage(&self) -> bool { self.storage.is_some() }
+
+	/// Empty unless registered with `register_reflect`
+	pub fn field_names(&self) -> &'static [&'static str] {
+		self.fields.map_or(&[], |fields| fields())
+	}
+
+	pub(crate) const fn debug<C: Component + fmt::Debug>() -> DebugFn {
+		debug_component::<C>
+	}
+
+	pub(crate) const fn eq<C: Component + PartialEq>() -> EqFn {
+		eq_component::<C>
 	}
 }
 
+#[derive(Default)]
 pub struct ComponentRegistry {
 	pub(crate) components: HashMap<ComponentID, ComponentInfo>,
+	pub(crate) names: crate::named::NamedComponents,
+	pub(crate) scene: HashMap<alloc::string::String, (ComponentID, crate::scene::SceneFn)>,
+	pub(crate) migrations: HashMap<(ComponentID, u32), crate::scene::MigrateFn>,
+}
+
+impl fmt::Debug for ComponentInfo {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ComponentInfo")
+			.field("name", &self.name())
+			.field("stable_name", &self.stable_name)
+			.field("size", &self.layout.size())
+			.field("align", &self.layout.align())
+			.field("stride", &self.stride)
+			.field("cloneable", &self.is_cloneable())
+			.field("paged", &self.paged)
+			.field("custom_storage", &self.has_custom_storage())
+			.finish()
+	}
 }
 
 impl ComponentRegistry {
 	pub fn new() -> Self {
-		ComponentRegistry { components: HashMap::new() }
+		ComponentRegistry::default()
+	}
+
+	pub fn get_info<C: Component>(&self) -> Option<&ComponentInfo> {
+		self.components.get(&ComponentID::of::<C>())
 	}
 
 	pub fn register<C: Component>(&mut self) {
@@ -37,4 +185,215 @@ impl ComponentRegistry {
 		let component_info = ComponentInfo::new::<C>();
 		self.components.insert(id, component_info);
 	}
+
+	/// Register a Component that snapshots and world copies can duplicate
+	pub fn register_cloneable<C: Component + Clone>(&mut self) {
+		self.components.insert(ComponentID::of::<C>(), ComponentInfo::cloneable::<C>());
+	}
+
+	/// Register a Component held by few entities, see `ComponentInfo::paged`
+	pub fn register_paged<C: Component>(&mut self) {
+		self.components.insert(ComponentID::of::<C>(), ComponentInfo::new::<C>().paged());
+	}
+
+	/// Register a Component whose values live in `S`, see `ComponentStorage`
+	pub fn register_with_storage<C: Component, S: ComponentStorage>(&mut self) {
+		self.components.insert(ComponentID::of::<C>(), ComponentInfo::new::<C>().with_storage::<S>());
+	}
+
+	/// Let `ECS::dump` print values of `C`, registering it first if needed
+	pub fn register_debug<C: Component + fmt::Debug>(&mut self) {
+		let info = self.components.entry(ComponentID::of::<C>()).or_insert(ComponentInfo::new::<C>());
+		info.debug = Some(ComponentInfo::debug::<C>());
+	}
+
+	/// Let `ECS::compare` check values of `C`, registering it first if needed
+	pub fn register_eq<C: Component + PartialEq>(&mut self) {
+		let info = self.components.entry(ComponentID::of::<C>()).or_insert(ComponentInfo::new::<C>());
+		info.eq = Some(ComponentInfo::eq::<C>());
+	}
+
+	/// Record the field names of `C` for `export_schema`, registering it first if needed
+	pub fn register_reflect<C: Component + crate::Reflect>(&mut self) {
+		let info = self.components.entry(ComponentID::of::<C>()).or_insert(ComponentInfo::new::<C>());
+		info.fields = Some(C::field_names);
+	}
+
+	/// Identify `C` as `name` in `schema_hash`, registering it first if needed. Give one to every Component whose
+	/// data is saved or sent, so the hash survives renaming or moving the type.
+	pub fn register_stable_name<C: Component>(&mut self, name: &'static str) {
+		let info = self.components.entry(ComponentID::of::<C>()).or_insert(ComponentInfo::new::<C>());
+		info.stable_name = Some(name);
+	}
+
+	/// Forget `C`, along with any names, scene names and migrations it was registered with. Returns false if it wasn't registered.
+	/// Worlds already built from this registry keep it, see `ECS::unregister`.
+	pub fn unregister<C: Component>(&mut self) -> bool {
+		let id = ComponentID::of::<C>();
+		self.names.forget(id);
+		self.scene.retain(|_, (component, _)| *component != id);
+		self.migrations.retain(|(component, _), _| *component != id);
+		self.components.remove(&id).is_some()
+	}
+
+	/// Identifies the set of registered Components, see `ECS::schema_hash`
+	pub fn schema_hash(&self) -> u64 {
+		schema_hash(self.components.values())
+	}
+
+	pub fn is_registered<C: Component>(&self) -> bool {
+		self.components.contains_key(&ComponentID::of::<C>())
+	}
+
+	pub fn len(&self) -> usize { self.components.len() }
+
+	pub fn is_empty(&self) -> bool { self.components.is_empty() }
+
+	/// Add every Component of `other`, so feature crates can each export a registry for the host to combine.
+	/// Components in both keep any cloning or debug support either side registered.
+	pub fn merge(&mut self, other: &ComponentRegistry) {
+		for (id, info) in &other.components {
+			let merged = self.components.entry(*id).or_insert(*info);
+			merged.clone = merged.clone.or(info.clone);
+			merged.debug = merged.debug.or(info.debug);
+			merged.eq = merged.eq.or(info.eq);
+			merged.stable_name = merged.stable_name.or(info.stable_name);
+			merged.fields = merged.fields.or(info.fields);
+			merged.paged |= info.paged;
+			merged.storage = merged.storage.or(info.storage);
+		}
+		self.names.merge(&other.names);
+		for (name, load) in &other.scene {
+			self.scene.entry(name.clone()).or_insert(*load);
+		}
+		for (key, migrate) in &other.migrations {
+			self.migrations.entry(*key).or_insert_with(|| migrate.clone());
+		}
+	}
+
+	pub fn extend_from<'a>(&mut self, registries: impl IntoIterator<Item = &'a ComponentRegistry>) {
+		for registry in registries {
+			self.merge(registry);
+		}
+	}
+}
+
+/// Register every listed Component on a `ComponentRegistry` or `ECS`, optionally followed by ones that
+/// should also be cloneable.
+/// ```ignore
+/// register_components!(registry, [Position, Velocity], cloneable [Health]);
+/// ```
+#[macro_export]
+macro_rules! register_components {
+	($target:expr, [$($component:ty),* $(,)?] $(, cloneable [$($cloneable:ty),* $(,)?])? $(,)?) => {{
+		let target = &mut $target;
+		$(target.register::<$component>();)*
+		$($(target.register_cloneable::<$cloneable>();)*)?
+	}};
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ComponentRegistry, ECS};
+
+	struct Position(f32);
+	impl Component for Position {}
+	struct Velocity(f32);
+	impl Component for Velocity {}
+	#[derive(Clone)]
+	struct Health(u32);
+	impl Component for Health {}
+
+	#[test]
+	fn register_components() {
+		let mut registry = ComponentRegistry::new();
+		crate::register_components!(registry, [Position, Velocity], cloneable [Health]);
+		let mut ecs = ECS::from_registry(&registry, 1);
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Position(1.0));
+		ecs.add_component(&entity, Velocity(2.0));
+		ecs.add_component(&entity, Health(3));
+		assert_eq!(ecs.get_component::<Health>(&entity).unwrap().0, 3);
+
+		let mut other = ECS::new(1);
+		crate::register_components!(other, [Position, Velocity,]);
+		let entity = other.create_entity().unwrap();
+		other.add_component(&entity, Velocity(4.0));
+		assert_eq!(other.get_component::<Velocity>(&entity).unwrap().0 + ecs.query::<Position>().next().unwrap().1.0, 5.0);
+	}
+
+	#[test]
+	fn merge() {
+		let mut physics = ComponentRegistry::new();
+		crate::register_components!(physics, [Position, Velocity]);
+		let mut combat = ComponentRegistry::new();
+		crate::register_components!(combat, [Position], cloneable [Health]);
+
+		let mut host = ComponentRegistry::new();
+		host.register::<Health>();
+		host.extend_from([&physics, &combat]);
+		assert_eq!(host.len(), 3);
+		assert!(host.is_registered::<Velocity>());
+		assert!(host.components[&crate::component::ComponentID::of::<Health>()].clone.is_some());
+	}
+
+	#[test]
+	fn unregister() {
+		let mut physics = ComponentRegistry::new();
+		crate::register_components!(physics, [Position, Velocity]);
+		assert!(physics.unregister::<Velocity>() && !physics.unregister::<Velocity>());
+		assert_eq!(physics.len(), 1);
+		assert!(physics.is_registered::<Position>() && !physics.is_registered::<Velocity>());
+	}
+
+	#[test]
+	fn schema_hash() {
+		let mut physics = ComponentRegistry::new();
+		crate::register_components!(physics, [Position, Velocity]);
+		let mut host = ComponentRegistry::new();
+		host.register::<Health>();
+		host.extend_from([&physics]);
+		assert_eq!(crate::ECS::from_registry(&host, 1).schema_hash(), host.schema_hash());
+		assert_ne!(host.schema_hash(), physics.schema_hash());
+	}
+
+	#[test]
+	fn stable_name() {
+		// Same layout, so only the names tell them apart
+		let mut before = ComponentRegistry::new();
+		before.register_stable_name::<Position>("position");
+		let mut after = ComponentRegistry::new();
+		after.register_stable_name::<Velocity>("position");
+		assert_eq!(before.schema_hash(), after.schema_hash());
+		assert_eq!(after.get_info::<Velocity>().unwrap().stable_name(), Some("position"));
+
+		let mut ecs = ECS::from_registry(&before, 1);
+		assert_eq!(ecs.schema_hash(), before.schema_hash());
+		ecs.register::<Health>();
+		assert!(ecs.register_stable_name::<Health>("health"));
+		assert!(!ecs.register_stable_name::<Velocity>("velocity"));
+		before.register_stable_name::<Health>("health");
+		assert_eq!(ecs.schema_hash(), before.schema_hash());
+	}
+
+	#[test]
+	fn info() {
+		#[repr(C, align(8))]
+		struct Odd(u8);
+		impl Component for Odd {}
+
+		let mut ecs = ECS::new(4);
+		ecs.register::<Odd>();
+		let info = ecs.get_component_info::<Odd>().unwrap();
+		assert_eq!((info.layout().size(), info.layout().align(), info.stride()), (8, 8, 8));
+		assert!(info.name().ends_with("Odd") && !info.is_cloneable());
+		assert!(ecs.get_component_info::<Health>().is_none());
+		ecs.register_cloneable::<Health>();
+		assert!(ecs.get_component_info::<Health>().unwrap().is_cloneable());
+
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Odd(1));
+		ecs.add_component(&entity, Odd(2));
+		assert_eq!(ecs.get_component::<Odd>(&entity).unwrap().0, 2);
+	}
 }
@@ -0,0 +1,89 @@
+use crate::{Component, EntityID, ECS};
+use core::marker::PhantomData;
+
+/// Moves every `C` out of the ECS, see `ECS::drain`. Dropping it early still empties the rest of the column.
+pub struct DrainIter<'a, C: Component> {
+	ecs: &'a mut ECS,
+	index: usize,
+	despawn: bool,
+	_marker: PhantomData<fn() -> C>,
+}
+
+impl<'a, C: Component> Iterator for DrainIter<'a, C> {
+	type Item = (EntityID, C);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.index < self.ecs.capacity {
+			let index = self.index;
+			self.index += 1;
+			if !self.ecs.has_at::<C>(index) { continue; }
+			let entity = self.ecs.get_index(index).unwrap();
+			let component = self.ecs.take_at::<C>(index).unwrap();
+			if self.despawn {
+				self.ecs.destroy_entity(entity);
+			}
+			return Some((entity, component));
+		}
+		None
+	}
+}
+
+impl<'a, C: Component> Drop for DrainIter<'a, C> {
+	fn drop(&mut self) {
+		for _ in self.by_ref() {}
+	}
+}
+
+impl ECS {
+	/// Remove every `C`, yielding it by value with its entity
+	pub fn drain<C: Component>(&mut self) -> DrainIter<'_, C> {
+		self.flush();
+		DrainIter { ecs: self, index: 0, despawn: false, _marker: PhantomData }
+	}
+
+	/// Like `drain` but also destroys each entity, for short lived message entities.
+	/// The yielded ids are already invalid.
+	pub fn drain_entities<C: Component>(&mut self) -> DrainIter<'_, C> {
+		self.flush();
+		DrainIter { ecs: self, index: 0, despawn: true, _marker: PhantomData }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+	use alloc::vec::Vec;
+
+	struct Damage(u32);
+	impl Component for Damage {}
+	struct Health;
+	impl Component for Health {}
+
+	#[test]
+	fn drain() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<Damage>();
+		ecs.register::<Health>();
+		let target = ecs.create_entity().unwrap();
+		ecs.add_component(&target, Health);
+		ecs.add_component(&target, Damage(1));
+		for amount in 2..5 {
+			let message = ecs.create_entity().unwrap();
+			ecs.add_component(&message, Damage(amount));
+		}
+
+		let first = ecs.drain::<Damage>().next().unwrap();
+		assert!(first.0 == target);
+		assert_eq!(ecs.query::<Damage>().count(), 0);
+		assert_eq!(ecs.get_entity_count(), 4);
+		assert!(ecs.has_component::<Health>(&target));
+
+		for amount in 5..7 {
+			let message = ecs.create_entity().unwrap();
+			ecs.add_component(&message, Damage(amount));
+		}
+		let drained: Vec<_> = ecs.drain_entities::<Damage>().map(|(_, damage)| damage.0).collect();
+		assert_eq!(drained.len(), 2);
+		assert_eq!(ecs.get_entity_count(), 4);
+	}
+}
@@ -0,0 +1,87 @@
+use crate::component::{ComponentID, ComponentInfo, DebugFn};
+use crate::{Component, ECS};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Formats a value through its type erased `Debug` impl
+struct Erased(*const u8, DebugFn);
+
+impl fmt::Debug for Erased {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		unsafe { (self.1)(self.0, f) }
+	}
+}
+
+/// Type name without its module path, generic arguments are kept whole
+fn short_name(name: &str) -> &str {
+	let path_end = name.find('<').unwrap_or(name.len());
+	match name[..path_end].rfind("::") {
+		Some(position) => &name[position + 2..],
+		None => name,
+	}
+}
+
+impl ECS {
+	/// Let `dump` print values of `C`, registering it first if needed. Existing values are kept.
+	pub fn register_debug<C: Component + fmt::Debug>(&mut self) {
+		if !self.components.set_debug(ComponentID::of::<C>(), ComponentInfo::debug::<C>()) {
+			let mut info = ComponentInfo::new::<C>();
+			info.debug = Some(ComponentInfo::debug::<C>());
+			self.components.register(ComponentID::of::<C>(), info, self.capacity);
+		}
+	}
+
+	/// Write one line per alive entity listing its Components by name, sorted, with values
+	/// for Components registered through `register_debug`. Entities reserved since the last `flush` aren't listed.
+	pub fn dump(&self, out: &mut impl fmt::Write) -> fmt::Result {
+		let mut arrays: Vec<_> = self.components.arrays().map(|array| ((array.info().name)(), array)).collect();
+		arrays.sort_by_key(|(name, _)| *name);
+
+		writeln!(out, "{} entities", self.entity_count)?;
+		for index in 0..self.capacity {
+			let Some(entity) = self.get_index(index) else { continue };
+			write!(out, "{}v{}:", index, entity.generation)?;
+			let mut first = true;
+			for (name, array) in &arrays {
+				let Some(value) = array.get_ptr(index) else { continue };
+				write!(out, "{} {}", if first { "" } else { "," }, short_name(name))?;
+				if let Some(debug) = array.info().debug {
+					write!(out, " = {:?}", Erased(value, debug))?;
+				}
+				first = false;
+			}
+			writeln!(out)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+	use alloc::string::String;
+
+	#[derive(Debug)]
+	struct Health(u32);
+	impl Component for Health {}
+
+	struct Handle;
+	impl Component for Handle {}
+
+	#[test]
+	fn dump() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Handle>();
+		ecs.register::<Health>();
+		let first = ecs.create_entity().unwrap();
+		ecs.add_component(&first, Health(3));
+		ecs.add_component(&first, Handle);
+		ecs.create_entity().unwrap();
+		ecs.register_debug::<Health>();
+		assert_eq!(ecs.get_component::<Health>(&first).unwrap().0, 3);
+
+		let mut out = String::new();
+		ecs.dump(&mut out).unwrap();
+		assert_eq!(out, "2 entities\n0v1: Handle, Health = Health(3)\n1v1:\n");
+	}
+}
@@ -0,0 +1,235 @@
+use crate::component::ComponentID;
+use crate::storage::ComponentArray;
+use crate::{Access, Component, ComponentInfo, EntityID, ECS};
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::marker::PhantomData;
+
+/// Query over Component types only known at runtime. Rows hold every Component the `Access` reads,
+/// in the order of `Access::reads`, and only those it writes can be borrowed mutably.
+#[derive(Clone, Debug)]
+pub struct DynamicQuery {
+	/// Component and whether it is writable
+	columns: Vec<(ComponentID, bool)>,
+}
+
+impl DynamicQuery {
+	pub fn new(access: &Access) -> Self {
+		let columns = access.reads().iter().map(|id| (*id, access.can_write(*id))).collect();
+		DynamicQuery { columns }
+	}
+
+	pub fn len(&self) -> usize { self.columns.len() }
+
+	pub fn is_empty(&self) -> bool { self.columns.is_empty() }
+
+	/// Position of `id` within each row
+	pub fn column(&self, id: ComponentID) -> Option<usize> {
+		self.columns.iter().position(|(column, _)| *column == id)
+	}
+}
+
+/// One matching entity of a `DynamicQuery`
+pub struct DynamicRow<'a> {
+	entity: EntityID,
+	columns: &'a [(ComponentID, bool)],
+	pointers: Vec<*mut u8>,
+	_marker: PhantomData<&'a mut ECS>,
+}
+
+impl<'a> DynamicRow<'a> {
+	pub fn entity(&self) -> EntityID { self.entity }
+
+	/// Untyped pointer to the value in `column`, valid for this row's lifetime
+	pub fn ptr(&self, column: usize) -> *const u8 {
+		self.pointers[column]
+	}
+
+	/// Panics unless `column` was declared writable
+	pub fn ptr_mut(&mut self, column: usize) -> *mut u8 {
+		assert!(self.columns[column].1, "DynamicQuery has no write access to this column");
+		self.pointers[column]
+	}
+
+	/// Typed view of a column, `None` if `C` is not part of the query
+	pub fn get<C: Component>(&self) -> Option<&C> {
+		let column = self.columns.iter().position(|(id, _)| *id == ComponentID::of::<C>())?;
+		Some(unsafe { &*(self.pointers[column] as *const C) })
+	}
+
+	/// Typed mutable view of a column, `None` if `C` is not part of the query or not writable
+	pub fn get_mut<C: Component>(&mut self) -> Option<&mut C> {
+		let column = self.columns.iter().position(|(id, write)| *id == ComponentID::of::<C>() && *write)?;
+		Some(unsafe { &mut *(self.pointers[column] as *mut C) })
+	}
+}
+
+pub struct DynamicIter<'a> {
+	ecs: &'a ECS,
+	query: &'a DynamicQuery,
+	/// Empty if any Component is unregistered, nothing can match then
+	arrays: Vec<&'a ComponentArray>,
+	index: usize,
+}
+
+impl<'a> Iterator for DynamicIter<'a> {
+	type Item = DynamicRow<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.arrays.len() != self.query.columns.len() { return None; }
+		while self.index < self.ecs.capacity {
+			let index = self.index;
+			self.index += 1;
+			let Some(entity) = self.ecs.get_index(index) else { continue };
+			let pointers: Option<Vec<_>> = self.arrays.iter().map(|array| array.get_ptr(index).map(|pointer| pointer as *mut u8)).collect();
+			if let Some(pointers) = pointers {
+				return Some(DynamicRow { entity, columns: &self.query.columns, pointers, _marker: PhantomData });
+			}
+		}
+		None
+	}
+}
+
+impl ECS {
+	/// Iterate every entity holding all Components of `query`. Components that were never registered match nothing.
+	pub fn query_dynamic<'a>(&'a mut self, query: &'a DynamicQuery) -> DynamicIter<'a> {
+		self.flush();
+		for (id, _) in query.columns.iter().filter(|(_, write)| *write) {
+			self.components.unshare(*id);
+		}
+		let arrays = query.columns.iter().map_while(|(id, _)| self.components.get_array_by_id(*id)).collect();
+		DynamicIter { ecs: self, query, arrays, index: 0 }
+	}
+
+	/// Every value in the column of `id` as an untyped pointer, nothing if `id` is unregistered.
+	/// Pointers are valid until the ECS is next mutated, `component_layout` gives their size.
+	pub fn iter_component_raw(&self, id: ComponentID) -> impl Iterator<Item = (EntityID, *const u8)> + '_ {
+		let array = self.components.get_array_by_id(id);
+		(0..self.capacity).filter_map(move |index| {
+			let pointer = array?.get_ptr(index)?;
+			Some((self.get_index(index)?, pointer))
+		})
+	}
+
+	pub fn component_layout(&self, id: ComponentID) -> Option<Layout> {
+		Some(self.components.get_array_by_id(id)?.info().layout)
+	}
+
+	/// Full type name of a registered Component
+	pub fn component_name(&self, id: ComponentID) -> Option<&'static str> {
+		Some((self.components.get_array_by_id(id)?.info().name)())
+	}
+
+	/// Convert the live column of `old_id` to the layout of `new_info` in place, for a reloaded module whose
+	/// struct changed. `convert` gets each old value's bytes and takes ownership of it, the old value is never
+	/// dropped. Hooks, indexes, reflection, names, replication, and trait casts of `old_id` are forgotten and
+	/// checkpoints stop restoring it, all of them being written against the old layout. Returns false if
+	/// `old_id` is unregistered.
+	/// # Safety
+	/// `convert` must fully initialize a valid value of `new_info` at the pointer it is given, and afterwards
+	/// the column may only be accessed as the type `new_info` describes.
+	pub unsafe fn migrate_component(&mut self, old_id: ComponentID, new_info: ComponentInfo, mut convert: impl FnMut(&[u8], *mut u8)) -> bool {
+		self.flush();
+		if !self.components.migrate(old_id, new_info, &mut convert) { return false; }
+		debug!(component = new_info.name(), "migrate component");
+		self.indexes.forget(old_id);
+		self.drop_hooks.forget(old_id);
+		self.set_hooks.forget(old_id);
+		self.reflect.forget(old_id);
+		self.names.forget(old_id);
+		self.replication.forget(old_id);
+		self.traits.forget(old_id);
+		self.checkpoints.forget(old_id);
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Access, Component, DynamicQuery, ECS};
+	use core::any::TypeId;
+
+	struct Health(u32);
+	impl Component for Health {}
+	struct Armor(u32);
+	impl Component for Armor {}
+	struct Unused;
+	impl Component for Unused {}
+
+	#[test]
+	fn dynamic() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Health>();
+		ecs.register::<Armor>();
+		for value in 0..4 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Health(value));
+			if value % 2 == 1 {
+				ecs.add_component(&entity, Armor(value));
+			}
+		}
+
+		let mut access = Access::new();
+		access.add_write(TypeId::of::<Health>());
+		access.add_read(TypeId::of::<Armor>());
+		let query = DynamicQuery::new(&access);
+		let armor = query.column(TypeId::of::<Armor>()).unwrap();
+		for mut row in ecs.query_dynamic(&query) {
+			let bonus = unsafe { *(row.ptr(armor) as *const u32) };
+			assert_eq!(row.get::<Armor>().unwrap().0, bonus);
+			row.get_mut::<Health>().unwrap().0 += bonus;
+			assert!(row.get_mut::<Armor>().is_none());
+		}
+		let mut values: alloc::vec::Vec<_> = ecs.query::<Health>().map(|(_, health)| health.0).collect();
+		values.sort();
+		assert_eq!(values, [0, 2, 2, 6]);
+
+		let missing = DynamicQuery::new(&Access::new().read::<Health>().read::<Unused>());
+		assert_eq!(ecs.query_dynamic(&missing).count(), 0);
+	}
+
+	#[test]
+	fn migrate() {
+		struct Position { x: f32, y: f32 }
+		impl Component for Position {}
+		struct Position3 { x: f32, y: f32, z: f32, _label: alloc::boxed::Box<u8> }
+		impl Component for Position3 {}
+
+		let mut ecs = ECS::new(4);
+		ecs.register::<Position>();
+		ecs.create_entity().unwrap();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Position { x: 1.0, y: 2.0 });
+
+		let id = TypeId::of::<Position>();
+		let migrated = unsafe {
+			ecs.migrate_component(id, crate::ComponentInfo::new::<Position3>(), |old, new| {
+				let old = &*(old.as_ptr() as *const Position);
+				(new as *mut Position3).write(Position3 { x: old.x, y: old.y, z: 0.0, _label: alloc::boxed::Box::new(7) });
+			})
+		};
+		assert!(migrated);
+		assert_eq!(ecs.component_layout(id), Some(core::alloc::Layout::new::<Position3>()));
+		let (moved, pointer) = ecs.iter_component_raw(id).next().unwrap();
+		assert!(moved == entity);
+		let position = unsafe { &*(pointer as *const Position3) };
+		assert_eq!((position.x, position.y, position.z), (1.0, 2.0, 0.0));
+		assert!(!unsafe { ecs.migrate_component(TypeId::of::<Unused>(), crate::ComponentInfo::new::<Unused>(), |_, _| {}) });
+	}
+
+	#[test]
+	fn raw() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Health>();
+		ecs.create_entity().unwrap();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Health(7));
+
+		let id = TypeId::of::<Health>();
+		assert_eq!(ecs.component_layout(id).unwrap().size(), 4);
+		assert!(ecs.component_name(id).unwrap().ends_with("Health"));
+		let values: alloc::vec::Vec<_> = ecs.iter_component_raw(id).map(|(entity, pointer)| (entity.index, unsafe { *(pointer as *const u32) })).collect();
+		assert_eq!(values, [(1, 7)]);
+		assert_eq!(ecs.iter_component_raw(TypeId::of::<Unused>()).count(), 0);
+	}
+}
@@ -1,78 +1,440 @@
+use crate::borrow::StorageBorrows;
+use crate::command::CommandQueue;
+use crate::compact::RemapListener;
 use crate::component::{ComponentID, ComponentInfo};
-use crate::storage::{Slot, ComponentMap};
-use crate::{Component, ComponentRegistry, Query, QueryIter, QueryMut, QueryMutIter};
+use crate::group::Groups;
+use crate::guid::Guids;
+use crate::hook::{DropHooks, SetHooks};
+use crate::index::Indexes;
+use crate::named::NamedComponents;
+use crate::reflect::ReflectRegistry;
+use crate::relation::RelationKind;
+use crate::replication::ReplicationState;
+use crate::resource::Resources;
+use crate::snapshot::Checkpoints;
+use crate::watch::Watches;
+use crate::stats::Stats;
+use crate::trait_query::TraitMap;
+use crate::storage::ComponentMap;
+use crate::{Access, Commands, Component, ComponentRegistry, ComponentStorage, FromWorld, ExcludingIter, PairIter, Query, QueryIter, QueryMut, QueryMutIter, WithoutIter};
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicIsize, Ordering};
+use hashbrown::HashMap;
 use runtime_id::RuntimeID;
 
 type Index = usize;
 type Generation = usize;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct EntityID {
-	scene_id: RuntimeID,
+	pub(crate) scene_id: RuntimeID,
+	pub(crate) index: Index,
+	pub(crate) generation: Generation,
+}
+
+impl EntityID {
+	pub const fn get_scene_id(&self) -> RuntimeID { self.scene_id }
+
+	/// Slot of the entity, always below the capacity of its ECS, for keying dense side arrays such as GPU
+	/// instance data instead of hashing whole ids. The entity holds this slot for as long as the id is valid.
+	/// `compact` moves entities by replacing their ids, reporting `(old, new)` to remap
+	/// listeners so side arrays can follow. Freed slots are reused, `generation` tells occupants apart.
+	pub const fn index(&self) -> usize { self.index }
+
+	/// Bumped each time the index is reused, see `index`
+	pub const fn generation(&self) -> usize { self.generation }
+
+	pub const fn downgrade(&self) -> WeakEntity {
+		WeakEntity { index: self.index, generation: self.generation }
+	}
+}
+
+/// An `EntityID` without its scene. Only meaningful against the ECS it came from,
+/// resolves to nothing once its slot has been destroyed, even if the index is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WeakEntity {
 	index: Index,
 	generation: Generation,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-struct Entity {
-	alive: bool,
-	generation: Generation,
+pub(crate) struct Entity {
+	pub(crate) alive: bool,
+	pub(crate) generation: Generation,
+}
+
+/// Why `ECS::validate` rejected an `EntityID`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidEntity {
+	/// Came from a different ECS
+	WrongScene,
+	/// Index past the ECS's capacity and past anything reserved beyond it
+	OutOfRange,
+	/// The slot never held this generation, as with an id from `reserve_entity` before the next flush, even one
+	/// past the capacity. Under `GenerationPolicy::Wrap` a destroyed id is reported here once its slot's
+	/// generation has wrapped below it.
+	NeverAlive,
+	/// The entity was destroyed, and the slot may since have been reused
+	Stale,
+}
+
+impl fmt::Display for InvalidEntity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			InvalidEntity::WrongScene => write!(f, "entity belongs to another ECS"),
+			InvalidEntity::OutOfRange => write!(f, "entity index is out of range"),
+			InvalidEntity::NeverAlive => write!(f, "entity was never alive"),
+			InvalidEntity::Stale => write!(f, "entity was destroyed"),
+		}
+	}
 }
 
 pub type GrowFn = fn(usize) -> usize;
 
+/// Starting capacity of `ECS::new_unbounded`
+const UNBOUNDED_CAPACITY: usize = 16;
+
+fn double(capacity: usize) -> usize { capacity.saturating_mul(2).max(1) }
+
+/// What happens to a slot whose generation counter is exhausted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GenerationPolicy {
+	/// Never reuse the slot again, so a stale handle can never validate. Costs one index of capacity per exhausted slot.
+	Retire,
+	/// Wrap back around. A handle held across `usize::MAX` reuses of its slot will validate against the new entity.
+	Wrap,
+}
+
+/// Which free index `create_entity` and `reserve_entity` hand out next
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecyclePolicy {
+	/// Most recently freed first, saving the sorted insert
+	Lifo,
+	/// Lowest free index first, the default, so peers performing the same operations in the same order produce the
+	/// same `EntityID`s regardless of how their free lists were built. Freeing costs a sorted insert.
+	Lowest,
+}
+
+/// Borrows only the allocator of an `ECS`, so it can be shared across threads whatever Components the world holds
+#[derive(Clone, Copy)]
+pub struct EntityReserver<'a> {
+	scene_id: RuntimeID,
+	capacity: usize,
+	entities: &'a [Entity],
+	free: &'a [Index],
+	cursor: &'a AtomicIsize,
+}
+
+impl EntityReserver<'_> {
+	/// See `ECS::reserve_entity`
+	pub fn reserve_entity(&self) -> EntityID {
+		let cursor = self.cursor.fetch_sub(1, Ordering::Relaxed);
+		if cursor > 0 {
+			let index = self.free[cursor as usize - 1];
+			EntityID { scene_id: self.scene_id, index, generation: self.entities[index].generation.wrapping_add(1) }
+		} else {
+			let index = self.capacity + (-cursor) as usize;
+			EntityID { scene_id: self.scene_id, index, generation: 1 }
+		}
+	}
+}
+
 pub struct ECS {
 	scene_id: RuntimeID,
 	pub(crate) capacity: usize,
-	entity_count: usize,
+	pub(crate) entity_count: usize,
+	/// Frames advanced, see `advance_tick`
+	tick: u64,
+	/// Bumped on every recorded write, see `query_changed`
+	pub(crate) change_tick: u64,
+	/// `change_tick` when the current frame began, see `frame_cursor`
+	pub(crate) frame_change_tick: u64,
 	grow_fn: Option<GrowFn>,
-	entities: Vec<Entity>,
+	generation_policy: GenerationPolicy,
+	recycle_policy: RecyclePolicy,
+	pub(crate) entities: Vec<Entity>,
+	/// Tag bits of each index, see `set_tag`
+	pub(crate) tags: Vec<u64>,
+	/// Dead indices, popped from the back on allocation. Kept in descending order under `RecyclePolicy::Lowest`.
+	pub(crate) free: Vec<Index>,
+	/// Entries of `free` not yet handed out by `reserve_entity`, negative once reservations run past the end of `entities`
+	pub(crate) free_cursor: AtomicIsize,
 	pub(crate) components: ComponentMap,
+	pub(crate) groups: Groups,
+	pub(crate) guids: Guids,
+	pub(crate) drop_hooks: DropHooks,
+	pub(crate) set_hooks: SetHooks,
+	pub(crate) indexes: Indexes,
+	pub(crate) reflect: ReflectRegistry,
+	/// Components addressable by string, see `register_named`
+	pub(crate) names: NamedComponents,
+	pub(crate) relations: Vec<RelationKind>,
+	pub(crate) remap_listeners: Vec<Option<RemapListener>>,
+	pub(crate) checkpoints: Checkpoints,
+	pub(crate) replication: ReplicationState,
+	pub(crate) resources: Resources,
+	pub(crate) stats: Stats,
+	/// Per Component churn counters as of the last `reset_stats`
+	pub(crate) stats_baseline: HashMap<ComponentID, (usize, usize, usize)>,
+	pub(crate) storage_borrows: StorageBorrows,
+	pub(crate) traits: TraitMap,
+	pub(crate) watches: Watches,
+	commands: Arc<CommandQueue>,
+}
+
+impl Default for ECS {
+	fn default() -> Self { ECS::new_unbounded() }
 }
 
 impl ECS {
+	/// Columns allocate values a page of 256 at a time, so even a world of a handful of entities holds a whole
+	/// page per registered Component. `register_paged` holds off until a value arrives.
 	pub fn new(capacity: usize) -> Self {
 		let mut entities = Vec::with_capacity(capacity);
 		entities.resize(capacity, Entity { alive: false, generation: 0 });
+		let free: Vec<Index> = (0..capacity).rev().collect();
 
 		ECS {
 			scene_id: RuntimeID::new(),
 			capacity,
 			entity_count: 0,
+			tick: 0,
+			change_tick: 0,
+			frame_change_tick: 0,
 			grow_fn: None,
+			generation_policy: GenerationPolicy::Retire,
+			recycle_policy: RecyclePolicy::Lowest,
 			entities,
+			tags: alloc::vec![0; capacity],
+			free_cursor: AtomicIsize::new(free.len() as isize),
+			free,
 			components: ComponentMap::new(),
+			groups: Groups::new(),
+			guids: Guids::new(capacity),
+			drop_hooks: DropHooks::default(),
+			set_hooks: SetHooks::default(),
+			indexes: Indexes::default(),
+			reflect: ReflectRegistry::default(),
+			names: NamedComponents::default(),
+			relations: Vec::new(),
+			remap_listeners: Vec::new(),
+			checkpoints: Checkpoints::new(),
+			replication: ReplicationState::new(capacity),
+			resources: Resources::default(),
+			stats: Stats::default(),
+			stats_baseline: HashMap::new(),
+			storage_borrows: StorageBorrows::default(),
+			traits: TraitMap::default(),
+			watches: Watches::default(),
+			commands: Arc::new(CommandQueue::new()),
 		}
 	}
 	
+	/// Start small and double whenever full, for when a fixed capacity isn't worth picking
+	pub fn new_unbounded() -> Self {
+		let mut ecs = ECS::new(UNBOUNDED_CAPACITY);
+		ecs.set_grow_fn(Some(double));
+		ecs
+	}
+
 	pub fn from_registry(registry: &ComponentRegistry, capacity: usize) -> Self {
 		let mut ecs = ECS::new(capacity);
 
 		for (id, info) in &registry.components {
 			ecs.components.register(*id, *info, capacity);
 		}
+		ecs.names.merge(&registry.names);
 
 		return ecs;
 	}
 	
+	/// Deep copy for speculative simulation, holding the same entities at the same indices and generations
+	/// with clones of every cloneable Component. Non-cloneable Components stay registered but empty.
+	/// The copy is a new scene, so carry ids over with `downgrade` and `resolve`. Resources, checkpoints,
+	/// replication, and trait registrations start fresh, while groups, relations, reflection, and names carry over.
+	pub fn clone_world(&mut self) -> ECS {
+		let components = self.components.duplicate();
+		self.copy_world(components)
+	}
+
+	/// `clone_world` without copying any Component values up front. Each cloneable column stays shared
+	/// between the two worlds until either one writes to it, which then copies only the page of 256 values written.
+	/// Cheap for speculative simulations that only touch a few Component types of a large world.
+	pub fn fork(&mut self) -> ECS {
+		let components = self.components.fork();
+		self.copy_world(components)
+	}
+
+	/// Copy of everything but Components, which come from `components`
+	fn copy_world(&mut self, components: ComponentMap) -> ECS {
+		self.flush();
+		let mut world = ECS {
+			scene_id: RuntimeID::new(),
+			capacity: self.capacity,
+			entity_count: self.entity_count,
+			tick: self.tick,
+			change_tick: self.change_tick,
+			frame_change_tick: self.frame_change_tick,
+			grow_fn: self.grow_fn,
+			generation_policy: self.generation_policy,
+			recycle_policy: self.recycle_policy,
+			entities: self.entities.clone(),
+			tags: self.tags.clone(),
+			free: self.free.clone(),
+			free_cursor: AtomicIsize::new(self.free.len() as isize),
+			components,
+			groups: self.groups.duplicate_empty(),
+			guids: self.guids.clone(),
+			drop_hooks: DropHooks::default(),
+			set_hooks: SetHooks::default(),
+			indexes: Indexes::default(),
+			reflect: self.reflect.clone(),
+			names: self.names.clone(),
+			relations: self.relations.clone(),
+			remap_listeners: Vec::new(),
+			checkpoints: Checkpoints::new(),
+			replication: ReplicationState::new(self.capacity),
+			resources: Resources::default(),
+			stats: Stats::default(),
+			stats_baseline: HashMap::new(),
+			storage_borrows: StorageBorrows::default(),
+			traits: TraitMap::default(),
+			watches: Watches::default(),
+			commands: Arc::new(CommandQueue::new()),
+		};
+		let entities = &world.entities;
+		world.groups.rebuild(&mut world.components, world.capacity, |index| entities[index].alive);
+		world.reset_stats();
+		world
+	}
+
 	pub fn register<C: Component>(&mut self) {
 		self.components.register(ComponentID::of::<C>(), ComponentInfo::new::<C>(), self.capacity);
 	}
 
+	/// Register a Component that snapshots and world copies can duplicate
+	pub fn register_cloneable<C: Component + Clone>(&mut self) {
+		self.components.register(ComponentID::of::<C>(), ComponentInfo::cloneable::<C>(), self.capacity);
+	}
+
+	/// Register a Component held by few entities, see `ComponentInfo::paged`
+	pub fn register_paged<C: Component>(&mut self) {
+		self.components.register(ComponentID::of::<C>(), ComponentInfo::new::<C>().paged(), self.capacity);
+	}
+
+	/// Register a Component whose values live in `S`, see `ComponentStorage`
+	pub fn register_with_storage<C: Component, S: ComponentStorage>(&mut self) {
+		self.components.register(ComponentID::of::<C>(), ComponentInfo::new::<C>().with_storage::<S>(), self.capacity);
+	}
+
+	/// How `C` is stored, or `None` if it isn't registered
+	pub fn get_component_info<C: Component>(&self) -> Option<&ComponentInfo> {
+		self.components.get_array::<C>().map(|array| array.info())
+	}
+
+	/// Identifies the set of registered Components by stable name and layout, matching `ComponentRegistry::schema_hash`
+	/// for a world built from that registry. Saved data tagged with it can be checked before it is trusted. Only
+	/// stable across builds if every Component has a stable name, see `ComponentRegistry::register_stable_name`.
+	pub fn schema_hash(&self) -> u64 { self.components.schema_hash() }
+
+	/// See `ComponentRegistry::register_stable_name`. Returns false if `C` isn't registered.
+	pub fn register_stable_name<C: Component>(&mut self, name: &'static str) -> bool {
+		self.components.set_stable_name(ComponentID::of::<C>(), name)
+	}
+
+	/// Drop every `C` and free its column, for retiring a Component type without rebuilding the world.
+	/// Hooks, indexes, reflection, names, replication, trait casts, relation cleanup, and the group owning `C` are
+	/// forgotten too, and checkpoints stop restoring it. Returns false if `C` wasn't registered.
+	pub fn unregister<C: Component>(&mut self) -> bool {
+		let id = ComponentID::of::<C>();
+		if self.components.get_array::<C>().is_none() { return false; }
+		for index in 0..self.capacity {
+			self.run_drop_hook(id, index);
+		}
+		let Some(array) = self.components.unregister(id) else { return false };
+		self.stats.removed += array.filled();
+		self.groups.forget(id);
+		trace!(component = core::any::type_name::<C>(), "unregister component");
+		self.indexes.forget(id);
+		self.drop_hooks.forget(id);
+		self.set_hooks.forget(id);
+		self.reflect.forget(id);
+		self.names.forget(id);
+		self.replication.forget(id);
+		self.traits.forget(id);
+		self.checkpoints.forget(id);
+		self.forget_relation(id);
+		true
+	}
+
+	pub const fn get_scene_id(&self) -> RuntimeID { self.scene_id }
+
 	pub const fn get_capacity(&self) -> usize { self.capacity }
 
+	/// Frames advanced so far, for anything measured in frames rather than writes
+	pub const fn current_tick(&self) -> u64 { self.tick }
+
+	/// Move to the next frame, returning its tick. `Schedule::run` calls this before anything else.
+	/// Starts a new `frame_cursor`, lets `Events` age by frame, and destroys entities whose `Expires` has come.
+	pub fn advance_tick(&mut self) -> u64 {
+		self.tick += 1;
+		self.frame_change_tick = self.change_tick;
+		trace!(tick = self.tick, "advance tick");
+		self.destroy_expired();
+		self.tick
+	}
+
 	pub fn set_grow_fn(&mut self, grow: Option<GrowFn>) { self.grow_fn = grow }
 
+	pub const fn get_generation_policy(&self) -> GenerationPolicy { self.generation_policy }
+
+	pub fn set_generation_policy(&mut self, policy: GenerationPolicy) { self.generation_policy = policy }
+
+	pub const fn get_recycle_policy(&self) -> RecyclePolicy { self.recycle_policy }
+
+	/// Switching to `RecyclePolicy::Lowest` flushes reservations and sorts the free list once
+	pub fn set_recycle_policy(&mut self, policy: RecyclePolicy) {
+		self.flush();
+		self.recycle_policy = policy;
+		if policy == RecyclePolicy::Lowest {
+			self.free.sort_unstable_by(|a, b| b.cmp(a));
+		}
+	}
+
+	/// Current generation of the slot at `index`, alive or not
+	pub fn get_generation(&self, index: usize) -> Option<usize> {
+		self.entities.get(index).map(|entity| entity.generation)
+	}
+
+	/// Grow by the grow function. Stored Components keep their addresses, so pointers to them stay valid as long
+	/// as the value stays put; removing it, `unregister`, `migrate_component`, or writing to a column still shared
+	/// with a fork can all still move or free it.
 	pub fn grow_capacity(&mut self) {
-		let new_capacity = self.grow_fn.unwrap()(self.capacity);
-		self.grow_capacity_to_size(new_capacity);
+		let Some(grow) = self.grow_fn else { fail!("no grow function set") };
+		self.grow_capacity_to_size(grow(self.capacity));
 	}
 
 	pub fn grow_capacity_to_size(&mut self, new_capacity: usize) {
-		assert!(new_capacity > self.capacity, "new capacity must be larget than previous");
+		self.flush();
+		self.resize_entities(new_capacity);
+	}
+
+	fn resize_entities(&mut self, new_capacity: usize) {
+		if new_capacity <= self.capacity { fail!("new capacity must be larget than previous") }
+		if self.storage_borrows.is_borrowed() { fail!("cannot grow capacity while storage is borrowed") }
+		debug!(old_capacity = self.capacity, new_capacity, "grow capacity");
 		self.entities.resize(new_capacity, Entity { alive: false, generation: 0 });
+		self.tags.resize(new_capacity, 0);
 		self.components.resize(new_capacity);
+		self.stats.reallocations += self.components.len();
+		self.guids.resize(new_capacity);
+		self.set_hooks.resize(new_capacity);
+		self.replication.resize(new_capacity);
+		// New indices go underneath the existing free ones so those are reused first
+		self.free.splice(0..0, (self.capacity..new_capacity).rev());
+		*self.free_cursor.get_mut() = self.free.len() as isize;
 		self.capacity = new_capacity;
 	}
 
@@ -80,30 +442,108 @@ impl ECS {
 
 	pub fn is_valid(&self, entity: &EntityID) -> bool {
 		if entity.scene_id != self.scene_id { return false; }
-		self.entities[entity.index] == Entity { alive: true, generation: entity.generation }
+		self.entities.get(entity.index) == Some(&Entity { alive: true, generation: entity.generation })
+	}
+
+	/// Like `is_valid` but saying what is wrong with `entity`
+	pub fn validate(&self, entity: &EntityID) -> Result<(), InvalidEntity> {
+		if entity.scene_id != self.scene_id { return Err(InvalidEntity::WrongScene); }
+		let Some(slot) = self.entities.get(entity.index) else {
+			// Reservations past the end only get a slot at the next flush
+			let reserved = (-self.free_cursor.load(Ordering::Relaxed)).max(0) as usize;
+			return Err(if entity.index < self.capacity + reserved { InvalidEntity::NeverAlive } else { InvalidEntity::OutOfRange });
+		};
+		if slot.generation < entity.generation { return Err(InvalidEntity::NeverAlive); }
+		if slot.generation != entity.generation || !slot.alive { return Err(InvalidEntity::Stale); }
+		Ok(())
+	}
+
+	pub fn resolve(&self, weak: WeakEntity) -> Option<EntityID> {
+		let entity = self.entities.get(weak.index)?;
+		if !entity.alive || entity.generation != weak.generation { return None; }
+		Some(EntityID { scene_id: self.scene_id, index: weak.index, generation: weak.generation })
 	}
 
 	fn allocate_entity(&mut self) -> Option<EntityID> {
-		for index in 0..self.capacity {
-			let entity = &mut self.entities[index];
-			if !entity.alive {
-				entity.alive = true;
-				entity.generation += 1;
-				return Some(EntityID { scene_id: self.scene_id, index, generation: entity.generation });
+		let index = self.free.pop()?;
+		*self.free_cursor.get_mut() = self.free.len() as isize;
+		let entity = self.entities.get_mut(index)?;
+		entity.alive = true;
+		entity.generation = entity.generation.wrapping_add(1);
+		let generation = entity.generation;
+		trace!(index, generation, "create entity");
+		self.stats.spawned += 1;
+		self.guids.on_create(index);
+		self.replication.on_create(index, generation);
+		Some(EntityID { scene_id: self.scene_id, index, generation })
+	}
+
+	/// Hand out an id without `&mut`. The entity only becomes valid at the next `flush`,
+	/// growing capacity if more ids were reserved than there were free slots.
+	pub fn reserve_entity(&self) -> EntityID {
+		self.reserver().reserve_entity()
+	}
+
+	/// `reserve_entity` for other threads. The world itself isn't `Sync`, since it may hold Components that aren't.
+	pub fn reserver(&self) -> EntityReserver<'_> {
+		EntityReserver { scene_id: self.scene_id, capacity: self.capacity, entities: &self.entities, free: &self.free, cursor: &self.free_cursor }
+	}
+
+	/// Activate every entity handed out by `reserve_entity` since the last flush
+	pub fn flush(&mut self) {
+		let cursor = *self.free_cursor.get_mut();
+		if cursor == self.free.len() as isize { return; }
+
+		if cursor < 0 {
+			let required = self.capacity + (-cursor) as usize;
+			let mut new_capacity = self.capacity;
+			while new_capacity < required {
+				new_capacity = match self.grow_fn {
+					Some(grow) => grow(new_capacity).max(new_capacity + 1),
+					None => required,
+				};
+			}
+			let old_capacity = self.capacity;
+			let reserved_free = self.free.len();
+			self.resize_entities(new_capacity);
+			if self.capacity != new_capacity { return; }
+			// Reserved ids past the old end were counted from the old capacity upwards
+			self.free.retain(|index| !(old_capacity..required).contains(index));
+			for index in old_capacity..required {
+				self.activate(index);
 			}
+			*self.free_cursor.get_mut() = (self.free.len() - reserved_free) as isize;
+		}
+
+		let cursor = *self.free_cursor.get_mut() as usize;
+		for index in self.free.split_off(cursor).into_iter().rev() {
+			self.activate(index);
 		}
-		None
+		*self.free_cursor.get_mut() = self.free.len() as isize;
+	}
+
+	fn activate(&mut self, index: Index) {
+		let Some(entity) = self.entities.get_mut(index) else { return };
+		entity.alive = true;
+		entity.generation = entity.generation.wrapping_add(1);
+		let generation = entity.generation;
+		self.entity_count += 1;
+		trace!(index, generation, "activate reserved entity");
+		self.stats.spawned += 1;
+		self.guids.on_create(index);
+		self.replication.on_create(index, generation);
 	}
 	
 	pub fn create_entity(&mut self) -> Option<EntityID> {
+		self.flush();
 		let entity = match self.allocate_entity() {
 			Some(entity) => entity,
 			None => {
-				if self.grow_fn == None {
+				if self.grow_fn.is_none() {
 					return None;
 				} else {
 					self.grow_capacity();
-					self.allocate_entity().unwrap()
+					self.allocate_entity()?
 				}
 			},
 		};
@@ -113,70 +553,338 @@ impl ECS {
 	}
 
 	pub fn get_index(&self, index: usize) -> Option<EntityID> {
-		if index >= self.capacity { return None }
-		let entity = &self.entities[index];
+		let entity = self.entities.get(index)?;
 		if !entity.alive { return None }
 		Some(EntityID { scene_id: self.scene_id, index, generation: entity.generation })
 	}
+
+	/// Id of an index known to be alive, skipping the check `get_index` makes
+	pub(crate) fn alive_id(&self, index: usize) -> EntityID {
+		EntityID { scene_id: self.scene_id, index, generation: self.entities[index].generation }
+	}
 	
 	pub fn destroy_entity(&mut self, entity: EntityID) {
+		self.flush();
 		if self.is_valid(&entity) {
+			trace!(index = entity.index, generation = entity.generation, "destroy entity");
 			self.entity_count -= 1;
-			self.components.delete_index(entity.index);
-			self.entities[entity.index].alive = false;
+			self.stats.despawned += 1;
+			self.indexes.on_destroy(entity, &self.components);
+			self.run_drop_hooks(entity.index);
+			self.set_hooks.on_destroy(entity.index);
+			self.stats.removed += self.components.delete_index(entity.index);
+			self.groups.on_destroy(entity.index, &mut self.components);
+			self.guids.on_destroy(entity.index);
+			self.replication.on_destroy(entity.index, entity.generation);
+			self.watches.on_destroy(entity.index);
+			if let Some(slot) = self.entities.get_mut(entity.index) {
+				slot.alive = false;
+			}
+			self.tags[entity.index] = 0;
+			// Retired slots never return to the free list, so generations only wrap under `Wrap`
+			let exhausted = entity.generation == Generation::MAX;
+			if !exhausted || self.generation_policy == GenerationPolicy::Wrap {
+				self.release(entity.index);
+			}
+			self.cleanup_relations(entity);
+		}
+	}
+
+	fn release(&mut self, index: Index) {
+		match self.recycle_policy {
+			RecyclePolicy::Lifo => self.free.push(index),
+			RecyclePolicy::Lowest => {
+				let position = self.free.partition_point(|free| *free > index);
+				self.free.insert(position, index);
+			},
+		}
+		*self.free_cursor.get_mut() = self.free.len() as isize;
+	}
+
+	/// Destroy every entity holding `C` for which `keep` returns false, in one pass over the column.
+	/// Entities without `C` are left alone.
+	pub fn retain<C: Component>(&mut self, mut keep: impl FnMut(EntityID, &C) -> bool) {
+		self.flush();
+		let doomed: Vec<EntityID> = self.query::<C>().filter(|(entity, component)| !keep(*entity, component)).map(|(entity, _)| entity).collect();
+		for entity in doomed {
+			self.destroy_entity(entity);
 		}
 	}
 
 	pub fn has_component<C: Component>(&self, entity: &EntityID) -> bool {
 		if !self.is_valid(entity) { return false; }
-		return match self.components.get_array::<C>() {
-		    Some(array) => array[entity.index].is_filled(),
-		    None => false,
-		}
+		self.has_at::<C>(entity.index)
 	}
 
+	/// Insert `component`, dropping any `C` already on `entity`. See `replace_component` to keep the old value.
 	pub fn add_component<C: Component>(&mut self, entity: &EntityID, component: C) {
 		if !self.is_valid(entity) { return; }
-		match unsafe { self.components.get_array_mut::<C>() } {
-		    Some(array) => array[entity.index] = Slot::Filled(component),
-		    None => todo!(),
+		self.insert_at(entity.index, component);
+	}
+
+	/// Insert a clone of `value` on every valid entity in `entities`, see `add_components`
+	pub fn add_component_batch<C: Component + Clone>(&mut self, entities: &[EntityID], value: C) {
+		self.add_components(entities.iter().map(|entity| (*entity, value.clone())));
+	}
+
+	/// `add_component` for many entities at once, looking the column up once rather than per entity.
+	/// Invalid entities are skipped.
+	pub fn add_components<C: Component>(&mut self, components: impl IntoIterator<Item = (EntityID, C)>) {
+		let id = ComponentID::of::<C>();
+		let indexed = self.indexes.watches(id);
+		let Some(array) = self.components.get_array_mut::<C>() else { fail!("Component not registered") };
+		let mut inserted = Vec::new();
+		for (entity, component) in components {
+			let alive = Entity { alive: true, generation: entity.generation };
+			if entity.scene_id != self.scene_id || self.entities.get(entity.index) != Some(&alive) { continue; }
+			array.unshare_index(entity.index);
+			if let Some(pointer) = array.get_ptr(entity.index) {
+				if indexed {
+					unsafe { self.indexes.on_remove(id, entity, pointer) };
+				}
+				unsafe { self.drop_hooks.run(id, entity, pointer as *mut u8) };
+				self.set_hooks.mark(id, entity.index);
+			}
+			unsafe { array.insert(entity.index, component) };
+			if indexed {
+				unsafe { self.indexes.on_insert(id, entity, array.get_ptr(entity.index).unwrap()) };
+			}
+			inserted.push(entity.index);
+		}
+		trace!(count = inserted.len(), component = core::any::type_name::<C>(), "insert component batch");
+		self.stats.inserted += inserted.len();
+		for index in inserted {
+			self.groups.on_insert(id, index, &mut self.components);
+			self.on_change(id, index);
 		}
 	}
 
+	/// Give every alive entity a clone of `value`, overwriting any `C` it already had
+	pub fn fill<C: Component + Clone>(&mut self, value: C) {
+		self.flush();
+		let alive: Vec<EntityID> = (0..self.capacity).filter_map(|index| self.get_index(index)).collect();
+		self.add_component_batch(&alive, value);
+	}
+
 	pub fn remove_component<C: Component>(&mut self, entity: &EntityID) {
 		if !self.is_valid(entity) { return; }
-		match unsafe { self.components.get_array_mut::<C>() } {
-			Some(array) => array[entity.index] = Slot::Empty,
-			None => todo!(),
+		self.remove_at::<C>(entity.index);
+	}
+
+	/// Insert `component` and return the `C` it displaced, if any
+	pub fn replace_component<C: Component>(&mut self, entity: &EntityID, component: C) -> Option<C> {
+		if !self.is_valid(entity) { return None; }
+		self.index_remove(ComponentID::of::<C>(), entity.index);
+		let old = match self.components.get_array_mut::<C>() {
+			Some(array) => unsafe { array.take::<C>(entity.index) },
+			None => fail!(None, "Component not registered"),
+		};
+		self.insert_at(entity.index, component);
+		if old.is_some() {
+			self.set_hooks.mark(ComponentID::of::<C>(), entity.index);
 		}
+		old
+	}
+
+	/// Exchange the `C` slots of two entities in place, including whether each is filled
+	pub fn swap_component<C: Component>(&mut self, a: &EntityID, b: &EntityID) {
+		if !self.is_valid(a) || !self.is_valid(b) || a.index == b.index { return; }
+		let id = ComponentID::of::<C>();
+		self.index_remove(id, a.index);
+		self.index_remove(id, b.index);
+		match self.components.get_array_mut::<C>() {
+			Some(array) => array.swap(a.index, b.index),
+			None => fail!("Component not registered"),
+		}
+		for index in [a.index, b.index] {
+			self.index_insert(id, index);
+			if self.components.is_filled(id, index) {
+				self.groups.on_insert(id, index, &mut self.components);
+			} else {
+				self.groups.on_remove(id, index, &mut self.components);
+			}
+			self.on_change(id, index);
+		}
+	}
+
+	/// Remove `C` from `entity` and hand it back instead of dropping it
+	pub fn take_component<C: Component>(&mut self, entity: &EntityID) -> Option<C> {
+		if !self.is_valid(entity) { return None; }
+		self.take_at(entity.index)
 	}
 
 	pub fn get_component<C: Component>(&self, entity: &EntityID) -> Option<&C> {
 		if !self.is_valid(entity) { return None; }
-		match self.components.get_array::<C>() {
-		    Some(array) => array[entity.index].as_option(),
-		    None => None,
-		}
+		self.get_at(entity.index)
 	}
 
+	/// Writes through the reference aren't seen by `EntityIndex`es, see `modify_component`
 	pub fn get_component_mut<C: Component>(&mut self, entity: &EntityID) -> Option<&mut C> {
 		if !self.is_valid(entity) { return None; }
-		match unsafe { self.components.get_array_mut::<C>() } {
-		    Some(array) => array[entity.index].as_option_mut(),
+		self.get_mut_at(entity.index)
+	}
+
+	/// `C` on `entity`, inserting `default()` first if it has none. `None` only if `entity` is invalid.
+	pub fn get_or_insert_with<C: Component>(&mut self, entity: &EntityID, default: impl FnOnce() -> C) -> Option<&mut C> {
+		if !self.is_valid(entity) { return None; }
+		if self.has_at::<C>(entity.index) { return self.get_mut_at(entity.index); }
+		self.insert_at(entity.index, default());
+		self.get_mut_at(entity.index)
+	}
+
+	pub fn add_default<C: Component + Default>(&mut self, entity: &EntityID) {
+		self.add_component(entity, C::default());
+	}
+
+	/// Insert `C` built by `FromWorld`, which runs before the component is added
+	pub fn add_from_world<C: Component + FromWorld>(&mut self, entity: &EntityID) {
+		if !self.is_valid(entity) { return; }
+		let component = C::from_world(self);
+		self.add_component(entity, component);
+	}
+
+	/// Stop sharing the columns of `ids` with any fork before writing through pointers to them, and start
+	/// the change tick those writes are stamped with
+	pub(crate) fn unshare(&mut self, ids: &[ComponentID]) {
+		self.change_tick += 1;
+		for id in ids {
+			self.components.unshare(*id);
+		}
+	}
+
+	/// Record a write to the `id` slot at `index` for change ticks, replication, and watches
+	pub(crate) fn on_change(&mut self, id: ComponentID, index: Index) {
+		self.change_tick += 1;
+		self.components.set_tick(id, index, self.change_tick);
+		self.replication.on_change(id, index);
+		self.watches.on_change(id, index);
+	}
+
+	// Component access by index for callers that already validated the entity
+
+	pub(crate) fn has_at<C: Component>(&self, index: Index) -> bool {
+		match self.components.get_array::<C>() {
+		    Some(array) => array.is_filled(index),
+		    None => false,
+		}
+	}
+
+	pub(crate) fn insert_at<C: Component>(&mut self, index: Index, component: C) {
+		self.index_remove(ComponentID::of::<C>(), index);
+		self.run_drop_hook(ComponentID::of::<C>(), index);
+		if self.has_at::<C>(index) {
+			self.set_hooks.mark(ComponentID::of::<C>(), index);
+		}
+		match self.components.get_array_mut::<C>() {
+		    Some(array) => unsafe { array.insert(index, component) },
+		    None => fail!("Component not registered"),
+		}
+		trace!(index, component = core::any::type_name::<C>(), "insert component");
+		self.stats.inserted += 1;
+		self.groups.on_insert(ComponentID::of::<C>(), index, &mut self.components);
+		self.on_change(ComponentID::of::<C>(), index);
+		self.index_insert(ComponentID::of::<C>(), index);
+	}
+
+	pub(crate) fn remove_at<C: Component>(&mut self, index: Index) {
+		self.index_remove(ComponentID::of::<C>(), index);
+		self.run_drop_hook(ComponentID::of::<C>(), index);
+		self.set_hooks.unmark(ComponentID::of::<C>(), index);
+		match self.components.get_array_mut::<C>() {
+			Some(array) => {
+				if array.delete_index(index) {
+					self.stats.removed += 1;
+				}
+			},
+			None => fail!("Component not registered"),
+		}
+		trace!(index, component = core::any::type_name::<C>(), "remove component");
+		self.groups.on_remove(ComponentID::of::<C>(), index, &mut self.components);
+		self.on_change(ComponentID::of::<C>(), index);
+	}
+
+	pub(crate) fn take_at<C: Component>(&mut self, index: Index) -> Option<C> {
+		self.index_remove(ComponentID::of::<C>(), index);
+		self.set_hooks.unmark(ComponentID::of::<C>(), index);
+		let component = match self.components.get_array_mut::<C>() {
+			Some(array) => unsafe { array.take::<C>(index) },
+			None => fail!(None, "Component not registered"),
+		};
+		if component.is_some() {
+			trace!(index, component = core::any::type_name::<C>(), "take component");
+			self.stats.removed += 1;
+			self.groups.on_remove(ComponentID::of::<C>(), index, &mut self.components);
+			self.on_change(ComponentID::of::<C>(), index);
+		}
+		component
+	}
+
+	pub(crate) fn get_at<C: Component>(&self, index: Index) -> Option<&C> {
+		match self.components.get_array::<C>() {
+		    Some(array) => unsafe { array.get::<C>(index) },
 		    None => None,
 		}
 	}
 
-	pub fn query<Q: Query>(&self) -> QueryIter<Q> { QueryIter::new(self) }
+	/// Counts as a write for change detection, replication, and on-set hooks only if there is a value
+	pub(crate) fn get_mut_at<C: Component>(&mut self, index: Index) -> Option<&mut C> {
+		if !self.has_at::<C>(index) { return None; }
+		self.on_change(ComponentID::of::<C>(), index);
+		self.set_hooks.mark(ComponentID::of::<C>(), index);
+		let array = self.components.get_array_mut::<C>()?;
+		array.unshare_index(index);
+		unsafe { array.get_mut::<C>(index) }
+	}
+
+	pub fn commands(&self) -> Commands { Commands::new(self.commands.clone()) }
+
+	pub fn apply_commands(&mut self) {
+		self.flush();
+		for command in self.commands.take() {
+			command(self);
+		}
+	}
+
+	pub fn query<Q: Query>(&self) -> QueryIter<'_, Q> {
+		trace!(query = core::any::type_name::<Q>(), "query");
+		QueryIter::new(self)
+	}
 
-	pub fn query_mut<Q: QueryMut>(&mut self) -> QueryMutIter<Q> { QueryMutIter::new(self) }
+	pub fn query_mut<Q: QueryMut>(&mut self) -> QueryMutIter<'_, Q> {
+		trace!(query = core::any::type_name::<Q>(), "query_mut");
+		QueryMutIter::new(self)
+	}
+
+	/// Iterate `A` mutably and `B` at the same time, for looking up other entities while updating.
+	/// Panics if `A` and `B` share a Component, since that would alias the mutable references.
+	pub fn query_mut_with<A: QueryMut, B: Query>(&mut self) -> (QueryMutIter<'_, A>, QueryIter<'_, B>) {
+		trace!(query = core::any::type_name::<(A, B)>(), "query_mut_with");
+		let (access, other) = (Access::of_query_mut::<A>(), Access::of_query::<B>());
+		assert!(access.is_compatible(&other), "Cannot Query a Component mutably alongside another Query for it");
+		self.unshare(&A::component_ids());
+		let ecs: &ECS = self;
+		(unsafe { QueryMutIter::new_unchecked(ecs) }, QueryIter::new(ecs))
+	}
+
+	/// Every alive entity that does not have `C`
+	pub fn query_without<C: Component>(&self) -> WithoutIter<'_, C> { WithoutIter::new(self) }
+
+	/// Like `query` but skipping entities that have `C`
+	pub fn query_excluding<Q: Query, C: Component>(&self) -> ExcludingIter<'_, Q, C> { ExcludingIter::new(self) }
+
+	pub fn query_pairs<C: Component>(&self) -> PairIter<'_, C> { PairIter::new(self, None) }
+
+	/// Pairs among entities that hold both `C` and `F`
+	pub fn query_pairs_with<C: Component, F: Component>(&self) -> PairIter<'_, C> {
+		PairIter::new(self, Some(self.components.get_array::<F>().unwrap()))
+	}
 }
 
 #[cfg(test)]
 mod test {
-	use crate::{ComponentRegistry, ECS, Component};
+	use crate::{ComponentRegistry, ECS, Component, FromWorld};
 	
-	#[derive(PartialEq, Eq)]
+	#[derive(Clone, Default, PartialEq, Eq)]
 	struct TestComponent(usize);
 	impl Component for TestComponent {}
 
@@ -191,6 +899,46 @@ mod test {
 		assert!(!ecs.is_valid(&entity));
 	}
 
+	#[test]
+	fn weak() {
+		let mut ecs = ECS::new(4);
+		let entity = ecs.create_entity().unwrap();
+		let weak = entity.downgrade();
+		assert!(ecs.resolve(weak) == Some(entity));
+
+		ecs.destroy_entity(entity);
+		let reused = ecs.create_entity().unwrap();
+		assert_eq!(reused.index, entity.index);
+		assert!(ecs.resolve(weak).is_none());
+		assert!(ecs.resolve(reused.downgrade()) == Some(reused));
+	}
+
+	#[test]
+	fn generation_policy() {
+		use super::GenerationPolicy;
+
+		let mut ecs = ECS::new(2);
+		let entity = ecs.create_entity().unwrap();
+		ecs.entities[entity.index].generation = usize::MAX;
+		let exhausted = ecs.get_index(entity.index).unwrap();
+		ecs.destroy_entity(exhausted);
+
+		// Retired: only the other slot is left
+		let other = ecs.create_entity().unwrap();
+		assert_ne!(other.index, entity.index);
+		assert!(ecs.create_entity().is_none());
+		assert_eq!(ecs.get_generation(entity.index), Some(usize::MAX));
+
+		let mut ecs = ECS::new(1);
+		ecs.set_generation_policy(GenerationPolicy::Wrap);
+		let entity = ecs.create_entity().unwrap();
+		ecs.entities[entity.index].generation = usize::MAX;
+		ecs.destroy_entity(ecs.get_index(entity.index).unwrap());
+		let wrapped = ecs.create_entity().unwrap();
+		assert_eq!(wrapped.index, entity.index);
+		assert_eq!(ecs.get_generation(wrapped.index), Some(0));
+	}
+
 	#[test]
 	fn count() {
 		const CAPACITY: usize = 64;
@@ -206,6 +954,35 @@ mod test {
 		assert_eq!(ecs.get_entity_count(), ENTITY_COUNT);
 	}
 
+	#[test]
+	fn recycle_lowest() {
+		use super::RecyclePolicy;
+
+		let spawn = |order: &[usize]| {
+			let mut ecs = ECS::new(8);
+			let entities: alloc::vec::Vec<_> = (0..6).map(|_| ecs.create_entity().unwrap()).collect();
+			for index in order {
+				ecs.destroy_entity(entities[*index]);
+			}
+			let reserved = ecs.reserve_entity();
+			ecs.flush();
+			let created: alloc::vec::Vec<_> = (0..3).map(|_| ecs.create_entity().unwrap().index).collect();
+			(reserved.index, created)
+		};
+		assert_eq!(spawn(&[4, 1, 3]), (1, alloc::vec![3, 4, 6]));
+		assert_eq!(spawn(&[3, 4, 1]), spawn(&[4, 1, 3]));
+
+		let mut ecs = ECS::new(4);
+		ecs.set_recycle_policy(RecyclePolicy::Lifo);
+		let entities: alloc::vec::Vec<_> = (0..4).map(|_| ecs.create_entity().unwrap()).collect();
+		ecs.destroy_entity(entities[0]);
+		ecs.destroy_entity(entities[2]);
+		assert_eq!(ecs.create_entity().unwrap().index, 2);
+		ecs.destroy_entity(entities[1]);
+		ecs.set_recycle_policy(RecyclePolicy::Lowest);
+		assert_eq!(ecs.create_entity().unwrap().index, 0);
+	}
+
 	#[test]
 	fn grow_to_size() {
 		const STARTING_CAPACITY: usize = 64;
@@ -213,11 +990,27 @@ mod test {
 		
 		let mut ecs = ECS::new(STARTING_CAPACITY);
 		ecs.register::<TestComponent>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, TestComponent(7));
+		let address = ecs.get_component::<TestComponent>(&entity).unwrap() as *const TestComponent;
 		
 		assert_eq!(ecs.capacity, STARTING_CAPACITY);
 		
 		ecs.grow_capacity_to_size(NEW_CAPACITY);
 		assert_eq!(ecs.capacity, NEW_CAPACITY);
+		assert!(core::ptr::eq(ecs.get_component::<TestComponent>(&entity).unwrap(), address));
+	}
+
+	#[test]
+	fn unbounded() {
+		let mut ecs = ECS::default();
+		ecs.register::<TestComponent>();
+		for value in 0..100 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, TestComponent(value));
+		}
+		assert_eq!(ecs.get_capacity(), 128);
+		assert_eq!(ecs.query::<TestComponent>().count(), 100);
 	}
 
 	#[test]
@@ -251,6 +1044,147 @@ mod test {
 		assert!(!ecs.has_component::<TestComponent>(&entity));
 	}
 
+	#[test]
+	fn get_or_insert_with() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<TestComponent>();
+		let entity = ecs.create_entity().unwrap();
+
+		ecs.get_or_insert_with(&entity, || TestComponent(1)).unwrap().0 += 1;
+		ecs.get_or_insert_with(&entity, || TestComponent(10)).unwrap().0 += 1;
+		assert_eq!(ecs.get_component::<TestComponent>(&entity).unwrap().0, 3);
+
+		ecs.destroy_entity(entity);
+		assert!(ecs.get_or_insert_with(&entity, || TestComponent(1)).is_none());
+	}
+
+	#[test]
+	fn take_component() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<TestComponent>();
+		let dying = ecs.create_entity().unwrap();
+		let loot = ecs.create_entity().unwrap();
+		ecs.add_component(&dying, TestComponent(7));
+
+		let taken = ecs.take_component::<TestComponent>(&dying).unwrap();
+		assert!(!ecs.has_component::<TestComponent>(&dying));
+		assert!(ecs.take_component::<TestComponent>(&dying).is_none());
+		ecs.add_component(&loot, taken);
+		assert_eq!(ecs.get_component::<TestComponent>(&loot).unwrap().0, 7);
+	}
+
+	#[test]
+	fn replace_component() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<TestComponent>();
+		let entity = ecs.create_entity().unwrap();
+
+		assert!(ecs.replace_component(&entity, TestComponent(1)).is_none());
+		assert_eq!(ecs.replace_component(&entity, TestComponent(2)).unwrap().0, 1);
+		assert_eq!(ecs.get_component::<TestComponent>(&entity).unwrap().0, 2);
+	}
+
+	#[test]
+	fn swap_component() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<TestComponent>();
+		ecs.create_group::<TestComponent>();
+		let a = ecs.create_entity().unwrap();
+		let b = ecs.create_entity().unwrap();
+		ecs.add_component(&a, TestComponent(1));
+
+		ecs.swap_component::<TestComponent>(&a, &b);
+		assert!(!ecs.has_component::<TestComponent>(&a));
+		assert_eq!(ecs.get_component::<TestComponent>(&b).unwrap().0, 1);
+		let grouped: alloc::vec::Vec<_> = ecs.query_group::<TestComponent>().map(|(entity, _)| entity.index).collect();
+		assert_eq!(grouped, [b.index]);
+
+		ecs.add_component(&a, TestComponent(2));
+		ecs.swap_component::<TestComponent>(&a, &b);
+		ecs.swap_component::<TestComponent>(&a, &a);
+		assert_eq!(ecs.get_component::<TestComponent>(&a).unwrap().0, 1);
+		assert_eq!(ecs.get_component::<TestComponent>(&b).unwrap().0, 2);
+	}
+
+	#[test]
+	fn retain() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<TestComponent>();
+		let bare = ecs.create_entity().unwrap();
+		for height in 0..6 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, TestComponent(height));
+		}
+
+		ecs.retain::<TestComponent>(|_, height| height.0 >= 3);
+		assert_eq!(ecs.get_entity_count(), 4);
+		assert!(ecs.is_valid(&bare));
+		assert!(ecs.query::<TestComponent>().all(|(_, height)| height.0 >= 3));
+	}
+
+	#[test]
+	fn chunks() {
+		extern crate std;
+		let mut ecs = ECS::new(10);
+		ecs.register::<TestComponent>();
+		for value in 0..10 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, TestComponent(value));
+		}
+
+		let chunks: alloc::vec::Vec<_> = ecs.query_mut::<TestComponent>().chunks(4).collect();
+		assert_eq!(chunks.len(), 3);
+		std::thread::scope(|scope| {
+			for chunk in chunks {
+				scope.spawn(move || {
+					for (_, component) in chunk {
+						component.0 *= 2;
+					}
+				});
+			}
+		});
+		let sizes: alloc::vec::Vec<_> = ecs.query::<TestComponent>().chunks(4).map(|chunk| chunk.count()).collect();
+		assert_eq!(sizes, [4, 4, 2]);
+		assert_eq!(ecs.query::<TestComponent>().map(|(_, component)| component.0).sum::<usize>(), 90);
+	}
+
+	#[test]
+	fn with_range() {
+		let mut ecs = ECS::new(10);
+		ecs.register::<TestComponent>();
+		for value in 0..10 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, TestComponent(value));
+		}
+
+		let slice: alloc::vec::Vec<_> = ecs.query::<TestComponent>().with_range(3..6).map(|(_, component)| component.0).collect();
+		assert_eq!(slice, [3, 4, 5]);
+		for (_, component) in ecs.query_mut::<TestComponent>().with_range(8..100) {
+			component.0 = 0;
+		}
+		assert_eq!(ecs.query::<TestComponent>().with_range(5..5).count(), 0);
+		assert_eq!(ecs.query::<TestComponent>().map(|(_, component)| component.0).sum::<usize>(), 28);
+	}
+
+	#[test]
+	fn from_world() {
+		struct Serial(usize);
+		impl Component for Serial {}
+		impl FromWorld for Serial {
+			fn from_world(ecs: &mut ECS) -> Self { Serial(ecs.get_entity_count()) }
+		}
+
+		let mut ecs = ECS::new(4);
+		ecs.register::<Serial>();
+		ecs.register::<TestComponent>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.create_entity().unwrap();
+		ecs.add_from_world::<Serial>(&entity);
+		ecs.add_default::<TestComponent>(&entity);
+		assert_eq!(ecs.get_component::<Serial>(&entity).unwrap().0, 2);
+		assert_eq!(ecs.get_component::<TestComponent>(&entity).unwrap().0, 0);
+	}
+
 	#[test]
 	fn query() {
 		const CAPACITY: usize = 256;
@@ -267,6 +1201,451 @@ mod test {
 		}
 	}
 
+	#[test]
+	fn reserve() {
+		const CAPACITY: usize = 16;
+		let mut ecs = ECS::new(CAPACITY);
+		ecs.register::<TestComponent>();
+
+		let first = ecs.create_entity().unwrap();
+		ecs.destroy_entity(first);
+
+		let reserved: [_; CAPACITY + 8] = core::array::from_fn(|_| ecs.reserve_entity());
+		assert!(!ecs.is_valid(&reserved[0]));
+		assert_eq!(ecs.get_entity_count(), 0);
+
+		ecs.flush();
+		assert_eq!(ecs.get_entity_count(), CAPACITY + 8);
+		assert!(ecs.get_capacity() >= CAPACITY + 8);
+		for (index, entity) in reserved.iter().enumerate() {
+			assert!(ecs.is_valid(entity));
+			ecs.add_component(entity, TestComponent(index));
+		}
+		assert!(!ecs.is_valid(&first));
+		assert_eq!(ecs.query::<TestComponent>().count(), CAPACITY + 8);
+	}
+
+	#[test]
+	fn take_absent() {
+		let mut ecs = ECS::new(2);
+		ecs.register::<TestComponent>();
+		let entity = ecs.create_entity().unwrap();
+		let cursor = ecs.change_cursor();
+		assert!(ecs.take_component::<TestComponent>(&entity).is_none());
+		assert_eq!(ecs.change_cursor(), cursor);
+	}
+
+	#[test]
+	fn non_send_component() {
+		use alloc::rc::Rc;
+		struct Shared(Rc<usize>);
+		impl Component for Shared {}
+
+		let mut ecs = ECS::new(2);
+		ecs.register::<Shared>();
+		let value = Rc::new(3);
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Shared(value.clone()));
+		assert_eq!(*ecs.get_component::<Shared>(&entity).unwrap().0, 3);
+		ecs.destroy_entity(entity);
+		assert_eq!(Rc::strong_count(&value), 1);
+	}
+
+	#[test]
+	fn reserve_threaded() {
+		extern crate std;
+		const CAPACITY: usize = 64;
+		let mut ecs = ECS::new(CAPACITY);
+		let reserver = ecs.reserver();
+
+		let ids: std::vec::Vec<_> = std::thread::scope(|scope| {
+			let handles: std::vec::Vec<_> = (0..4).map(|_| scope.spawn(|| {
+				(0..CAPACITY / 2).map(|_| reserver.reserve_entity()).collect::<std::vec::Vec<_>>()
+			})).collect();
+			handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+		});
+
+		ecs.flush();
+		assert_eq!(ecs.get_entity_count(), CAPACITY * 2);
+		let mut indices: std::vec::Vec<_> = ids.iter().map(|id| id.index).collect();
+		indices.sort();
+		indices.dedup();
+		assert_eq!(indices.len(), CAPACITY * 2);
+		assert!(ids.iter().all(|id| ecs.is_valid(id)));
+	}
+
+	#[test]
+	fn without() {
+		struct Collider;
+		impl Component for Collider {}
+
+		let mut ecs = ECS::new(16);
+		ecs.register::<TestComponent>();
+		assert_eq!(ecs.query_without::<Collider>().count(), 0);
+
+		let mut entities = alloc::vec::Vec::new();
+		for index in 0..6 {
+			let entity = ecs.create_entity().unwrap();
+			if index < 4 {
+				ecs.add_component(&entity, TestComponent(index));
+			}
+			entities.push(entity);
+		}
+		assert_eq!(ecs.query_without::<Collider>().count(), 6);
+
+		ecs.register::<Collider>();
+		ecs.add_component(&entities[1], Collider);
+		ecs.add_component(&entities[5], Collider);
+		let lacking: alloc::vec::Vec<_> = ecs.query_without::<Collider>().map(|id| id.index).collect();
+		assert_eq!(lacking, [0, 2, 3, 4]);
+
+		let excluding: alloc::vec::Vec<_> = ecs.query_excluding::<TestComponent, Collider>().map(|(_, test)| test.0).collect();
+		assert_eq!(excluding, [0, 2, 3]);
+	}
+
+	#[test]
+	fn pairs() {
+		struct Filter;
+		impl Component for Filter {}
+
+		let mut ecs = ECS::new(32);
+		ecs.register::<TestComponent>();
+		ecs.register::<Filter>();
+		for index in 0..6 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, TestComponent(index));
+			if index % 2 == 0 {
+				ecs.add_component(&entity, Filter);
+			}
+		}
+		ecs.create_entity().unwrap();
+
+		assert_eq!(ecs.query_pairs::<TestComponent>().count(), 15);
+		for (a, first, b, second) in ecs.query_pairs::<TestComponent>() {
+			assert!(first.0 < second.0);
+			assert!(ecs.is_valid(&a) && ecs.is_valid(&b));
+		}
+
+		let filtered: alloc::vec::Vec<_> = ecs.query_pairs_with::<TestComponent, Filter>().map(|(_, a, _, b)| (a.0, b.0)).collect();
+		assert_eq!(filtered, [(0, 2), (0, 4), (2, 4)]);
+	}
+
+	#[test]
+	fn mut_with() {
+		struct Target(usize);
+		impl Component for Target {}
+
+		let mut ecs = ECS::new(8);
+		ecs.register::<TestComponent>();
+		ecs.register::<Target>();
+		for index in 0..4 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, TestComponent(0));
+			ecs.add_component(&entity, Target(index));
+		}
+
+		let (seekers, targets) = ecs.query_mut_with::<TestComponent, Target>();
+		let targets: alloc::vec::Vec<_> = targets.collect();
+		for (_, seeker) in seekers {
+			seeker.0 = targets.iter().map(|(_, target)| target.0).sum();
+		}
+		assert!(ecs.query::<TestComponent>().all(|(_, seeker)| seeker.0 == 6));
+	}
+
+	#[test]
+	#[should_panic]
+	fn mut_with_alias() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<TestComponent>();
+		let _ = ecs.query_mut_with::<TestComponent, TestComponent>();
+	}
+
+	#[test]
+	#[should_panic]
+	fn tuple_alias() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<TestComponent>();
+		let _ = ecs.query::<(TestComponent, TestComponent)>();
+	}
+
+	#[test]
+	fn wide_tuple() {
+		struct Transform(usize);
+		impl Component for Transform {}
+		struct Mesh;
+		impl Component for Mesh {}
+		struct Material;
+		impl Component for Material {}
+		struct Visible(bool);
+		impl Component for Visible {}
+
+		let mut ecs = ECS::new(8);
+		crate::register_components!(ecs, [Transform, Mesh, Material, Visible]);
+		for index in 0..8 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Transform(index));
+			ecs.add_component(&entity, Mesh);
+			ecs.add_component(&entity, Visible(index % 2 == 0));
+			if index != 3 {
+				ecs.add_component(&entity, Material);
+			}
+		}
+
+		assert_eq!(ecs.query::<(Transform, Mesh, Material, Visible)>().count(), 7);
+		let visible = ecs.query::<(Transform, Mesh, Material, Visible)>().filter_components(|_: &Transform, _: &Mesh, _: &Material, visible: &Visible| visible.0);
+		let indices: alloc::vec::Vec<_> = visible.map(|(_, transform, ..)| transform.0).collect();
+		assert_eq!(indices, [0, 2, 4, 6]);
+	}
+
+	struct Mass(usize);
+	impl Component for Mass {}
+
+	/// 200 entities, `Mass(index)` on those not divisible by 3, `TestComponent` from 100 on, tagged 1 if odd
+	fn masses() -> ECS {
+		let mut ecs = ECS::new(200);
+		ecs.register::<Mass>();
+		ecs.register::<TestComponent>();
+		for index in 0..200 {
+			let entity = ecs.create_entity().unwrap();
+			if index % 3 != 0 {
+				ecs.add_component(&entity, Mass(index));
+			}
+			if index >= 100 {
+				ecs.add_component(&entity, TestComponent(1));
+			}
+			ecs.set_tag(&entity, (index % 2) as u64);
+		}
+		ecs
+	}
+
+	fn expected_mass(range: core::ops::Range<usize>) -> usize {
+		range.filter(|index| index % 3 != 0).sum()
+	}
+
+	#[test]
+	fn fold() {
+		let ecs = masses();
+		assert_eq!(ecs.query::<Mass>().map(|(_, mass)| mass.0).sum::<usize>(), expected_mass(0..200));
+		assert_eq!(ecs.query::<(Mass, TestComponent)>().fold(0, |total, (_, mass, _)| total + mass.0), expected_mass(100..200));
+		assert_eq!(ecs.query::<Mass>().map(|(_, mass)| mass.0).reduce(usize::max), Some(199));
+	}
+
+	#[test]
+	fn fold_range() {
+		let mut ecs = masses();
+		assert_eq!(ecs.query::<Mass>().with_range(70..131).map(|(_, mass)| mass.0).sum::<usize>(), expected_mass(70..131));
+		ecs.query_mut::<Mass>().with_range(0..64).for_each(|(_, mass)| mass.0 = 0);
+		assert_eq!(ecs.query::<Mass>().map(|(_, mass)| mass.0).sum::<usize>(), expected_mass(64..200));
+	}
+
+	#[test]
+	fn fold_layers() {
+		let ecs = masses();
+		assert_eq!(ecs.query::<Mass>().with_layers(1).count(), (0..200).filter(|index| index % 3 != 0 && index % 2 == 1).count());
+	}
+
+	#[test]
+	fn prefetch() {
+		let ecs = masses();
+		assert_eq!(ecs.query::<Mass>().prefetch(8).map(|(_, mass)| mass.0).sum::<usize>(), expected_mass(0..200));
+		let mut prefetching = ecs.query::<(Mass, TestComponent)>().prefetch(500);
+		assert_eq!(prefetching.next().map(|(_, mass, _)| mass.0), Some(100));
+		assert_eq!(ecs.query::<Mass>().prefetch(usize::MAX).count(), ecs.query::<Mass>().count());
+		assert_eq!(ecs.query::<Mass>().prefetch(usize::MAX).fold(0, |total, (_, mass)| total + mass.0), expected_mass(0..200));
+	}
+
+	#[test]
+	fn index() {
+		let mut ecs = ECS::new(2);
+		let first = ecs.create_entity().unwrap();
+		let second = ecs.create_entity().unwrap();
+		assert!(first.index() < 2 && second.index() < 2 && first.index() != second.index());
+		assert!(ecs.get_index(first.index()) == Some(first));
+
+		ecs.destroy_entity(first);
+		let reused = ecs.create_entity().unwrap();
+		assert_eq!(reused.index(), first.index());
+		assert_ne!(reused.generation(), first.generation());
+	}
+
+	#[test]
+	fn validate() {
+		use crate::ecs::{EntityID, InvalidEntity};
+		let mut ecs = ECS::new(2);
+		let other = ECS::new(2).create_entity().unwrap();
+		let entity = ecs.create_entity().unwrap();
+		assert_eq!(ecs.validate(&entity), Ok(()));
+		assert_eq!(ecs.validate(&other), Err(InvalidEntity::WrongScene));
+		let past_end = EntityID { index: 2, ..entity };
+		assert_eq!(ecs.validate(&past_end), Err(InvalidEntity::OutOfRange));
+		let reserved = ecs.reserve_entity();
+		assert_eq!(ecs.validate(&reserved), Err(InvalidEntity::NeverAlive));
+		ecs.destroy_entity(entity);
+		assert_eq!(ecs.validate(&entity), Err(InvalidEntity::Stale));
+		ecs.flush();
+		assert_eq!(ecs.validate(&reserved), Ok(()));
+	}
+
+	#[test]
+	fn validate_reserved_past_capacity() {
+		use crate::ecs::{EntityID, InvalidEntity};
+		let mut ecs = ECS::new(1);
+		ecs.create_entity().unwrap();
+		let reserved = [ecs.reserve_entity(), ecs.reserve_entity()];
+		assert_eq!(reserved.map(|entity| entity.index), [1, 2]);
+		assert_eq!(ecs.validate(&reserved[1]), Err(InvalidEntity::NeverAlive));
+		assert_eq!(ecs.validate(&EntityID { index: 3, ..reserved[1] }), Err(InvalidEntity::OutOfRange));
+		ecs.flush();
+		assert_eq!(reserved.map(|entity| ecs.validate(&entity)), [Ok(()), Ok(())]);
+	}
+
+	#[test]
+	fn batch() {
+		#[derive(Clone)]
+		struct Stunned(u32);
+		impl Component for Stunned {}
+
+		let mut ecs = ECS::new(8);
+		ecs.register::<Stunned>();
+		ecs.register::<TestComponent>();
+		ecs.create_group::<(Stunned, TestComponent)>();
+		let entities: alloc::vec::Vec<_> = (0..6).map(|_| ecs.create_entity().unwrap()).collect();
+		ecs.destroy_entity(entities[5]);
+
+		ecs.add_component_batch(&entities[2..], Stunned(3));
+		assert_eq!(ecs.query::<Stunned>().count(), 3);
+		ecs.add_components(entities.iter().enumerate().map(|(index, entity)| (*entity, TestComponent(index))));
+		assert_eq!(ecs.query::<TestComponent>().map(|(_, value)| value.0).sum::<usize>(), 10);
+		assert_eq!(ecs.group_len::<(Stunned, TestComponent)>(), Some(3));
+		assert!(ecs.query::<Stunned>().all(|(_, stunned)| stunned.0 == 3));
+	}
+
+	#[test]
+	fn fill() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<TestComponent>();
+		let entities: alloc::vec::Vec<_> = (0..5).map(|_| ecs.create_entity().unwrap()).collect();
+		ecs.add_component(&entities[0], TestComponent(1));
+		ecs.destroy_entity(entities[1]);
+		ecs.reserve_entity();
+
+		ecs.fill(TestComponent(100));
+		assert_eq!(ecs.query::<TestComponent>().count(), 5);
+		assert!(ecs.query::<TestComponent>().all(|(_, value)| value.0 == 100));
+	}
+
+	#[test]
+	fn clone_world() {
+		struct Handle;
+		impl Component for Handle {}
+
+		let mut ecs = ECS::new(4);
+		ecs.register_cloneable::<TestComponent>();
+		ecs.register::<Handle>();
+		ecs.create_group::<TestComponent>();
+		let entities: alloc::vec::Vec<_> = (0..3).map(|_| ecs.create_entity().unwrap()).collect();
+		ecs.destroy_entity(entities[0]);
+		ecs.add_component(&entities[1], TestComponent(1));
+		ecs.add_component(&entities[2], Handle);
+
+		let mut lookahead = ecs.clone_world();
+		let copy = lookahead.resolve(entities[1].downgrade()).unwrap();
+		assert!(!core::ptr::eq(lookahead.get_component::<TestComponent>(&copy).unwrap(), ecs.get_component::<TestComponent>(&entities[1]).unwrap()));
+		assert!(!lookahead.is_valid(&entities[1]));
+		assert!(lookahead.resolve(entities[0].downgrade()).is_none());
+		assert_eq!(lookahead.get_entity_count(), 2);
+		assert_eq!(lookahead.query_group::<TestComponent>().count(), 1);
+		assert_eq!(lookahead.query::<Handle>().count(), 0);
+
+		lookahead.get_component_mut::<TestComponent>(&copy).unwrap().0 = 2;
+		let spawned = lookahead.create_entity().unwrap();
+		assert_eq!(spawned.index, entities[0].index);
+		assert_eq!(ecs.get_component::<TestComponent>(&entities[1]).unwrap().0, 1);
+		assert_eq!(ecs.get_entity_count(), 2);
+	}
+
+	#[test]
+	fn fork() {
+		#[derive(Clone)]
+		struct Static;
+		impl Component for Static {}
+
+		let mut ecs = ECS::new(4);
+		ecs.register_cloneable::<TestComponent>();
+		ecs.register_cloneable::<Static>();
+		let entities: alloc::vec::Vec<_> = (0..3).map(|_| ecs.create_entity().unwrap()).collect();
+		for (index, entity) in entities.iter().enumerate() {
+			ecs.add_component(entity, TestComponent(index));
+			ecs.add_component(entity, Static);
+		}
+		let state = ecs.query_mut_state::<TestComponent>();
+
+		let mut fork = ecs.fork();
+		let id = crate::component::ComponentID::of::<TestComponent>();
+		assert!(fork.components.is_shared(id));
+		let copy = fork.resolve(entities[1].downgrade()).unwrap();
+		fork.get_component_mut::<TestComponent>(&copy).unwrap().0 = 10;
+		assert!(!fork.components.is_shared(id) && !ecs.components.is_shared(id));
+		assert!(ecs.components.is_shared(crate::component::ComponentID::of::<Static>()));
+		assert_eq!(ecs.get_component::<TestComponent>(&entities[1]).unwrap().0, 1);
+
+		let mut other = ecs.fork();
+		let mut state = state;
+		for (_, value) in state.iter_mut(&mut ecs) {
+			value.0 += 100;
+		}
+		let sum = |world: &ECS| world.query::<TestComponent>().map(|(_, value)| value.0).sum::<usize>();
+		assert_eq!((sum(&ecs), sum(&fork), sum(&other)), (303, 12, 3));
+		other.destroy_entity(other.resolve(entities[0].downgrade()).unwrap());
+		assert_eq!(ecs.query::<Static>().count(), 3);
+		assert_eq!(fork.query::<Static>().count(), 3);
+	}
+
+	#[test]
+	fn unregister() {
+		struct Shared(alloc::sync::Arc<()>);
+		impl Component for Shared {}
+
+		let shared = alloc::sync::Arc::new(());
+		let mut ecs = ECS::new(4);
+		ecs.register::<Shared>();
+		ecs.register::<TestComponent>();
+		ecs.create_group::<(Shared, TestComponent)>();
+		for _ in 0..3 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Shared(shared.clone()));
+			ecs.add_component(&entity, TestComponent(1));
+		}
+		assert!(ecs.query::<Shared>().all(|(_, value)| alloc::sync::Arc::ptr_eq(&value.0, &shared)));
+		assert_eq!(alloc::sync::Arc::strong_count(&shared), 4);
+
+		assert!(ecs.unregister::<Shared>());
+		assert!(!ecs.unregister::<Shared>());
+		assert_eq!(alloc::sync::Arc::strong_count(&shared), 1);
+		assert_eq!(ecs.group_len::<(Shared, TestComponent)>(), None);
+		assert_eq!(ecs.query::<TestComponent>().count(), 3);
+
+		ecs.register::<Shared>();
+		assert_eq!(ecs.query::<Shared>().count(), 0);
+	}
+
+	#[test]
+	#[cfg(feature = "panic-free")]
+	fn panic_free() {
+		struct Unregistered;
+		impl Component for Unregistered {}
+
+		let mut ecs = ECS::new(1);
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Unregistered);
+		assert!(ecs.take_component::<Unregistered>(&entity).is_none());
+		ecs.remove_component::<Unregistered>(&entity);
+		ecs.grow_capacity();
+		ecs.grow_capacity_to_size(1);
+		assert_eq!(ecs.get_capacity(), 1);
+		assert!(ecs.create_entity().is_none());
+	}
+
 	mod drop {
 		use crate::{ECS, Component};
 		use core::sync::atomic::{AtomicUsize, Ordering};
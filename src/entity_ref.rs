@@ -0,0 +1,100 @@
+use crate::{Component, EntityID, ECS};
+
+/// Shared access to one entity, validated once by `ECS::entity`
+#[derive(Clone, Copy)]
+pub struct EntityRef<'a> {
+	ecs: &'a ECS,
+	id: EntityID,
+}
+
+impl<'a> EntityRef<'a> {
+	pub fn id(&self) -> EntityID { self.id }
+
+	pub fn has<C: Component>(&self) -> bool {
+		self.ecs.has_at::<C>(self.id.index)
+	}
+
+	pub fn get<C: Component>(&self) -> Option<&'a C> {
+		self.ecs.get_at(self.id.index)
+	}
+}
+
+/// Exclusive access to one entity, validated once by `ECS::entity_mut`
+pub struct EntityMut<'a> {
+	ecs: &'a mut ECS,
+	id: EntityID,
+}
+
+impl<'a> EntityMut<'a> {
+	pub fn id(&self) -> EntityID { self.id }
+
+	pub fn has<C: Component>(&self) -> bool {
+		self.ecs.has_at::<C>(self.id.index)
+	}
+
+	pub fn get<C: Component>(&self) -> Option<&C> {
+		self.ecs.get_at(self.id.index)
+	}
+
+	pub fn get_mut<C: Component>(&mut self) -> Option<&mut C> {
+		self.ecs.get_mut_at(self.id.index)
+	}
+
+	pub fn insert<C: Component>(&mut self, component: C) -> &mut Self {
+		self.ecs.insert_at(self.id.index, component);
+		self
+	}
+
+	pub fn remove<C: Component>(&mut self) -> &mut Self {
+		self.ecs.remove_at::<C>(self.id.index);
+		self
+	}
+
+	pub fn despawn(self) {
+		self.ecs.destroy_entity(self.id);
+	}
+}
+
+impl ECS {
+	pub fn entity(&self, id: EntityID) -> Option<EntityRef<'_>> {
+		self.is_valid(&id).then_some(EntityRef { ecs: self, id })
+	}
+
+	/// Flushes reserved entities first, they can't be created through an `EntityMut`
+	pub fn entity_mut(&mut self, id: EntityID) -> Option<EntityMut<'_>> {
+		self.flush();
+		if !self.is_valid(&id) { return None; }
+		Some(EntityMut { ecs: self, id })
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+
+	struct Health(u32);
+	impl Component for Health {}
+	struct Stunned;
+	impl Component for Stunned {}
+
+	#[test]
+	fn accessors() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Health>();
+		ecs.register::<Stunned>();
+		let id = ecs.create_entity().unwrap();
+
+		let mut entity = ecs.entity_mut(id).unwrap();
+		entity.insert(Health(10)).insert(Stunned).remove::<Stunned>();
+		entity.get_mut::<Health>().unwrap().0 += 5;
+		assert!(!entity.has::<Stunned>());
+
+		let entity = ecs.entity(id).unwrap();
+		assert_eq!(entity.get::<Health>().unwrap().0, 15);
+		assert!(entity.id() == id);
+
+		ecs.entity_mut(id).unwrap().despawn();
+		assert!(ecs.entity(id).is_none());
+		assert!(ecs.entity_mut(id).is_none());
+	}
+}
@@ -0,0 +1,128 @@
+use crate::{Component, EntityID, ECS};
+use core::marker::PhantomData;
+
+/// Slot for `C` on one entity, see `ECS::component_entry`
+pub enum ComponentEntry<'a, C: Component> {
+	Occupied(OccupiedEntry<'a, C>),
+	Vacant(VacantEntry<'a, C>),
+}
+
+pub struct OccupiedEntry<'a, C: Component> {
+	ecs: &'a mut ECS,
+	id: EntityID,
+	_marker: PhantomData<fn() -> C>,
+}
+
+pub struct VacantEntry<'a, C: Component> {
+	ecs: &'a mut ECS,
+	id: EntityID,
+	_marker: PhantomData<fn() -> C>,
+}
+
+impl<'a, C: Component> ComponentEntry<'a, C> {
+	pub fn entity(&self) -> EntityID {
+		match self {
+			ComponentEntry::Occupied(entry) => entry.id,
+			ComponentEntry::Vacant(entry) => entry.id,
+		}
+	}
+
+	pub fn or_insert(self, default: C) -> &'a mut C {
+		self.or_insert_with(|| default)
+	}
+
+	pub fn or_insert_with<F: FnOnce() -> C>(self, default: F) -> &'a mut C {
+		match self {
+			ComponentEntry::Occupied(entry) => entry.into_mut(),
+			ComponentEntry::Vacant(entry) => entry.insert(default()),
+		}
+	}
+
+	pub fn or_default(self) -> &'a mut C where C: Default {
+		self.or_insert_with(C::default)
+	}
+
+	pub fn and_modify<F: FnOnce(&mut C)>(mut self, modify: F) -> Self {
+		if let ComponentEntry::Occupied(entry) = &mut self {
+			modify(entry.get_mut());
+		}
+		self
+	}
+}
+
+impl<'a, C: Component> OccupiedEntry<'a, C> {
+	pub fn get(&self) -> &C {
+		self.ecs.get_at(self.id.index).unwrap()
+	}
+
+	pub fn get_mut(&mut self) -> &mut C {
+		self.ecs.get_mut_at(self.id.index).unwrap()
+	}
+
+	pub fn into_mut(self) -> &'a mut C {
+		self.ecs.get_mut_at(self.id.index).unwrap()
+	}
+
+	/// Replace the value, returning the old one
+	pub fn insert(&mut self, component: C) -> C {
+		core::mem::replace(self.get_mut(), component)
+	}
+
+	pub fn remove(self) {
+		self.ecs.remove_at::<C>(self.id.index);
+	}
+}
+
+impl<'a, C: Component> VacantEntry<'a, C> {
+	pub fn insert(self, component: C) -> &'a mut C {
+		self.ecs.insert_at(self.id.index, component);
+		self.ecs.get_mut_at(self.id.index).unwrap()
+	}
+}
+
+impl ECS {
+	/// Like `HashMap::entry` for `C` on `entity`. `None` if `entity` is invalid.
+	pub fn component_entry<C: Component>(&mut self, entity: &EntityID) -> Option<ComponentEntry<'_, C>> {
+		if !self.is_valid(entity) { return None; }
+		let id = *entity;
+		Some(if self.has_at::<C>(entity.index) {
+			ComponentEntry::Occupied(OccupiedEntry { ecs: self, id, _marker: PhantomData })
+		} else {
+			ComponentEntry::Vacant(VacantEntry { ecs: self, id, _marker: PhantomData })
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ComponentEntry, ECS};
+
+	#[derive(Default)]
+	struct Score(u32);
+	impl Component for Score {}
+
+	#[test]
+	fn entry() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Score>();
+		let entity = ecs.create_entity().unwrap();
+
+		for _ in 0..3 {
+			ecs.component_entry::<Score>(&entity).unwrap().and_modify(|score| score.0 *= 2).or_insert(Score(1));
+		}
+		assert_eq!(ecs.get_component::<Score>(&entity).unwrap().0, 4);
+
+		match ecs.component_entry::<Score>(&entity).unwrap() {
+			ComponentEntry::Occupied(mut entry) => {
+				assert_eq!(entry.insert(Score(9)).0, 4);
+				assert_eq!(entry.get().0, 9);
+				entry.remove();
+			},
+			ComponentEntry::Vacant(_) => panic!(),
+		}
+		assert_eq!(ecs.component_entry::<Score>(&entity).unwrap().or_default().0, 0);
+
+		ecs.destroy_entity(entity);
+		assert!(ecs.component_entry::<Score>(&entity).is_none());
+	}
+}
@@ -0,0 +1,216 @@
+use crate::{Schedule, ECS};
+use alloc::vec::Vec;
+use core::any::TypeId;
+use core::marker::PhantomData;
+
+/// Resource queue of events of type `E`, kept for two frames. `update` is called once per frame by
+/// the schedule, see `Schedule::add_event`, so every system sees each event in the frame it was sent
+/// or the next, whatever order they run in.
+pub struct Events<E> {
+	/// Sent before the last `update`
+	previous: Vec<E>,
+	/// Sent since the last `update`
+	current: Vec<E>,
+	/// Number of events sent before the first in `previous`, so each event has a sequential id
+	previous_start: usize,
+	/// `ECS::current_tick` of the frame `current` was sent in
+	frame: u64,
+}
+
+impl<E> Default for Events<E> {
+	fn default() -> Self {
+		Events { previous: Vec::new(), current: Vec::new(), previous_start: 0, frame: 0 }
+	}
+}
+
+impl<E> Events<E> {
+	pub fn new() -> Self { Self::default() }
+
+	pub fn send(&mut self, event: E) {
+		self.current.push(event);
+	}
+
+	/// Drop the events sent before the last update and start a new frame
+	pub fn update(&mut self) {
+		self.update_to(self.frame + 1);
+	}
+
+	/// Catch up to the frame `tick`, doing nothing if already there and dropping everything if two frames
+	/// or more have passed since the last update
+	pub fn update_to(&mut self, tick: u64) {
+		if tick <= self.frame { return; }
+		if tick - self.frame > 1 {
+			self.clear();
+		} else {
+			self.previous_start += self.previous.len();
+			core::mem::swap(&mut self.previous, &mut self.current);
+			self.current.clear();
+		}
+		self.frame = tick;
+	}
+
+	/// Events from the last two frames, oldest first
+	pub fn iter(&self) -> impl Iterator<Item = &E> + '_ {
+		self.previous.iter().chain(&self.current)
+	}
+
+	pub fn len(&self) -> usize { self.previous.len() + self.current.len() }
+
+	pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+	pub fn clear(&mut self) {
+		self.previous_start += self.len();
+		self.previous.clear();
+		self.current.clear();
+	}
+}
+
+/// Cursor into an `Events<E>` owned by one consumer, usually kept in a `Local`, so several systems can
+/// each read every event exactly once without coordinating
+pub struct EventReader<E> {
+	/// Id of the next event this reader hasn't seen
+	cursor: usize,
+	marker: PhantomData<fn() -> E>,
+}
+
+impl<E> Default for EventReader<E> {
+	fn default() -> Self {
+		EventReader { cursor: 0, marker: PhantomData }
+	}
+}
+
+impl<E> EventReader<E> {
+	pub fn new() -> Self { Self::default() }
+
+	/// Start after every event currently in `events`
+	pub fn from_end(events: &Events<E>) -> Self {
+		EventReader { cursor: events.previous_start + events.len(), marker: PhantomData }
+	}
+
+	/// Events this reader hasn't seen yet, oldest first, and mark them seen. Events dropped by two
+	/// updates before being read are skipped.
+	pub fn read<'a>(&mut self, events: &'a Events<E>) -> impl Iterator<Item = &'a E> + 'a {
+		let skip = self.cursor.saturating_sub(events.previous_start);
+		self.cursor = events.previous_start + events.len();
+		events.iter().skip(skip)
+	}
+
+	pub fn len(&self, events: &Events<E>) -> usize {
+		(events.previous_start + events.len()).saturating_sub(self.cursor.max(events.previous_start))
+	}
+
+	pub fn is_empty(&self, events: &Events<E>) -> bool { self.len(events) == 0 }
+}
+
+impl ECS {
+	/// Queue `event` in the `Events<E>` resource, counting it as sent this frame. Returns false if there is
+	/// none, see `add_event`.
+	pub fn send_event<E: Send + Sync + 'static>(&mut self, event: E) -> bool {
+		let tick = self.current_tick();
+		let Some(events) = self.get_resource_mut::<Events<E>>() else { return false };
+		events.update_to(tick);
+		events.send(event);
+		true
+	}
+}
+
+impl Schedule {
+	/// Update `Events<E>` to the new frame at the start of every `run`, before any system. The resource
+	/// itself is inserted separately, usually with `App::add_event`.
+	pub fn add_event<E: Send + Sync + 'static>(&mut self) {
+		fn update<E: Send + Sync + 'static>(ecs: &mut ECS) {
+			let tick = ecs.current_tick();
+			if let Some(events) = ecs.get_resource_mut::<Events<E>>() {
+				events.update_to(tick);
+			}
+		}
+		let id = TypeId::of::<E>();
+		if self.event_updates.iter().all(|(other, _)| *other != id) {
+			self.event_updates.push((id, update::<E>));
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{App, EventReader, Events, Local, Schedule};
+	use alloc::vec::Vec;
+
+	struct Hit(u32);
+
+	struct Seen(Vec<u32>);
+
+	#[test]
+	fn frames() {
+		let mut app = App::new(1);
+		app.add_event::<Hit>().insert_resource(Seen(Vec::new()));
+		// The reader runs before the writer, so it only sees each hit on the following frame
+		app.add_exclusive_system(Schedule::UPDATE, |ecs| {
+			let hits: Vec<_> = ecs.get_resource::<Events<Hit>>().unwrap().iter().map(|hit| hit.0).collect();
+			ecs.get_resource_mut::<Seen>().unwrap().0 = hits;
+		});
+		app.add_exclusive_system_with_local(Schedule::LATE_UPDATE, |ecs, frame: &mut Local<u32>| {
+			**frame += 1;
+			if **frame == 1 {
+				ecs.send_event(Hit(1));
+				ecs.send_event(Hit(2));
+			}
+		});
+
+		app.run_frame();
+		assert!(app.ecs().get_resource::<Seen>().unwrap().0.is_empty());
+		app.run_frame();
+		assert_eq!(app.ecs().get_resource::<Seen>().unwrap().0, [1, 2]);
+		app.run_frame();
+		assert!(app.ecs().get_resource::<Seen>().unwrap().0.is_empty());
+		assert!(app.ecs().get_resource::<Events<Hit>>().unwrap().is_empty());
+	}
+
+	#[test]
+	fn readers() {
+		let mut events = Events::new();
+		let mut fast = EventReader::new();
+		let mut slow = EventReader::new();
+		events.send(1);
+		events.send(2);
+		assert_eq!(fast.len(&events), 2);
+		assert_eq!(fast.read(&events).copied().collect::<Vec<_>>(), [1, 2]);
+		assert!(fast.read(&events).next().is_none());
+
+		events.update();
+		events.send(3);
+		let late = EventReader::from_end(&events);
+		assert!(late.is_empty(&events));
+		assert_eq!(fast.read(&events).copied().collect::<Vec<_>>(), [3]);
+
+		events.update();
+		events.send(4);
+		assert_eq!(slow.len(&events), 2);
+		assert_eq!(slow.read(&events).copied().collect::<Vec<_>>(), [3, 4]);
+		assert_eq!(fast.read(&events).copied().collect::<Vec<_>>(), [4]);
+		events.clear();
+		assert!(slow.is_empty(&events));
+	}
+
+	#[test]
+	fn update_to() {
+		let mut events = Events::new();
+		events.send(1);
+		events.update_to(1);
+		events.update_to(1);
+		assert_eq!(events.iter().copied().collect::<Vec<_>>(), [1]);
+		events.send(2);
+		events.update_to(3);
+		assert!(events.is_empty());
+
+		let mut app = App::new(1);
+		app.add_event::<Hit>();
+		app.run_frame();
+		app.ecs_mut().advance_tick();
+		app.ecs_mut().advance_tick();
+		// Sent two frames after the last update, so it starts a frame of its own rather than joining a stale one
+		app.ecs_mut().send_event(Hit(1));
+		app.run_frame();
+		assert_eq!(app.ecs().get_resource::<Events<Hit>>().unwrap().len(), 1);
+	}
+}
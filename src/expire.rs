@@ -0,0 +1,47 @@
+use crate::{Component, EntityID, ECS};
+use alloc::vec::Vec;
+
+/// Destroys its entity once `ECS::advance_tick` reaches this tick. Register it like any other Component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Expires(pub u64);
+impl Component for Expires {}
+
+impl ECS {
+	/// Give `entity` an `Expires` that many frames from now
+	pub fn expire_after(&mut self, entity: &EntityID, frames: u64) {
+		self.add_component(entity, Expires(self.current_tick() + frames));
+	}
+
+	/// Run by `advance_tick`, does nothing unless `Expires` is registered
+	pub(crate) fn destroy_expired(&mut self) {
+		if self.components.get_array::<Expires>().is_none() { return; }
+		let tick = self.current_tick();
+		let expired: Vec<EntityID> = self.query::<Expires>().filter(|(_, expires)| expires.0 <= tick).map(|(entity, _)| entity).collect();
+		for entity in expired {
+			self.destroy_entity(entity);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Expires, ECS};
+
+	#[test]
+	fn expires() {
+		let mut ecs = ECS::new(4);
+		ecs.advance_tick();
+		let (short, long, forever) = (ecs.create_entity().unwrap(), ecs.create_entity().unwrap(), ecs.create_entity().unwrap());
+		ecs.register::<Expires>();
+		ecs.expire_after(&short, 1);
+		ecs.expire_after(&long, 3);
+		assert_eq!(ecs.get_component::<Expires>(&long), Some(&Expires(4)));
+
+		ecs.advance_tick();
+		assert!(!ecs.is_valid(&short) && ecs.is_valid(&long));
+		ecs.advance_tick();
+		ecs.advance_tick();
+		assert!(!ecs.is_valid(&long) && ecs.is_valid(&forever));
+		assert_eq!(ecs.get_entity_count(), 1);
+	}
+}
@@ -0,0 +1,174 @@
+use crate::storage::Column;
+use crate::{Component, Query, QueryIter, QueryMut, QueryMutIter};
+
+/// Predicate over the Components of a query, implemented for closures taking shared references in the order of
+/// the query's Components, e.g. `|a: &A, b: &B| -> bool` for `(A, B)`
+pub trait ComponentFilter<A> {
+	/// False if `index` lacks any of the Components.
+	/// # Safety
+	/// `array` must be live, and nothing may hold a mutable reference to the Components at `index`.
+	unsafe fn test(&mut self, array: &A, index: usize) -> bool;
+}
+
+impl<C: Component, F: FnMut(&C) -> bool> ComponentFilter<Column<C>> for F {
+	unsafe fn test(&mut self, array: &Column<C>, index: usize) -> bool {
+		array.get(index).is_some_and(self)
+	}
+}
+
+impl<C0: Component, C1: Component, F: FnMut(&C0, &C1) -> bool> ComponentFilter<(Column<C0>, Column<C1>)> for F {
+	unsafe fn test(&mut self, array: &(Column<C0>, Column<C1>), index: usize) -> bool {
+		match (array.0.get(index), array.1.get(index)) {
+			(Some(c0), Some(c1)) => self(c0, c1),
+			_ => false,
+		}
+	}
+}
+
+macro_rules! filter_tuple {
+	($($C:ident $i:tt),+) => {
+		impl<$($C: Component),+, F: FnMut($(&$C),+) -> bool> ComponentFilter<($(Column<$C>,)+)> for F {
+			unsafe fn test(&mut self, array: &($(Column<$C>,)+), index: usize) -> bool {
+				$(array.$i.is_filled(index))&&+ && self($(array.$i.get_unchecked(index)),+)
+			}
+		}
+	};
+}
+
+filter_tuple!(C0 0, C1 1, C2 2);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12, C13 13);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12, C13 13, C14 14);
+filter_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12, C13 13, C14 14, C15 15);
+
+/// Both predicates of chained `filter_components` calls
+pub struct And<F, G>(F, G);
+
+impl<A, F: ComponentFilter<A>, G: ComponentFilter<A>> ComponentFilter<A> for And<F, G> {
+	unsafe fn test(&mut self, array: &A, index: usize) -> bool {
+		self.0.test(array, index) && self.1.test(array, index)
+	}
+}
+
+/// Matches of `Q` passing a `ComponentFilter`, tested before the output is built
+pub struct FilteredIter<'a, Q: Query + 'a, F> {
+	iter: QueryIter<'a, Q>,
+	filter: F,
+}
+
+impl<'a, Q: Query, F: ComponentFilter<Q::Array>> FilteredIter<'a, Q, F> {
+	pub fn filter_components<G: ComponentFilter<Q::Array>>(self, filter: G) -> FilteredIter<'a, Q, And<F, G>> {
+		FilteredIter { iter: self.iter, filter: And(self.filter, filter) }
+	}
+}
+
+impl<'a, Q: Query, F: ComponentFilter<Q::Array>> Iterator for FilteredIter<'a, Q, F> {
+	type Item = Q::Output<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let iter = &mut self.iter;
+		while iter.index < iter.end {
+			let index = iter.index;
+			iter.index += 1;
+			if iter.layers.admits(iter.ecs, index) && unsafe { self.filter.test(&iter.array, index) } {
+				return Some(unsafe { Q::get_unchecked(iter.ecs, index, &iter.array) });
+			}
+		}
+		None
+	}
+}
+
+pub struct FilteredMutIter<'a, Q: QueryMut + 'a, F> {
+	iter: QueryMutIter<'a, Q>,
+	filter: F,
+}
+
+impl<'a, Q: QueryMut, F: ComponentFilter<Q::Array>> FilteredMutIter<'a, Q, F> {
+	pub fn filter_components<G: ComponentFilter<Q::Array>>(self, filter: G) -> FilteredMutIter<'a, Q, And<F, G>> {
+		FilteredMutIter { iter: self.iter, filter: And(self.filter, filter) }
+	}
+}
+
+impl<'a, Q: QueryMut, F: ComponentFilter<Q::Array>> Iterator for FilteredMutIter<'a, Q, F> {
+	type Item = Q::Output<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let iter = &mut self.iter;
+		while iter.index < iter.end {
+			let index = iter.index;
+			iter.index += 1;
+			// Tested through shared references, so the mutable output is only made for passing entities
+			if iter.layers.admits(iter.ecs, index) && unsafe { self.filter.test(&iter.array, index) } {
+				return Some(unsafe { Q::get_unchecked(iter.ecs, index, &iter.array) });
+			}
+		}
+		None
+	}
+}
+
+impl<'a, Q: Query> QueryIter<'a, Q> {
+	/// Only yield matches for which `filter` returns true. Unlike `Iterator::filter` the predicate runs inside the
+	/// scan, and it composes with `with_layers` and further `filter_components` calls.
+	pub fn filter_components<F: ComponentFilter<Q::Array>>(self, filter: F) -> FilteredIter<'a, Q, F> {
+		FilteredIter { iter: self, filter }
+	}
+}
+
+impl<'a, Q: QueryMut> QueryMutIter<'a, Q> {
+	/// The predicate only sees shared references, see `QueryIter::filter_components`
+	pub fn filter_components<F: ComponentFilter<Q::Array>>(self, filter: F) -> FilteredMutIter<'a, Q, F> {
+		FilteredMutIter { iter: self, filter }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+
+	struct Health(u32);
+	impl Component for Health {}
+	struct Armor(u32);
+	impl Component for Armor {}
+
+	#[test]
+	fn filtered() {
+		const BOSS: u64 = 1;
+
+		let mut ecs = ECS::new(16);
+		ecs.register::<Health>();
+		ecs.register::<Armor>();
+		for index in 0..16 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Health(index));
+			if index % 2 == 0 {
+				ecs.add_component(&entity, Armor(index * 10));
+			}
+			if index >= 12 {
+				ecs.set_tag(&entity, BOSS);
+			}
+		}
+
+		let wounded = ecs.query::<Health>().filter_components(|health: &Health| health.0 < 5);
+		assert_eq!(wounded.count(), 5);
+		let armored = ecs.query::<(Health, Armor)>()
+			.without_layers(BOSS)
+			.filter_components(|health: &Health, armor: &Armor| armor.0 > health.0 * 5)
+			.filter_components(|health: &Health, _: &Armor| health.0 > 2);
+		let indices: alloc::vec::Vec<_> = armored.map(|(_, health, _)| health.0).collect();
+		assert_eq!(indices, [4, 6, 8, 10]);
+
+		for (_, health) in ecs.query_mut::<Health>().with_layers(BOSS).filter_components(|health: &Health| health.0 % 2 == 1) {
+			health.0 = 0;
+		}
+		assert_eq!(ecs.query::<Health>().filter(|(_, health)| health.0 == 0).count(), 3);
+	}
+}
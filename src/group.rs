@@ -0,0 +1,450 @@
+use crate::component::ComponentID;
+use crate::storage::ComponentMap;
+use crate::{Component, Query, QueryMut, ECS};
+use core::cmp::Ordering;
+use alloc::vec::Vec;
+
+/// Components whose arrays keep every entity holding all of them packed into the first `len` slots,
+/// in the same order in each array, so iterating them reads each column front to back
+pub(crate) struct Group {
+	components: Vec<ComponentID>,
+	len: usize,
+}
+
+impl Group {
+	/// Slot `index` is stored at, if it is a member
+	fn member_slot(&self, index: usize, components: &ComponentMap) -> Option<usize> {
+		let slot = components.get_array_by_id(self.components[0])?.slot(index);
+		(slot < self.len).then_some(slot)
+	}
+
+	fn insert(&mut self, index: usize, components: &mut ComponentMap) {
+		if self.member_slot(index, components).is_some() { return; }
+		if !self.components.iter().all(|id| components.is_filled(*id, index)) { return; }
+		let Some(array) = components.get_array_by_id(self.components[0]) else { return };
+		let slot = array.slot(index);
+		components.swap_slots(&self.components, slot, self.len);
+		self.len += 1;
+	}
+
+	fn remove(&mut self, index: usize, components: &mut ComponentMap) {
+		let Some(slot) = self.member_slot(index, components) else { return };
+		self.len -= 1;
+		components.swap_slots(&self.components, slot, self.len);
+	}
+
+	/// Entity index stored at each packed slot
+	fn members<'a>(&self, components: &'a ComponentMap) -> &'a [usize] {
+		match components.get_array_by_id(self.components[0]).and_then(|array| array.order()) {
+			Some(order) => &order[..self.len],
+			None => &[],
+		}
+	}
+}
+
+pub(crate) struct Groups {
+	groups: Vec<Group>,
+}
+
+impl Groups {
+	pub(crate) fn new() -> Self {
+		Groups { groups: Vec::new() }
+	}
+
+	pub(crate) fn key(mut components: Vec<ComponentID>) -> Vec<ComponentID> {
+		components.sort();
+		components.dedup();
+		components
+	}
+
+	fn get(&self, components: &[ComponentID]) -> Option<&Group> {
+		self.groups.iter().find(|group| group.components == components)
+	}
+
+	pub(crate) fn is_owned(&self, id: ComponentID) -> bool {
+		self.owner(id).is_some()
+	}
+
+	/// Components of the group owning `id`, of which there is at most one
+	pub(crate) fn owner(&self, id: ComponentID) -> Option<&[ComponentID]> {
+		self.groups.iter().find(|group| group.components.contains(&id)).map(|group| &group.components[..])
+	}
+
+	pub(crate) fn members<'a>(&self, components: &[ComponentID], map: &'a ComponentMap) -> Option<&'a [usize]> {
+		Some(self.get(components)?.members(map))
+	}
+
+	/// Returns false if the group already existed
+	pub(crate) fn create(&mut self, components: Vec<ComponentID>, map: &mut ComponentMap, capacity: usize, alive: impl Fn(usize) -> bool) -> bool {
+		if self.get(&components).is_some() { return false; }
+		self.groups.push(Group { components, len: 0 });
+		Self::fill(self.groups.last_mut().unwrap(), map, capacity, alive);
+		true
+	}
+
+	/// Give every array of `group` the same order, then pack its members from scratch
+	fn fill(group: &mut Group, map: &mut ComponentMap, capacity: usize, alive: impl Fn(usize) -> bool) {
+		let order: Vec<usize> = match map.get_array_by_id(group.components[0]).and_then(|array| array.order()) {
+			Some(order) => order.to_vec(),
+			None => (0..capacity).collect(),
+		};
+		map.set_order(&group.components, &order);
+		group.len = 0;
+		// In the order already stored, so a packed group moves nothing
+		for index in order.into_iter().filter(|index| alive(*index)) {
+			group.insert(index, map);
+		}
+	}
+
+	pub(crate) fn on_insert(&mut self, id: ComponentID, index: usize, components: &mut ComponentMap) {
+		for group in self.groups.iter_mut().filter(|group| group.components.contains(&id)) {
+			group.insert(index, components);
+		}
+	}
+
+	pub(crate) fn on_remove(&mut self, id: ComponentID, index: usize, components: &mut ComponentMap) {
+		for group in self.groups.iter_mut().filter(|group| group.components.contains(&id)) {
+			group.remove(index, components);
+		}
+	}
+
+	pub(crate) fn on_destroy(&mut self, index: usize, components: &mut ComponentMap) {
+		for group in &mut self.groups {
+			group.remove(index, components);
+		}
+	}
+
+	/// Drop the group owning `id`, as when it is unregistered
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		self.groups.retain(|group| !group.components.contains(&id));
+	}
+
+	/// The same groups, all empty
+	pub(crate) fn duplicate_empty(&self) -> Self {
+		let groups = self.groups.iter().map(|group| Group { components: group.components.clone(), len: 0 }).collect();
+		Groups { groups }
+	}
+
+	/// Recompute every group's membership from scratch
+	pub(crate) fn rebuild(&mut self, components: &mut ComponentMap, capacity: usize, alive: impl Fn(usize) -> bool) {
+		for group in &mut self.groups {
+			Self::fill(group, components, capacity, &alive);
+		}
+	}
+}
+/// Iterates only the members of a group, every one of which is known to match, reading each column's packed slots in order
+pub struct GroupIter<'a, Q: Query + 'a> {
+	ecs: &'a ECS,
+	/// Entity index stored at each packed slot
+	members: &'a [usize],
+	slot: usize,
+	array: Q::Array,
+}
+
+impl<'a, Q: Query> GroupIter<'a, Q> {
+	pub(crate) fn new(ecs: &'a ECS, members: &'a [usize]) -> Self {
+		let array = unsafe { Q::get_array(ecs) };
+		GroupIter { ecs, members, slot: 0, array }
+	}
+}
+
+impl<'a, Q: Query> Iterator for GroupIter<'a, Q> {
+	type Item = Q::Output<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let index = *self.members.get(self.slot)?;
+		let slot = self.slot;
+		self.slot += 1;
+		Some(unsafe { Q::get_packed(self.ecs, index, slot, &self.array) })
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = self.members.len() - self.slot;
+		(remaining, Some(remaining))
+	}
+}
+
+impl<'a, Q: Query> ExactSizeIterator for GroupIter<'a, Q> {}
+
+pub struct GroupMutIter<'a, Q: QueryMut + 'a> {
+	ecs: &'a ECS,
+	members: &'a [usize],
+	slot: usize,
+	array: Q::Array,
+}
+
+impl<'a, Q: QueryMut> GroupMutIter<'a, Q> {
+	/// `ecs` must have come from an exclusive borrow
+	pub(crate) fn new(ecs: &'a ECS, members: &'a [usize]) -> Self {
+		let array = unsafe { Q::get_array(ecs) };
+		GroupMutIter { ecs, members, slot: 0, array }
+	}
+}
+
+impl<'a, Q: QueryMut> Iterator for GroupMutIter<'a, Q> {
+	type Item = Q::Output<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let index = *self.members.get(self.slot)?;
+		let slot = self.slot;
+		self.slot += 1;
+		Some(unsafe { Q::get_packed(self.ecs, index, slot, &self.array) })
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = self.members.len() - self.slot;
+		(remaining, Some(remaining))
+	}
+}
+
+impl<'a, Q: QueryMut> ExactSizeIterator for GroupMutIter<'a, Q> {}
+
+impl ECS {
+	/// Start keeping entities that hold every Component in `Q` packed at the front of those Components' columns,
+	/// so `query_group` walks them densely without occupancy checks. The group owns those columns, none of which
+	/// another group may own or may use a custom storage. Adds a swap to every insert and remove of those Components.
+	pub fn create_group<Q: Query>(&mut self) {
+		let key = Groups::key(Q::component_ids());
+		if self.groups.members(&key, &self.components).is_some() { return; }
+		if key.iter().any(|id| self.groups.is_owned(*id)) { fail!("Component already owned by another group") }
+		for id in &key {
+			match self.components.get_array_by_id(*id) {
+				Some(array) if array.info().has_custom_storage() => fail!("grouped Components must use the default storage"),
+				Some(_) => {},
+				None => fail!("Component not registered"),
+			}
+		}
+		let entities = &self.entities;
+		self.groups.create(key, &mut self.components, self.capacity, |index| entities[index].alive);
+	}
+
+	pub fn query_group<Q: Query>(&self) -> GroupIter<'_, Q> {
+		trace!(query = core::any::type_name::<Q>(), "query_group");
+		let key = Groups::key(Q::component_ids());
+		let members = self.groups.members(&key, &self.components).expect("no group created for this Query");
+		GroupIter::new(self, members)
+	}
+
+	pub fn query_group_mut<Q: QueryMut>(&mut self) -> GroupMutIter<'_, Q> {
+		trace!(query = core::any::type_name::<Q>(), "query_group_mut");
+		self.unshare(&Q::component_ids());
+		let key = Groups::key(Q::component_ids());
+		let ecs: &ECS = self;
+		let members = ecs.groups.members(&key, &ecs.components).expect("no group created for this Query");
+		GroupMutIter::new(ecs, members)
+	}
+
+	/// Order the group owning `C`, whichever other Components it holds, by `compare`. The values themselves
+	/// move, so `query_group` then walks memory in that order. This is a one-shot sort: later members are
+	/// packed at the end and a removal moves the last member into the gap, so sort again after membership
+	/// changes. Fails if no group owns `C`, see `create_group`.
+	pub fn sort<C: Component, F: FnMut(&C, &C) -> Ordering>(&mut self, mut compare: F) {
+		let Some(key) = self.groups.owner(ComponentID::of::<C>()) else { fail!("no group owns this Component") };
+		let key = key.to_vec();
+		let members = self.groups.members(&key, &self.components).unwrap();
+		let array = self.components.get_array::<C>().unwrap();
+		let mut order = members.to_vec();
+		order.sort_unstable_by(|a, b| unsafe { compare(array.get_unchecked(*a), array.get_unchecked(*b)) });
+		self.components.set_order(&key, &order);
+	}
+
+	pub fn sort_by_key<C: Component, K: Ord, F: FnMut(&C) -> K>(&mut self, mut key: F) {
+		self.sort::<C, _>(|a, b| key(a).cmp(&key(b)));
+	}
+
+	/// Like `sort_by_key` but computes each key once and keeps equal keys in their current order, for sorting
+	/// sprites by material or particles by depth once per frame. Entities keep their ids, only where their values
+	/// are stored changes. Returns how many members moved.
+	pub fn sort_column<C: Component, K: Ord>(&mut self, mut key: impl FnMut(&C) -> K) -> usize {
+		let Some(ids) = self.groups.owner(ComponentID::of::<C>()) else { fail!(0, "no group owns this Component") };
+		let ids = ids.to_vec();
+		let members = self.groups.members(&ids, &self.components).unwrap();
+		let array = self.components.get_array::<C>().unwrap();
+		let mut order = members.to_vec();
+		order.sort_by_cached_key(|index| key(unsafe { array.get_unchecked::<C>(*index) }));
+		let moved = order.iter().zip(members).filter(|(new, old)| new != old).count();
+		if moved == 0 { return 0; }
+		debug!(moved, component = core::any::type_name::<C>(), "sort column");
+		self.components.set_order(&ids, &order);
+		moved
+	}
+
+	pub fn group_len<Q: Query>(&self) -> Option<usize> {
+		let key = Groups::key(Q::component_ids());
+		self.groups.members(&key, &self.components).map(|members| members.len())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+
+	struct Position(usize);
+	impl Component for Position {}
+	struct Velocity(usize);
+	impl Component for Velocity {}
+
+	#[test]
+	fn membership() {
+		const CAPACITY: usize = 64;
+		let mut ecs = ECS::new(CAPACITY);
+		ecs.register::<Position>();
+		ecs.register::<Velocity>();
+
+		let mut entities = alloc::vec::Vec::new();
+		for index in 0..CAPACITY / 2 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(index));
+			if index % 2 == 0 {
+				ecs.add_component(&entity, Velocity(1));
+			}
+			entities.push(entity);
+		}
+
+		ecs.create_group::<(Position, Velocity)>();
+		assert_eq!(ecs.group_len::<(Velocity, Position)>(), Some(CAPACITY / 4));
+
+		ecs.add_component(&entities[1], Velocity(1));
+		ecs.remove_component::<Velocity>(&entities[0]);
+		ecs.destroy_entity(entities[2]);
+		assert_eq!(ecs.query_group::<(Position, Velocity)>().len(), CAPACITY / 4 - 1);
+
+		let mut grouped = 0;
+		for (id, position, velocity) in ecs.query_group::<(Position, Velocity)>() {
+			assert!(ecs.is_valid(&id));
+			assert_eq!(velocity.0, 1);
+			assert!(position.0 % 2 == 0 || position.0 == 1);
+			grouped += 1;
+		}
+		assert_eq!(grouped, ecs.query::<(Position, Velocity)>().count());
+	}
+
+	#[test]
+	fn packed() {
+		let mut ecs = ECS::new(64);
+		ecs.register::<Position>();
+		ecs.register::<Velocity>();
+		let entities: alloc::vec::Vec<_> = (0..40).map(|index| {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(index));
+			if index % 3 == 0 {
+				ecs.add_component(&entity, Velocity(index));
+			}
+			entity
+		}).collect();
+		ecs.create_group::<(Position, Velocity)>();
+		ecs.remove_component::<Velocity>(&entities[3]);
+		ecs.add_component(&entities[4], Velocity(4));
+
+		let grouped: alloc::vec::Vec<_> = ecs.query_group::<(Position, Velocity)>().collect();
+		for (slot, (_, position, velocity)) in grouped.iter().enumerate() {
+			assert_eq!(position.0, velocity.0);
+			let first = (grouped[0].1 as *const Position, grouped[0].2 as *const Velocity);
+			assert_eq!((*position as *const Position, *velocity as *const Velocity), (first.0.wrapping_add(slot), first.1.wrapping_add(slot)));
+		}
+		assert_eq!(grouped.len(), 14);
+		for (index, entity) in entities.iter().enumerate() {
+			assert_eq!(ecs.get_component::<Position>(entity).unwrap().0, index);
+		}
+		let mut queried: alloc::vec::Vec<_> = ecs.query::<(Position, Velocity)>().map(|(_, position, _)| position.0).collect();
+		queried.sort();
+		let mut expected: alloc::vec::Vec<_> = grouped.iter().map(|(_, position, _)| position.0).collect();
+		expected.sort();
+		assert_eq!(queried, expected);
+	}
+
+	#[test]
+	#[cfg(not(feature = "panic-free"))]
+	#[should_panic(expected = "already owned")]
+	fn owned_once() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Position>();
+		ecs.register::<Velocity>();
+		ecs.create_group::<(Position, Velocity)>();
+		ecs.create_group::<Position>();
+	}
+
+	#[test]
+	fn sorted() {
+		let mut ecs = ECS::new(32);
+		ecs.register::<Position>();
+		for value in [7, 3, 9, 1, 4, 8] {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(value));
+		}
+		ecs.create_group::<Position>();
+
+		ecs.sort_by_key::<Position, _, _>(|position| position.0);
+		let sorted: alloc::vec::Vec<_> = ecs.query_group::<Position>().map(|(_, position)| position.0).collect();
+		assert_eq!(sorted, [1, 3, 4, 7, 8, 9]);
+
+		ecs.sort::<Position, _>(|a, b| b.0.cmp(&a.0));
+		let (first, _) = ecs.query_group::<Position>().next().unwrap();
+		ecs.destroy_entity(first);
+		let sorted: alloc::vec::Vec<_> = ecs.query_group::<Position>().map(|(_, position)| position.0).collect();
+		assert_eq!(sorted, [1, 8, 7, 4, 3]);
+	}
+
+	#[test]
+	fn sorted_multiple() {
+		let mut ecs = ECS::new(32);
+		ecs.register::<Position>();
+		ecs.register::<Velocity>();
+		ecs.create_group::<(Position, Velocity)>();
+		for value in [5, 2, 8, 6] {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(value));
+			ecs.add_component(&entity, Velocity(value * 10));
+		}
+
+		ecs.sort_by_key::<Velocity, _, _>(|velocity| velocity.0);
+		let sorted: alloc::vec::Vec<_> = ecs.query_group::<(Position, Velocity)>().map(|(_, position, velocity)| (position.0, velocity.0)).collect();
+		assert_eq!(sorted, [(2, 20), (5, 50), (6, 60), (8, 80)]);
+	}
+
+	#[test]
+	fn sort_column() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<Position>();
+		ecs.register::<Velocity>();
+		ecs.create_group::<(Position, Velocity)>();
+		let entities: alloc::vec::Vec<_> = [5, 2, 9, 2, 7].iter().enumerate().map(|(index, value)| {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(*value));
+			ecs.add_component(&entity, Velocity(index));
+			entity
+		}).collect();
+
+		assert_eq!(ecs.sort_column::<Position, _>(|position| position.0), 5);
+		let sorted: alloc::vec::Vec<_> = ecs.query_group::<(Position, Velocity)>().map(|(entity, position, velocity)| (entity.index, position.0, velocity.0)).collect();
+		assert_eq!(sorted, [(1, 2, 1), (3, 2, 3), (0, 5, 0), (4, 7, 4), (2, 9, 2)]);
+		for (index, entity) in entities.iter().enumerate() {
+			assert!(ecs.is_valid(entity));
+			assert_eq!(ecs.get_component::<Velocity>(entity).unwrap().0, index);
+		}
+		assert_eq!(ecs.sort_column::<Position, _>(|position| position.0), 0);
+	}
+
+	#[test]
+	#[cfg(not(feature = "panic-free"))]
+	#[should_panic(expected = "no group owns")]
+	fn sort_ungrouped() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Position>();
+		ecs.sort_by_key::<Position, _, _>(|position| position.0);
+	}
+
+	#[test]
+	fn single_mut() {
+		let mut ecs = ECS::new(16);
+		ecs.register::<Position>();
+		ecs.create_group::<Position>();
+
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Position(1));
+		for (_, position) in ecs.query_group_mut::<Position>() {
+			position.0 += 1;
+		}
+		assert_eq!(ecs.get_component::<Position>(&entity).unwrap().0, 2);
+	}
+}
@@ -0,0 +1,137 @@
+use crate::component::ComponentID;
+use crate::named::ByteAccess;
+use crate::{EntityID, Replicated, ECS};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+/// Guest side stand in for an `EntityID`: the low 32 bits are the index and the high 32 the generation,
+/// truncated. Carries no scene, so a handle is only meaningful to the bridge of the world it came from.
+pub type GuestHandle = u64;
+
+/// Returned where no entity could be produced, never a valid handle
+pub const NULL_HANDLE: GuestHandle = u64::MAX;
+
+pub fn encode_handle(entity: &EntityID) -> GuestHandle {
+	(entity.generation as u32 as u64) << 32 | entity.index as u32 as u64
+}
+
+/// The world as a script guest sees it: entities are `GuestHandle`s, Components are numbered and move as
+/// bytes through `Replicated`, so a guest can never observe or forge the memory of a value. Every function
+/// takes only integers and byte slices to keep binding it into a wasm host a matter of copying buffers
+/// across linear memory. Only Components registered with `expose` are reachable.
+#[derive(Clone, Default)]
+pub struct GuestBridge {
+	components: HashMap<u32, ByteAccess>,
+}
+
+impl GuestBridge {
+	pub fn new() -> Self {
+		GuestBridge::default()
+	}
+
+	/// Let guests reach `C` as `guest_id`, registering it on `ecs` if needed. Returns false if the id is taken.
+	pub fn expose<C: Replicated>(&mut self, ecs: &mut ECS, guest_id: u32) -> bool {
+		if self.components.contains_key(&guest_id) { return false; }
+		if ecs.get_component_info::<C>().is_none() {
+			ecs.register::<C>();
+		}
+		self.components.insert(guest_id, ByteAccess::of::<C>());
+		true
+	}
+
+	/// The live entity behind `handle`
+	pub fn resolve(&self, ecs: &ECS, handle: GuestHandle) -> Option<EntityID> {
+		let entity = ecs.get_index((handle as u32) as usize)?;
+		(entity.generation as u32 == (handle >> 32) as u32).then_some(entity)
+	}
+
+	/// `NULL_HANDLE` if the world is full
+	pub fn create_entity(&self, ecs: &mut ECS) -> GuestHandle {
+		ecs.create_entity().map_or(NULL_HANDLE, |entity| encode_handle(&entity))
+	}
+
+	pub fn destroy_entity(&self, ecs: &mut ECS, handle: GuestHandle) -> bool {
+		let Some(entity) = self.resolve(ecs, handle) else { return false };
+		ecs.destroy_entity(entity);
+		true
+	}
+
+	pub fn is_alive(&self, ecs: &ECS, handle: GuestHandle) -> bool {
+		self.resolve(ecs, handle).is_some()
+	}
+
+	/// Append the serialized value to `out`. False if the entity, Component, or value is missing.
+	pub fn get_component(&self, ecs: &ECS, handle: GuestHandle, guest_id: u32, out: &mut Vec<u8>) -> bool {
+		let (Some(entity), Some(component)) = (self.resolve(ecs, handle), self.components.get(&guest_id)) else { return false };
+		(component.read)(ecs, &entity, out)
+	}
+
+	/// Add or overwrite from serialized bytes. False if the entity or Component is unknown or the bytes don't decode.
+	pub fn set_component(&self, ecs: &mut ECS, handle: GuestHandle, guest_id: u32, bytes: &[u8]) -> bool {
+		let (Some(entity), Some(component)) = (self.resolve(ecs, handle), self.components.get(&guest_id)) else { return false };
+		(component.write)(ecs, &entity, bytes)
+	}
+
+	/// False if there was nothing to remove
+	pub fn remove_component(&self, ecs: &mut ECS, handle: GuestHandle, guest_id: u32) -> bool {
+		let (Some(entity), Some(component)) = (self.resolve(ecs, handle), self.components.get(&guest_id)) else { return false };
+		(component.remove)(ecs, &entity)
+	}
+
+	/// Handles of every entity holding all of `guest_ids`, in index order. Nothing matches an unexposed id.
+	pub fn query(&self, ecs: &ECS, guest_ids: &[u32]) -> Vec<GuestHandle> {
+		let ids: Option<Vec<ComponentID>> = guest_ids.iter().map(|guest_id| self.components.get(guest_id).map(|component| component.id)).collect();
+		let Some(ids) = ids else { return Vec::new() };
+		let Some(arrays) = ids.iter().map(|id| ecs.components.get_array_by_id(*id)).collect::<Option<Vec<_>>>() else { return Vec::new() };
+		(0..ecs.capacity).filter(|index| arrays.iter().all(|array| array.is_filled(*index)))
+			.filter_map(|index| ecs.get_index(index))
+			.map(|entity| encode_handle(&entity))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{GuestBridge, NULL_HANDLE};
+	use crate::{Component, Replicated, ECS};
+	use alloc::vec::Vec;
+
+	#[derive(Debug, PartialEq)]
+	struct Health(u32);
+	impl Component for Health {}
+	impl Replicated for Health {
+		fn serialize(&self, out: &mut Vec<u8>) { out.extend_from_slice(&self.0.to_le_bytes()); }
+		fn deserialize(bytes: &[u8]) -> Option<Self> { Some(Health(u32::from_le_bytes(bytes.try_into().ok()?))) }
+	}
+
+	#[test]
+	fn guest() {
+		const HEALTH: u32 = 1;
+		let mut ecs = ECS::new(2);
+		let mut bridge = GuestBridge::new();
+		assert!(bridge.expose::<Health>(&mut ecs, HEALTH));
+		assert!(!bridge.expose::<Health>(&mut ecs, HEALTH));
+
+		let first = bridge.create_entity(&mut ecs);
+		let second = bridge.create_entity(&mut ecs);
+		assert_eq!(bridge.create_entity(&mut ecs), NULL_HANDLE);
+		assert!(bridge.set_component(&mut ecs, second, HEALTH, &9u32.to_le_bytes()));
+		assert!(!bridge.set_component(&mut ecs, first, HEALTH, &[1, 2]));
+		assert!(!bridge.set_component(&mut ecs, first, 2, &9u32.to_le_bytes()));
+		assert_eq!(bridge.query(&ecs, &[HEALTH]), [second]);
+		assert!(bridge.query(&ecs, &[HEALTH, 2]).is_empty());
+
+		let mut out = Vec::new();
+		assert!(bridge.get_component(&ecs, second, HEALTH, &mut out));
+		assert_eq!(out, 9u32.to_le_bytes());
+		assert_eq!(ecs.get_component::<Health>(&bridge.resolve(&ecs, second).unwrap()), Some(&Health(9)));
+
+		assert!(bridge.remove_component(&mut ecs, second, HEALTH));
+		assert!(!bridge.remove_component(&mut ecs, second, HEALTH));
+		assert!(bridge.destroy_entity(&mut ecs, first));
+		assert!(!bridge.is_alive(&ecs, first));
+		let reused = bridge.create_entity(&mut ecs);
+		assert_ne!(reused, first);
+		assert!(!bridge.destroy_entity(&mut ecs, first));
+	}
+}
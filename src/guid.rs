@@ -0,0 +1,157 @@
+use crate::{EntityID, ECS};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+/// Persistent identity of an entity, unlike `EntityID` it survives saving, loading and `transfer_entity`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Guid(pub u64);
+
+/// Bijective mix so consecutive counters give unrelated looking ids that still never collide
+const fn mix(mut value: u64) -> u64 {
+	value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+	value ^ (value >> 31)
+}
+
+#[derive(Clone)]
+pub(crate) struct Guids {
+	/// `None` until `enable_guids`
+	next: Option<u64>,
+	by_index: Vec<Option<Guid>>,
+	by_guid: HashMap<Guid, usize>,
+}
+
+impl Guids {
+	pub(crate) fn new(length: usize) -> Self {
+		Guids { next: None, by_index: alloc::vec![None; length], by_guid: HashMap::new() }
+	}
+
+	fn generate(&mut self) -> Option<Guid> {
+		loop {
+			let next = self.next.as_mut()?;
+			let guid = Guid(mix(*next));
+			*next = next.wrapping_add(1);
+			// Only possible after `set_guid` claimed a generated value
+			if !self.by_guid.contains_key(&guid) { return Some(guid); }
+		}
+	}
+
+	fn assign(&mut self, index: usize, guid: Guid) {
+		if let Some(old) = self.by_index[index].replace(guid) {
+			self.by_guid.remove(&old);
+		}
+		self.by_guid.insert(guid, index);
+	}
+
+	pub(crate) fn on_create(&mut self, index: usize) {
+		if let Some(guid) = self.generate() {
+			self.assign(index, guid);
+		}
+	}
+
+	pub(crate) fn on_destroy(&mut self, index: usize) {
+		if let Some(guid) = self.by_index[index].take() {
+			self.by_guid.remove(&guid);
+		}
+	}
+
+	pub(crate) fn move_index(&mut self, from: usize, to: usize) {
+		if let Some(guid) = self.by_index[from].take() {
+			self.by_index[to] = Some(guid);
+			self.by_guid.insert(guid, to);
+		}
+	}
+
+	pub(crate) fn resize(&mut self, new_length: usize) {
+		self.by_index.resize(new_length, None);
+	}
+
+	pub(crate) fn get(&self, index: usize) -> Option<Guid> { self.by_index[index] }
+
+	pub(crate) fn find(&self, guid: Guid) -> Option<usize> { self.by_guid.get(&guid).copied() }
+}
+
+impl ECS {
+	/// Give every alive and future entity a `Guid`. Ids are derived from `seed`, so worlds whose
+	/// entities may meet, through `transfer_entity` or a shared save, should use different seeds.
+	pub fn enable_guids(&mut self, seed: u64) {
+		self.flush();
+		self.guids.next = Some(seed);
+		for index in 0..self.capacity {
+			if self.entities[index].alive && self.guids.by_index[index].is_none() {
+				self.guids.on_create(index);
+			}
+		}
+	}
+
+	pub fn get_guid(&self, entity: &EntityID) -> Option<Guid> {
+		if !self.is_valid(entity) { return None; }
+		self.guids.by_index[entity.index]
+	}
+
+	/// Overwrite the `Guid` of `entity`, as done when loading a save. Returns false if `entity`
+	/// is invalid or another entity already holds `guid`.
+	pub fn set_guid(&mut self, entity: &EntityID, guid: Guid) -> bool {
+		if !self.is_valid(entity) { return false; }
+		match self.guids.by_guid.get(&guid) {
+			Some(index) => *index == entity.index,
+			None => {
+				self.guids.assign(entity.index, guid);
+				true
+			},
+		}
+	}
+
+	pub fn find_by_guid(&self, guid: Guid) -> Option<EntityID> {
+		let index = *self.guids.by_guid.get(&guid)?;
+		self.get_index(index)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Guid, SceneRemapper, ECS};
+
+	#[test]
+	fn lookup() {
+		let mut ecs = ECS::new(4);
+		let early = ecs.create_entity().unwrap();
+		ecs.enable_guids(7);
+		let late = ecs.create_entity().unwrap();
+		let reserved = ecs.reserve_entity();
+		ecs.flush();
+
+		let guids = [early, late, reserved].map(|entity| ecs.get_guid(&entity).unwrap());
+		assert!(guids[0] != guids[1] && guids[1] != guids[2] && guids[0] != guids[2]);
+		for (entity, guid) in [early, late, reserved].iter().zip(guids) {
+			assert!(ecs.find_by_guid(guid) == Some(*entity));
+		}
+
+		ecs.destroy_entity(late);
+		assert!(ecs.find_by_guid(guids[1]).is_none());
+		assert!(!ecs.set_guid(&early, guids[2]));
+		assert!(ecs.set_guid(&early, Guid(1)));
+		assert!(ecs.find_by_guid(guids[0]).is_none());
+		assert!(ecs.find_by_guid(Guid(1)) == Some(early));
+	}
+
+	#[test]
+	fn preserved() {
+		let mut room = ECS::new(4);
+		room.enable_guids(1);
+		let mut lobby = ECS::new(4);
+		lobby.enable_guids(2);
+
+		let player = room.create_entity().unwrap();
+		let guid = room.get_guid(&player).unwrap();
+		let checkpoint = room.save_checkpoint();
+		room.destroy_entity(player);
+		assert!(room.rollback_to(checkpoint));
+		assert!(room.find_by_guid(guid) == Some(player));
+
+		let mut remapper = SceneRemapper::new(&room, &lobby);
+		let moved = room.transfer_entity(player, &mut lobby, &mut remapper).unwrap();
+		assert!(room.find_by_guid(guid).is_none());
+		assert!(lobby.find_by_guid(guid) == Some(moved));
+	}
+}
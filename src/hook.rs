@@ -0,0 +1,235 @@
+use crate::component::ComponentID;
+use crate::storage::Occupancy;
+use crate::{Component, EntityID, ECS};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+type DropHook = Box<dyn FnMut(EntityID, *mut u8)>;
+type SetHook = Box<dyn FnMut(EntityID, *const u8)>;
+
+/// User callbacks run on Components about to be dropped
+#[derive(Default)]
+pub(crate) struct DropHooks {
+	map: HashMap<ComponentID, DropHook>,
+}
+
+impl DropHooks {
+	pub(crate) fn is_empty(&self) -> bool { self.map.is_empty() }
+
+	pub(crate) fn ids(&self) -> Vec<ComponentID> { self.map.keys().copied().collect() }
+
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		self.map.remove(&id);
+	}
+
+	/// `pointer` must point to the `id` Component held by `entity`, about to be dropped
+	pub(crate) unsafe fn run(&mut self, id: ComponentID, entity: EntityID, pointer: *mut u8) {
+		if let Some(hook) = self.map.get_mut(&id) {
+			hook(entity, pointer);
+		}
+	}
+}
+
+/// User callbacks run once per tick on Components written since the last `run_set_hooks`
+#[derive(Default)]
+pub(crate) struct SetHooks {
+	map: HashMap<ComponentID, (SetHook, Occupancy)>,
+}
+
+impl SetHooks {
+	pub(crate) fn mark(&mut self, id: ComponentID, index: usize) {
+		if let Some((_, written)) = self.map.get_mut(&id) {
+			written.set(index, true);
+		}
+	}
+
+	/// Forget writes to a value that is leaving
+	pub(crate) fn unmark(&mut self, id: ComponentID, index: usize) {
+		if let Some((_, written)) = self.map.get_mut(&id) {
+			written.set(index, false);
+		}
+	}
+
+	pub(crate) fn on_destroy(&mut self, index: usize) {
+		for (_, written) in self.map.values_mut() {
+			written.set(index, false);
+		}
+	}
+
+	/// Pending writes follow entities relocated between `a` and `b`
+	pub(crate) fn swap_index(&mut self, a: usize, b: usize) {
+		for (_, written) in self.map.values_mut() {
+			let written_a = written.get(a);
+			written.set(a, written.get(b));
+			written.set(b, written_a);
+		}
+	}
+
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		self.map.remove(&id);
+	}
+
+	pub(crate) fn resize(&mut self, new_length: usize) {
+		for (_, written) in self.map.values_mut() {
+			written.resize(new_length);
+		}
+	}
+}
+
+impl ECS {
+	/// Run `hook` just before any `C` is dropped, whether removed, overwritten, destroyed with its entity,
+	/// unregistered, rolled back, or dropped with the ECS, so Components owning external handles can be
+	/// cleaned up in one place. Values handed back by `take_component` or `replace_component` aren't dropped
+	/// here and so don't run it. Replaces any hook `C` already had. Copies from `clone_world` and `fork`
+	/// start without hooks.
+	pub fn set_drop_hook<C: Component>(&mut self, mut hook: impl FnMut(EntityID, &mut C) + 'static) {
+		let hook: DropHook = Box::new(move |entity, pointer| hook(entity, unsafe { &mut *(pointer as *mut C) }));
+		self.drop_hooks.map.insert(ComponentID::of::<C>(), hook);
+	}
+
+	/// Returns false if `C` had no hook
+	pub fn remove_drop_hook<C: Component>(&mut self) -> bool {
+		self.drop_hooks.map.remove(&ComponentID::of::<C>()).is_some()
+	}
+
+	/// Call `hook` with the current value of every `C` overwritten by `add_component` or borrowed through
+	/// `get_component_mut` since the last `run_set_hooks`, once per entity however many writes there were.
+	/// `Schedule::run` calls `run_set_hooks` at the end of every tick. Replaces any hook `C` already had.
+	pub fn set_on_set_hook<C: Component>(&mut self, mut hook: impl FnMut(EntityID, &C) + 'static) {
+		let hook: SetHook = Box::new(move |entity, pointer| hook(entity, unsafe { &*(pointer as *const C) }));
+		self.set_hooks.map.insert(ComponentID::of::<C>(), (hook, Occupancy::new(self.capacity)));
+	}
+
+	/// Returns false if `C` had no hook
+	pub fn remove_on_set_hook<C: Component>(&mut self) -> bool {
+		self.set_hooks.map.remove(&ComponentID::of::<C>()).is_some()
+	}
+
+	/// Call every on-set hook for the values written since the last call, in index order per Component
+	pub fn run_set_hooks(&mut self) {
+		let scene_id = self.get_scene_id();
+		for (id, (hook, written)) in self.set_hooks.map.iter_mut() {
+			let Some(array) = self.components.get_array_by_id(*id) else { continue };
+			for index in written.iter() {
+				let entity = self.entities[index];
+				if let (true, Some(pointer)) = (entity.alive, array.get_ptr(index)) {
+					hook(EntityID { scene_id, index, generation: entity.generation }, pointer);
+				}
+			}
+			*written = Occupancy::new(self.capacity);
+		}
+	}
+
+	/// Called before the `id` value at `index` is dropped, while its entity is still alive
+	pub(crate) fn run_drop_hook(&mut self, id: ComponentID, index: usize) {
+		if !self.drop_hooks.map.contains_key(&id) { return; }
+		let Some(entity) = self.get_index(index) else { return };
+		self.components.unshare_index(id, index);
+		let Some(pointer) = self.components.get_array_by_id(id).and_then(|array| array.get_ptr(index)) else { return };
+		unsafe { self.drop_hooks.run(id, entity, pointer as *mut u8) };
+	}
+
+	/// `run_drop_hook` for every hooked Component of the entity at `index`
+	pub(crate) fn run_drop_hooks(&mut self, index: usize) {
+		if self.drop_hooks.is_empty() { return; }
+		for id in self.drop_hooks.ids() {
+			self.run_drop_hook(id, index);
+		}
+	}
+}
+
+impl Drop for ECS {
+	fn drop(&mut self) {
+		if self.drop_hooks.is_empty() { return; }
+		for index in 0..self.capacity {
+			self.run_drop_hooks(index);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+	use alloc::sync::Arc;
+	use core::sync::atomic::{AtomicUsize, Ordering};
+
+	#[derive(Clone)]
+	struct Handle(usize);
+	impl Component for Handle {}
+
+	#[test]
+	fn drop_hook() {
+		let closed = Arc::new(AtomicUsize::new(0));
+		let mut ecs = ECS::new(8);
+		ecs.register_cloneable::<Handle>();
+		let counter = closed.clone();
+		ecs.set_drop_hook::<Handle>(move |entity, handle| {
+			assert_eq!(entity.index, handle.0 % 8);
+			counter.fetch_add(1, Ordering::Relaxed);
+		});
+
+		let entities: alloc::vec::Vec<_> = (0..5).map(|_| ecs.create_entity().unwrap()).collect();
+		for entity in &entities {
+			ecs.add_component(entity, Handle(entity.index));
+		}
+		let checkpoint = ecs.save_checkpoint();
+		ecs.remove_component::<Handle>(&entities[0]);
+		ecs.destroy_entity(entities[1]);
+		ecs.add_component(&entities[2], Handle(10));
+		ecs.add_components([(entities[3], Handle(11))]);
+		ecs.take_component::<Handle>(&entities[4]);
+		assert_eq!(closed.load(Ordering::Relaxed), 4);
+
+		// The two values still held are replaced by clones from the checkpoint
+		assert!(ecs.rollback_to(checkpoint));
+		assert_eq!(closed.load(Ordering::Relaxed), 6);
+		drop(ecs);
+		assert_eq!(closed.load(Ordering::Relaxed), 11);
+	}
+
+	#[test]
+	fn on_set() {
+		let written = Arc::new(AtomicUsize::new(0));
+		let mut ecs = ECS::new(4);
+		ecs.register::<Handle>();
+		let counter = written.clone();
+		ecs.set_on_set_hook::<Handle>(move |entity, handle| {
+			assert_eq!(entity.index * 10, handle.0);
+			counter.fetch_add(1, Ordering::Relaxed);
+		});
+
+		let entities: alloc::vec::Vec<_> = (0..3).map(|_| ecs.create_entity().unwrap()).collect();
+		for entity in &entities {
+			ecs.add_component(entity, Handle(0));
+		}
+		ecs.run_set_hooks();
+		assert_eq!(written.load(Ordering::Relaxed), 0);
+
+		ecs.add_component(&entities[1], Handle(10));
+		ecs.get_component_mut::<Handle>(&entities[1]).unwrap().0 = 10;
+		ecs.get_component_mut::<Handle>(&entities[2]).unwrap().0 = 20;
+		ecs.get_component_mut::<Handle>(&entities[0]).unwrap().0 = 5;
+		ecs.destroy_entity(entities[0]);
+		ecs.create_entity().unwrap();
+		let mut schedule = crate::Schedule::new();
+		schedule.run(&mut ecs);
+		assert_eq!(written.load(Ordering::Relaxed), 2);
+		ecs.run_set_hooks();
+		assert_eq!(written.load(Ordering::Relaxed), 2);
+	}
+
+	#[test]
+	fn unregister() {
+		let closed = Arc::new(AtomicUsize::new(0));
+		let mut ecs = ECS::new(4);
+		ecs.register::<Handle>();
+		let counter = closed.clone();
+		ecs.set_drop_hook::<Handle>(move |_, _| { counter.fetch_add(1, Ordering::Relaxed); });
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Handle(0));
+		ecs.unregister::<Handle>();
+		assert_eq!(closed.load(Ordering::Relaxed), 1);
+		assert!(!ecs.remove_drop_hook::<Handle>());
+	}
+}
@@ -0,0 +1,275 @@
+use crate::component::ComponentID;
+use crate::storage::ComponentMap;
+use crate::{Component, EntityID, ECS};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::hash::Hash;
+use hashbrown::HashMap;
+
+/// Acceleration structure kept in sync with one Component, see `ECS::add_entity_index`. Inserts, removals,
+/// overwrites, and destruction are reported, but writes through `get_component_mut` or mutable queries
+/// are not. Go through `ECS::modify_component` for values an index depends on.
+pub trait EntityIndex<C: Component>: Send + Sync + 'static {
+	/// `component` was just put on `entity`
+	fn insert(&mut self, entity: EntityID, component: &C);
+
+	/// `component` is about to leave `entity`, whether removed, overwritten, or destroyed with it
+	fn remove(&mut self, entity: EntityID, component: &C);
+
+	/// Forget every entity before the index is refilled from scratch
+	fn clear(&mut self);
+}
+
+type NotifyFn = unsafe fn(&mut dyn Any, EntityID, *const u8);
+
+struct IndexSlot {
+	id: ComponentID,
+	index: Box<dyn Any + Send + Sync>,
+	insert: NotifyFn,
+	remove: NotifyFn,
+	clear: fn(&mut dyn Any),
+}
+
+unsafe fn insert<C: Component, I: EntityIndex<C>>(index: &mut dyn Any, entity: EntityID, pointer: *const u8) {
+	index.downcast_mut::<I>().unwrap().insert(entity, &*(pointer as *const C));
+}
+
+unsafe fn remove<C: Component, I: EntityIndex<C>>(index: &mut dyn Any, entity: EntityID, pointer: *const u8) {
+	index.downcast_mut::<I>().unwrap().remove(entity, &*(pointer as *const C));
+}
+
+fn clear<C: Component, I: EntityIndex<C>>(index: &mut dyn Any) {
+	index.downcast_mut::<I>().unwrap().clear();
+}
+
+#[derive(Default)]
+pub(crate) struct Indexes {
+	slots: Vec<IndexSlot>,
+}
+
+impl Indexes {
+	pub(crate) fn watches(&self, id: ComponentID) -> bool {
+		self.slots.iter().any(|slot| slot.id == id)
+	}
+
+	/// `pointer` must point to the `id` Component just inserted on `entity`
+	pub(crate) unsafe fn on_insert(&mut self, id: ComponentID, entity: EntityID, pointer: *const u8) {
+		for slot in self.slots.iter_mut().filter(|slot| slot.id == id) {
+			(slot.insert)(slot.index.as_mut(), entity, pointer);
+		}
+	}
+
+	/// `pointer` must point to the `id` Component about to leave `entity`
+	pub(crate) unsafe fn on_remove(&mut self, id: ComponentID, entity: EntityID, pointer: *const u8) {
+		for slot in self.slots.iter_mut().filter(|slot| slot.id == id) {
+			(slot.remove)(slot.index.as_mut(), entity, pointer);
+		}
+	}
+
+	/// Called before `entity`'s Components are dropped
+	pub(crate) fn on_destroy(&mut self, entity: EntityID, components: &ComponentMap) {
+		for slot in &mut self.slots {
+			if let Some(pointer) = components.get_array_by_id(slot.id).and_then(|array| array.get_ptr(entity.index)) {
+				unsafe { (slot.remove)(slot.index.as_mut(), entity, pointer) };
+			}
+		}
+	}
+
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		self.slots.retain(|slot| slot.id != id);
+	}
+}
+
+impl ECS {
+	/// Keep `index` in sync with every `C` from now on, filling it from the entities already holding one.
+	/// Replaces any index of the same type. Writes through `get_component_mut`, `get_or_insert_with` or mutable
+	/// queries hand out plain references and can't be seen, so change indexed values with `modify_component`.
+	pub fn add_entity_index<C: Component, I: EntityIndex<C>>(&mut self, index: I) {
+		self.remove_entity_index::<I>();
+		let id = ComponentID::of::<C>();
+		let mut slot = IndexSlot { id, index: Box::new(index), insert: insert::<C, I>, remove: remove::<C, I>, clear: clear::<C, I> };
+		fill(&mut slot, self);
+		self.indexes.slots.push(slot);
+	}
+
+	pub fn get_entity_index<I: 'static>(&self) -> Option<&I> {
+		self.indexes.slots.iter().find_map(|slot| slot.index.downcast_ref::<I>())
+	}
+
+	/// Stop maintaining the index of type `I` and hand it back
+	pub fn remove_entity_index<I: 'static>(&mut self) -> Option<I> {
+		let slots = &mut self.indexes.slots;
+		let position = slots.iter().position(|slot| slot.index.as_ref().type_id() == TypeId::of::<I>())?;
+		let slot = slots.remove(position);
+		Some(*slot.index.downcast::<I>().unwrap())
+	}
+
+	/// Change `C` on `entity` in place, keeping indexes of `C` in sync. `None` if `entity` doesn't hold one.
+	pub fn modify_component<C: Component, R>(&mut self, entity: &EntityID, modify: impl FnOnce(&mut C) -> R) -> Option<R> {
+		if !self.has_component::<C>(entity) { return None; }
+		let id = ComponentID::of::<C>();
+		self.index_remove(id, entity.index);
+		let result = modify(self.get_mut_at::<C>(entity.index)?);
+		self.index_insert(id, entity.index);
+		Some(result)
+	}
+
+	pub(crate) fn index_insert(&mut self, id: ComponentID, index: usize) {
+		if !self.indexes.watches(id) { return; }
+		let Some(pointer) = self.components.get_array_by_id(id).and_then(|array| array.get_ptr(index)) else { return };
+		let entity = self.get_index(index).unwrap();
+		unsafe { self.indexes.on_insert(id, entity, pointer) };
+	}
+
+	pub(crate) fn index_remove(&mut self, id: ComponentID, index: usize) {
+		if !self.indexes.watches(id) { return; }
+		let Some(pointer) = self.components.get_array_by_id(id).and_then(|array| array.get_ptr(index)) else { return };
+		let entity = self.get_index(index).unwrap();
+		unsafe { self.indexes.on_remove(id, entity, pointer) };
+	}
+
+	/// Refill every index after storage changed wholesale, as on rollback or compaction
+	pub(crate) fn rebuild_indexes(&mut self) {
+		let mut indexes = core::mem::take(&mut self.indexes);
+		for slot in &mut indexes.slots {
+			(slot.clear)(slot.index.as_mut());
+			fill(slot, self);
+		}
+		self.indexes = indexes;
+	}
+}
+
+fn fill(slot: &mut IndexSlot, ecs: &ECS) {
+	let Some(array) = ecs.components.get_array_by_id(slot.id) else { return };
+	for index in 0..ecs.capacity {
+		if let (Some(entity), Some(pointer)) = (ecs.get_index(index), array.get_ptr(index)) {
+			unsafe { (slot.insert)(slot.index.as_mut(), entity, pointer) };
+		}
+	}
+}
+
+/// Entities by Component value, for exact lookups like finding a player by name
+pub struct HashIndex<C: Component + Hash + Eq + Clone> {
+	map: HashMap<C, Vec<EntityID>>,
+}
+
+impl<C: Component + Hash + Eq + Clone> HashIndex<C> {
+	pub fn new() -> Self {
+		HashIndex { map: HashMap::new() }
+	}
+
+	/// Every entity holding a `C` equal to `value`, in no particular order
+	pub fn get(&self, value: &C) -> &[EntityID] {
+		self.map.get(value).map_or(&[], |entities| entities.as_slice())
+	}
+
+	/// Number of distinct values
+	pub fn len(&self) -> usize { self.map.len() }
+
+	pub fn is_empty(&self) -> bool { self.map.is_empty() }
+}
+
+impl<C: Component + Hash + Eq + Clone> Default for HashIndex<C> {
+	fn default() -> Self { HashIndex::new() }
+}
+
+impl<C: Component + Send + Sync + Hash + Eq + Clone> EntityIndex<C> for HashIndex<C> {
+	fn insert(&mut self, entity: EntityID, component: &C) {
+		self.map.entry(component.clone()).or_default().push(entity);
+	}
+
+	fn remove(&mut self, entity: EntityID, component: &C) {
+		let Some(entities) = self.map.get_mut(component) else { return };
+		if let Some(position) = entities.iter().position(|held| *held == entity) {
+			entities.swap_remove(position);
+		}
+		if entities.is_empty() {
+			self.map.remove(component);
+		}
+	}
+
+	fn clear(&mut self) {
+		self.map.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, EntityID, EntityIndex, HashIndex, ECS};
+
+	#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+	struct Team(u8);
+	impl Component for Team {}
+
+	/// Counts entities per cell of a 1D grid
+	#[derive(Default)]
+	struct Grid([usize; 4]);
+
+	#[derive(Clone, Copy)]
+	struct Position(usize);
+	impl Component for Position {}
+
+	impl EntityIndex<Position> for Grid {
+		fn insert(&mut self, _: EntityID, position: &Position) { self.0[position.0 / 10] += 1; }
+		fn remove(&mut self, _: EntityID, position: &Position) { self.0[position.0 / 10] -= 1; }
+		fn clear(&mut self) { self.0 = [0; 4]; }
+	}
+
+	#[test]
+	fn hash_index() {
+		let mut ecs = ECS::new(16);
+		ecs.register::<Team>();
+		let red = ecs.create_entity().unwrap();
+		ecs.add_component(&red, Team(1));
+		ecs.add_entity_index(HashIndex::<Team>::new());
+
+		let blue = ecs.create_entity().unwrap();
+		ecs.add_component(&blue, Team(2));
+		let other_red = ecs.create_entity().unwrap();
+		ecs.add_component(&other_red, Team(1));
+		let index = ecs.get_entity_index::<HashIndex<Team>>().unwrap();
+		assert_eq!(index.get(&Team(1)).len(), 2);
+		assert!(index.get(&Team(2)) == [blue]);
+
+		ecs.add_component(&red, Team(2));
+		ecs.destroy_entity(other_red);
+		let index = ecs.get_entity_index::<HashIndex<Team>>().unwrap();
+		assert!(index.get(&Team(1)).is_empty());
+		assert_eq!(index.get(&Team(2)).len(), 2);
+
+		ecs.swap_component::<Team>(&blue, &blue);
+		assert_eq!(ecs.get_entity_index::<HashIndex<Team>>().unwrap().get(&Team(2)).len(), 2);
+		ecs.modify_component::<Team, _>(&blue, |team| team.0 = 3);
+		ecs.take_component::<Team>(&red);
+		let index = ecs.get_entity_index::<HashIndex<Team>>().unwrap();
+		assert!(index.get(&Team(3)) == [blue]);
+		assert_eq!(index.len(), 1);
+
+		let index = ecs.remove_entity_index::<HashIndex<Team>>().unwrap();
+		ecs.add_component(&red, Team(3));
+		assert_eq!(index.get(&Team(3)).len(), 1);
+	}
+
+	#[test]
+	fn custom_index() {
+		let mut ecs = ECS::new(16);
+		ecs.register_cloneable::<Position>();
+		ecs.add_entity_index(Grid::default());
+		let entities: alloc::vec::Vec<_> = (0..4).map(|cell| {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(cell * 10));
+			entity
+		}).collect();
+
+		let checkpoint = ecs.save_checkpoint();
+		ecs.swap_component::<Position>(&entities[0], &entities[1]);
+		ecs.add_components([(entities[2], Position(5)), (entities[3], Position(6))]);
+		assert_eq!(ecs.get_entity_index::<Grid>().unwrap().0, [3, 1, 0, 0]);
+
+		ecs.rollback_to(checkpoint);
+		assert_eq!(ecs.get_entity_index::<Grid>().unwrap().0, [1, 1, 1, 1]);
+		ecs.unregister::<Position>();
+		assert!(ecs.get_entity_index::<Grid>().is_none());
+	}
+}
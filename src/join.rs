@@ -0,0 +1,97 @@
+use crate::{Component, Query, QueryIter, StorageBorrow, ECS};
+use core::hash::Hash;
+use hashbrown::HashMap;
+
+/// Entities of one world paired with their counterparts in another, see `ECS::join_guids` and `ECS::join_on`
+pub struct Join<'a, A: Query + 'a, B: Query + 'a, F> {
+	left: QueryIter<'a, A>,
+	right: &'a ECS,
+	right_array: B::Array,
+	_right_borrow: StorageBorrow,
+	/// Index in `right` of the counterpart of an index in the left world
+	counterpart: F,
+}
+
+impl<'a, A: Query, B: Query, F: FnMut(usize) -> Option<usize>> Join<'a, A, B, F> {
+	fn new(left: &'a ECS, right: &'a ECS, counterpart: F) -> Self {
+		let right_array = unsafe { B::get_array(right) };
+		Join { left: left.query::<A>(), right, right_array, _right_borrow: right.borrow_storage(), counterpart }
+	}
+}
+
+impl<'a, A: Query, B: Query, F: FnMut(usize) -> Option<usize>> Iterator for Join<'a, A, B, F> {
+	type Item = (A::Output<'a>, B::Output<'a>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let left = self.left.next()?;
+			// `next` leaves the index one past the match
+			let Some(index) = (self.counterpart)(self.left.index - 1) else { continue };
+			let filled = unsafe { B::filled_word(&self.right_array, index / 64) } & (1 << (index % 64)) != 0;
+			if filled {
+				return Some((left, unsafe { B::get_unchecked(self.right, index, &self.right_array) }));
+			}
+		}
+	}
+}
+
+impl ECS {
+	/// Pair every entity matching `A` here with the entity of the same `Guid` in `other`, if that one matches `B`.
+	/// For keeping worlds such as simulation and rendering in step without a mapping of their own.
+	pub fn join_guids<'a, A: Query, B: Query>(&'a self, other: &'a ECS) -> Join<'a, A, B, impl FnMut(usize) -> Option<usize> + 'a> {
+		Join::new(self, other, move |index| other.guids.find(self.guids.get(index)?))
+	}
+
+	/// Like `join_guids` but matching on equal values of the key Component `K`, which both worlds must register.
+	/// Where several entities of `other` share a key the lowest index wins.
+	pub fn join_on<'a, K: Component + Eq + Hash, A: Query, B: Query>(&'a self, other: &'a ECS) -> Join<'a, A, B, impl FnMut(usize) -> Option<usize> + 'a> {
+		let mut by_key: HashMap<&K, usize> = HashMap::new();
+		for (entity, key) in other.query::<K>() {
+			by_key.entry(key).or_insert(entity.index);
+		}
+		let keys = unsafe { K::get_array(self) };
+		Join::new(self, other, move |index| by_key.get(unsafe { keys.get(index) }?).copied())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+	use alloc::vec::Vec;
+
+	#[derive(PartialEq, Eq, Hash)]
+	struct NetId(u32);
+	impl Component for NetId {}
+	struct Position(u32);
+	impl Component for Position {}
+	struct Sprite(u32);
+	impl Component for Sprite {}
+
+	#[test]
+	fn join() {
+		let mut simulation = ECS::new(4);
+		let mut render = ECS::new(4);
+		simulation.enable_guids(1);
+		crate::register_components!(simulation, [NetId, Position]);
+		crate::register_components!(render, [NetId, Sprite]);
+
+		for id in 0..3 {
+			let entity = simulation.create_entity().unwrap();
+			simulation.add_component(&entity, NetId(id));
+			simulation.add_component(&entity, Position(id * 10));
+		}
+		// Created in a different order, and without a counterpart for the first
+		for id in [2, 1] {
+			let entity = render.create_entity().unwrap();
+			render.add_component(&entity, NetId(id));
+			render.add_component(&entity, Sprite(id));
+			let twin = simulation.query::<NetId>().find(|(_, net)| net.0 == id).unwrap().0;
+			assert!(render.set_guid(&entity, simulation.get_guid(&twin).unwrap()));
+		}
+
+		let pairs: Vec<_> = simulation.join_on::<NetId, Position, Sprite>(&render).map(|((_, position), (_, sprite))| (position.0, sprite.0)).collect();
+		assert_eq!(pairs, [(10, 1), (20, 2)]);
+		let pairs: Vec<_> = simulation.join_guids::<Position, Sprite>(&render).map(|((_, position), (_, sprite))| (position.0, sprite.0)).collect();
+		assert_eq!(pairs, [(10, 1), (20, 2)]);
+	}
+}
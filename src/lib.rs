@@ -1,13 +1,170 @@
 #![no_std]
+#![allow(clippy::needless_return)]
 extern crate alloc;
 
+/// Forward to `tracing` when the feature is enabled, otherwise expand to nothing
+macro_rules! trace {
+	($($arg:tt)*) => {
+		#[cfg(feature = "tracing")]
+		tracing::trace!($($arg)*);
+	};
+}
+
+macro_rules! debug {
+	($($arg:tt)*) => {
+		#[cfg(feature = "tracing")]
+		tracing::debug!($($arg)*);
+	};
+}
+
+/// Panic with the message, or with the `panic-free` feature return `$fallback` from the enclosing function
+macro_rules! fail {
+	($message:literal) => {{
+		#[cfg(feature = "panic-free")]
+		return;
+		#[cfg(not(feature = "panic-free"))]
+		panic!($message);
+	}};
+	($fallback:expr, $($message:tt)*) => {{
+		#[cfg(feature = "panic-free")]
+		return $fallback;
+		#[cfg(not(feature = "panic-free"))]
+		panic!($($message)*);
+	}};
+}
+
+pub use runtime_id::RuntimeID;
+
+mod app;
+pub use app::{App, Plugin};
+
+mod backend;
+pub use backend::{ComponentStorage, DenseVecStorage, NullStorage};
+
+mod borrow;
+pub use borrow::StorageBorrow;
+
+mod cell;
+pub use cell::{CellQuery, CellQueryMut, WorldCell};
+
+mod change;
+pub use change::{ChangeCursor, ChangedIter};
+
+mod command;
+pub use command::Commands;
+
+mod compact;
+pub use compact::RemapListenerID;
+
+mod compare;
+pub use compare::{assert_worlds_equal, WorldDifference};
+
 mod component;
-pub use component::{Component, ComponentRegistry};
+pub use component::{Component, ComponentInfo, ComponentRegistry, FromWorld};
+
+mod drain;
+pub use drain::DrainIter;
+
+mod dump;
+
+mod dynamic;
+pub use dynamic::{DynamicIter, DynamicQuery, DynamicRow};
 
 mod ecs;
-pub use ecs::{EntityID, ECS};
+pub use ecs::{EntityID, EntityReserver, GenerationPolicy, InvalidEntity, RecyclePolicy, WeakEntity, ECS};
+
+mod entity_ref;
+pub use entity_ref::{EntityMut, EntityRef};
+
+mod entry;
+pub use entry::{ComponentEntry, OccupiedEntry, VacantEntry};
+
+mod event;
+pub use event::{EventReader, Events};
+
+mod expire;
+pub use expire::Expires;
+
+mod filter;
+pub use filter::{And, ComponentFilter, FilteredIter, FilteredMutIter};
+
+mod group;
+pub use group::{GroupIter, GroupMutIter};
+
+mod guest;
+pub use guest::{encode_handle, GuestBridge, GuestHandle, NULL_HANDLE};
+
+mod guid;
+pub use guid::Guid;
+
+mod hook;
+
+mod index;
+pub use index::{EntityIndex, HashIndex};
+
+mod join;
+pub use join::Join;
+
+mod named;
+
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "rayon")]
+pub use parallel::{ParQueryIter, ParQueryMutIter};
 
 mod query;
-pub use query::{Query, QueryMut, QueryIter, QueryMutIter};
+pub use query::{Query, QueryMut, QueryIter, QueryMutIter, QueryChunk, QueryChunks, QueryMutChunk, QueryMutChunks, PairIter, WithoutIter, ExcludingIter};
+
+mod query_state;
+pub use query_state::{QueryMutState, QueryState};
+
+mod reflect;
+pub use reflect::{Reflect, ReflectValue};
+
+mod relation;
+pub use relation::{Relation, RelationPolicy};
+
+mod remap;
+pub use remap::SceneRemapper;
+
+mod replication;
+pub use replication::{ConnectionID, Replica, Replicated, ReplicationError};
+
+mod resource;
+
+mod runs;
+pub use runs::{Runs, RunsMut};
+
+mod scene;
+pub use scene::{parse_scene, FromScene, SceneError, SceneValue};
+
+mod schedule;
+pub use schedule::{Local, Schedule, SystemConfig};
+
+mod schema;
+pub use schema::ComponentSchema;
+
+mod snapshot;
+pub use snapshot::Checkpoint;
+
+mod state;
+pub use state::{in_state, States};
+
+mod stats;
+pub use stats::{ComponentStats, Stats};
+
+mod split;
+pub use split::SplitComponent;
+
+mod storage;
+
+mod subworld;
+pub use subworld::{Access, SubWorld};
+
+mod tag;
+
+mod trait_query;
+pub use trait_query::TraitIter;
 
-mod storage;
\ No newline at end of file
+mod watch;
+pub use watch::{WatchEvent, WatchID};
\ No newline at end of file
@@ -0,0 +1,166 @@
+use crate::component::ComponentID;
+use crate::{ComponentRegistry, EntityID, Replicated, ECS};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+/// Byte level access to one Component type, encoded through `Replicated`
+#[derive(Clone, Copy)]
+pub(crate) struct ByteAccess {
+	pub(crate) id: ComponentID,
+	/// Appends the serialized value, false if `entity` has none
+	pub(crate) read: fn(&ECS, &EntityID, &mut Vec<u8>) -> bool,
+	/// Adds or overwrites, false if the bytes don't decode
+	pub(crate) write: fn(&mut ECS, &EntityID, &[u8]) -> bool,
+	/// False if there was nothing to remove
+	pub(crate) remove: fn(&mut ECS, &EntityID) -> bool,
+}
+
+impl ByteAccess {
+	pub(crate) fn of<C: Replicated>() -> Self {
+		ByteAccess { id: ComponentID::of::<C>(), read: read::<C>, write: write::<C>, remove: remove::<C> }
+	}
+}
+
+fn read<C: Replicated>(ecs: &ECS, entity: &EntityID, out: &mut Vec<u8>) -> bool {
+	match ecs.get_component::<C>(entity) {
+		Some(component) => {
+			component.serialize(out);
+			true
+		},
+		None => false,
+	}
+}
+
+fn write<C: Replicated>(ecs: &mut ECS, entity: &EntityID, bytes: &[u8]) -> bool {
+	match C::deserialize(bytes) {
+		Some(component) => {
+			ecs.add_component(entity, component);
+			true
+		},
+		None => false,
+	}
+}
+
+fn remove<C: Replicated>(ecs: &mut ECS, entity: &EntityID) -> bool {
+	ecs.take_component::<C>(entity).is_some()
+}
+
+/// Components addressable by a name chosen at runtime
+#[derive(Clone, Default)]
+pub(crate) struct NamedComponents {
+	map: HashMap<String, ByteAccess>,
+}
+
+impl NamedComponents {
+	pub(crate) fn insert(&mut self, name: &str, access: ByteAccess) {
+		self.map.insert(name.to_string(), access);
+	}
+
+	pub(crate) fn get(&self, name: &str) -> Option<&ByteAccess> {
+		self.map.get(name)
+	}
+
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		self.map.retain(|_, access| access.id != id);
+	}
+
+	/// Add every name of `other` not already taken here
+	pub(crate) fn merge(&mut self, other: &NamedComponents) {
+		for (name, access) in &other.map {
+			self.map.entry(name.clone()).or_insert(*access);
+		}
+	}
+}
+
+impl ComponentRegistry {
+	/// Make `C` addressable as `name` for `ECS::add_dynamic` and friends in worlds built from this registry.
+	/// Registers `C` if needed, and replaces whatever `name` meant before.
+	pub fn register_named<C: Replicated>(&mut self, name: &str) {
+		if !self.is_registered::<C>() {
+			self.register::<C>();
+		}
+		self.names.insert(name, ByteAccess::of::<C>());
+	}
+
+	/// The Component registered as `name`, for building an `Access` or `DynamicQuery` without the type
+	pub fn component_id(&self, name: &str) -> Option<ComponentID> {
+		self.names.get(name).map(|access| access.id)
+	}
+}
+
+impl ECS {
+	/// Make `C` addressable as `name`, for embedders whose scripts can't name Rust types.
+	/// Values cross as bytes through `Replicated`, so scripts can't forge the memory of a value.
+	pub fn register_named<C: Replicated>(&mut self, name: &str) {
+		if self.get_component_info::<C>().is_none() {
+			self.register::<C>();
+		}
+		self.names.insert(name, ByteAccess::of::<C>());
+	}
+
+	pub fn component_id(&self, name: &str) -> Option<ComponentID> {
+		self.names.get(name).map(|access| access.id)
+	}
+
+	/// Add or overwrite the Component registered as `name` from its serialized bytes.
+	/// Returns false if the entity is invalid, the name unknown, or the bytes don't decode.
+	pub fn add_dynamic(&mut self, entity: &EntityID, name: &str, bytes: &[u8]) -> bool {
+		let Some(access) = self.names.get(name).copied() else { return false };
+		if !self.is_valid(entity) { return false; }
+		(access.write)(self, entity, bytes)
+	}
+
+	/// Append the serialized value to `out`, false if there is none
+	pub fn get_dynamic(&self, entity: &EntityID, name: &str, out: &mut Vec<u8>) -> bool {
+		let Some(access) = self.names.get(name) else { return false };
+		(access.read)(self, entity, out)
+	}
+
+	/// Returns false if there was nothing to remove
+	pub fn remove_dynamic(&mut self, entity: &EntityID, name: &str) -> bool {
+		let Some(access) = self.names.get(name).copied() else { return false };
+		(access.remove)(self, entity)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Access, Component, ComponentRegistry, DynamicQuery, Replicated, ECS};
+	use alloc::vec::Vec;
+
+	struct Health(u32);
+	impl Component for Health {}
+	impl Replicated for Health {
+		fn serialize(&self, out: &mut Vec<u8>) { out.extend_from_slice(&self.0.to_le_bytes()); }
+		fn deserialize(bytes: &[u8]) -> Option<Self> { Some(Health(u32::from_le_bytes(bytes.try_into().ok()?))) }
+	}
+
+	#[test]
+	fn named() {
+		let mut registry = ComponentRegistry::new();
+		registry.register_named::<Health>("Health");
+		assert!(registry.is_registered::<Health>());
+		let mut ecs = ECS::from_registry(&registry, 4);
+		let entity = ecs.create_entity().unwrap();
+
+		assert!(ecs.add_dynamic(&entity, "Health", &5u32.to_le_bytes()));
+		assert!(!ecs.add_dynamic(&entity, "Health", &[0]));
+		assert!(!ecs.add_dynamic(&entity, "Mana", &5u32.to_le_bytes()));
+		assert_eq!(ecs.get_component::<Health>(&entity).unwrap().0, 5);
+
+		let mut out = Vec::new();
+		assert!(ecs.get_dynamic(&entity, "Health", &mut out));
+		assert_eq!(out, 5u32.to_le_bytes());
+
+		let mut access = Access::new();
+		access.add_read(ecs.component_id("Health").unwrap());
+		let query = DynamicQuery::new(&access);
+		assert_eq!(ecs.query_dynamic(&query).count(), 1);
+
+		assert!(ecs.remove_dynamic(&entity, "Health"));
+		assert!(!ecs.get_dynamic(&entity, "Health", &mut out));
+		ecs.unregister::<Health>();
+		assert!(ecs.component_id("Health").is_none());
+	}
+}
@@ -0,0 +1,168 @@
+use crate::tag::Layers;
+use crate::{Query, QueryIter, QueryMut, QueryMutIter, ECS};
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Smallest index range handed to a single task
+const CHUNK_SIZE: usize = 1024;
+
+/// Split `start..end` roughly in half on a `CHUNK_SIZE` boundary
+fn split_range(start: usize, end: usize) -> Option<usize> {
+	let length = end - start;
+	if length <= CHUNK_SIZE { return None; }
+	Some(start + (length / 2).div_ceil(CHUNK_SIZE) * CHUNK_SIZE)
+}
+
+pub struct ParQueryIter<'a, Q: Query + 'a> {
+	iter: QueryIter<'a, Q>,
+}
+
+// `Send` outputs mean the queried Components are `Sync`, and the rest of the world is only read
+unsafe impl<'a, Q: Query + 'a> Send for ParQueryIter<'a, Q> where Q::Output<'a>: Send {}
+
+impl<'a, Q: Query + 'a> IntoParallelIterator for QueryIter<'a, Q> where Q::Output<'a>: Send {
+	type Iter = ParQueryIter<'a, Q>;
+	type Item = Q::Output<'a>;
+
+	fn into_par_iter(self) -> Self::Iter { ParQueryIter { iter: self } }
+}
+
+impl<'a, Q: Query + 'a> ParallelIterator for ParQueryIter<'a, Q> where Q::Output<'a>: Send {
+	type Item = Q::Output<'a>;
+
+	fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+		let QueryIter { ecs, index, end, array, layers, .. } = self.iter;
+		let producer = QueryProducer::<Q> { ecs, start: index, end, array: &array, layers };
+		bridge_unindexed(producer, consumer)
+	}
+}
+
+struct QueryProducer<'a, 'p, Q: Query + 'a> {
+	ecs: &'a ECS,
+	start: usize,
+	end: usize,
+	array: &'p Q::Array,
+	layers: Layers,
+}
+
+unsafe impl<'a, 'p, Q: Query + 'a> Send for QueryProducer<'a, 'p, Q> where Q::Output<'a>: Send {}
+
+impl<'a, 'p, Q: Query + 'a> UnindexedProducer for QueryProducer<'a, 'p, Q> where Q::Output<'a>: Send {
+	type Item = Q::Output<'a>;
+
+	fn split(self) -> (Self, Option<Self>) {
+		match split_range(self.start, self.end) {
+			Some(mid) => (
+				QueryProducer { end: mid, ..self },
+				Some(QueryProducer { start: mid, ..self }),
+			),
+			None => (self, None),
+		}
+	}
+
+	fn fold_with<F: Folder<Self::Item>>(self, folder: F) -> F {
+		let mut index = self.start;
+		folder.consume_iter(core::iter::from_fn(|| loop {
+			let output = unsafe { Q::next(self.ecs, &mut index, self.end, self.array) }?;
+			if self.layers.admits(self.ecs, index - 1) { return Some(output); }
+		}))
+	}
+}
+
+pub struct ParQueryMutIter<'a, Q: QueryMut + 'a> {
+	iter: QueryMutIter<'a, Q>,
+}
+
+// Producers cover disjoint index ranges, so no two threads see the same slot
+unsafe impl<'a, Q: QueryMut + 'a> Send for ParQueryMutIter<'a, Q> where Q::Output<'a>: Send {}
+
+impl<'a, Q: QueryMut + 'a> IntoParallelIterator for QueryMutIter<'a, Q> where Q::Output<'a>: Send {
+	type Iter = ParQueryMutIter<'a, Q>;
+	type Item = Q::Output<'a>;
+
+	fn into_par_iter(self) -> Self::Iter { ParQueryMutIter { iter: self } }
+}
+
+impl<'a, Q: QueryMut + 'a> ParallelIterator for ParQueryMutIter<'a, Q> where Q::Output<'a>: Send {
+	type Item = Q::Output<'a>;
+
+	fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+		let QueryMutIter { ecs, index, end, array, layers, .. } = self.iter;
+		let producer = QueryMutProducer::<Q> { ecs, start: index, end, array: &array, layers };
+		bridge_unindexed(producer, consumer)
+	}
+}
+
+struct QueryMutProducer<'a, 'p, Q: QueryMut + 'a> {
+	ecs: &'a ECS,
+	start: usize,
+	end: usize,
+	array: &'p Q::Array,
+	layers: Layers,
+}
+
+unsafe impl<'a, 'p, Q: QueryMut + 'a> Send for QueryMutProducer<'a, 'p, Q> where Q::Output<'a>: Send {}
+
+impl<'a, 'p, Q: QueryMut + 'a> UnindexedProducer for QueryMutProducer<'a, 'p, Q> where Q::Output<'a>: Send {
+	type Item = Q::Output<'a>;
+
+	fn split(self) -> (Self, Option<Self>) {
+		match split_range(self.start, self.end) {
+			Some(mid) => (
+				QueryMutProducer { end: mid, ..self },
+				Some(QueryMutProducer { start: mid, ..self }),
+			),
+			None => (self, None),
+		}
+	}
+
+	fn fold_with<F: Folder<Self::Item>>(self, folder: F) -> F {
+		let mut index = self.start;
+		folder.consume_iter(core::iter::from_fn(|| loop {
+			let output = unsafe { Q::next(self.ecs, &mut index, self.end, self.array) }?;
+			if self.layers.admits(self.ecs, index - 1) { return Some(output); }
+		}))
+	}
+}
+
+impl ECS {
+	pub fn par_for_each<'a, Q: Query + 'a, F>(&'a self, f: F) where F: Fn(Q::Output<'a>) + Send + Sync, Q::Output<'a>: Send {
+		self.query::<Q>().into_par_iter().for_each(f)
+	}
+
+	pub fn par_for_each_mut<'a, Q: QueryMut + 'a, F>(&'a mut self, f: F) where F: Fn(Q::Output<'a>) + Send + Sync, Q::Output<'a>: Send {
+		self.query_mut::<Q>().into_par_iter().for_each(f)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+	use core::sync::atomic::{AtomicUsize, Ordering};
+	use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+	struct TestComponent(usize);
+	impl Component for TestComponent {}
+
+	#[test]
+	fn par_for_each() {
+		const CAPACITY: usize = 10_000;
+		let mut ecs = ECS::new(CAPACITY);
+		ecs.register::<TestComponent>();
+		for index in 0..CAPACITY {
+			let entity = ecs.create_entity().unwrap();
+			if index % 3 == 0 {
+				ecs.add_component(&entity, TestComponent(index));
+			}
+		}
+
+		ecs.par_for_each_mut::<TestComponent, _>(|(_, test)| test.0 *= 2);
+
+		let sum = AtomicUsize::new(0);
+		ecs.query::<TestComponent>().into_par_iter().for_each(|(_, test)| {
+			sum.fetch_add(test.0, Ordering::Relaxed);
+		});
+		let expected: usize = (0..CAPACITY).filter(|index| index % 3 == 0).map(|index| index * 2).sum();
+		assert_eq!(sum.load(Ordering::Relaxed), expected);
+	}
+}
@@ -1,131 +1,617 @@
-use crate::{Component, EntityID, storage::Slot, ECS};
-use core::{iter::Iterator, any::TypeId};
+use crate::{Component, EntityID, storage::{Column, ComponentArray}, tag::Layers, StorageBorrow, ECS};
+use alloc::vec::Vec;
+use core::{iter::Iterator, any::TypeId, ops::Range};
 
 pub trait Query {
 	type Output<'a> where Self: 'a;
-	type Array;
+	type Array: Clone;
 
+	/// # Safety
+	/// The returned pointers must not outlive `ecs` or any resize of its storage.
 	unsafe fn get_array(ecs: &ECS) -> Self::Array;
 
-	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, array: *const Self::Array) -> Option<Self::Output<'a>>;
+	/// Advance `index` to the next match below `end`.
+	/// # Safety
+	/// `array` must come from `get_array` on the same `ecs` and still be live, `end` must not exceed its capacity.
+	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, end: usize, array: *const Self::Array) -> Option<Self::Output<'a>>;
+
+	/// Components accessed by this query
+	fn component_ids() -> Vec<TypeId>;
+
+	/// Output for an index already known to hold every component, skipping occupancy checks.
+	/// # Safety
+	/// Same as `next`, and `index` must be alive with every component filled.
+	unsafe fn get_unchecked<'a>(ecs: &'a ECS, index: usize, array: *const Self::Array) -> Self::Output<'a>;
+
+	/// Output for an alive index whose values every column stores at `slot`, as in a group's packed prefix.
+	/// # Safety
+	/// Same as `get_unchecked`, and `slot` of every column must hold the filled value of `index`.
+	unsafe fn get_packed<'a>(ecs: &'a ECS, index: usize, slot: usize, array: *const Self::Array) -> Self::Output<'a>;
+
+	/// Bits of the indices `64 * word..64 * word + 64` holding every component, for iterating a word at a time.
+	/// # Safety
+	/// Same as `next`, and `word` must be below the capacity divided by 64, rounded up.
+	unsafe fn filled_word(array: *const Self::Array, word: usize) -> u64;
+
+	/// Hint that `index` of every column will be read soon, see `QueryIter::prefetch`
+	/// # Safety
+	/// `array` must come from `get_array` on a live `ECS`.
+	unsafe fn prefetch(_array: *const Self::Array, _index: usize) {}
 }
 
 pub trait QueryMut {
 	type Output<'a> where Self: 'a;
-	type Array;
+	type Array: Clone;
 
+	/// # Safety
+	/// The returned pointers must not outlive `ecs` or any resize of its storage.
 	unsafe fn get_array(ecs: &ECS) -> Self::Array;
 
-	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, array: *const Self::Array) -> Option<Self::Output<'a>>;
+	/// Advance `index` to the next match below `end`.
+	/// # Safety
+	/// `array` must come from `get_array` on the same `ecs` and still be live, `end` must not exceed its capacity.
+	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, end: usize, array: *const Self::Array) -> Option<Self::Output<'a>>;
+
+	/// Components accessed by this query
+	fn component_ids() -> Vec<TypeId>;
+
+	/// Output for an index already known to hold every component, skipping occupancy checks.
+	/// # Safety
+	/// Same as `next`, and `index` must be alive with every component filled.
+	unsafe fn get_unchecked<'a>(ecs: &'a ECS, index: usize, array: *const Self::Array) -> Self::Output<'a>;
+
+	/// Output for an alive index whose values every column stores at `slot`, as in a group's packed prefix.
+	/// # Safety
+	/// Same as `get_unchecked`, and `slot` of every column must hold the filled value of `index`.
+	unsafe fn get_packed<'a>(ecs: &'a ECS, index: usize, slot: usize, array: *const Self::Array) -> Self::Output<'a>;
+
+	/// Bits of the indices `64 * word..64 * word + 64` holding every component, for iterating a word at a time.
+	/// # Safety
+	/// Same as `next`, and `word` must be below the capacity divided by 64, rounded up.
+	unsafe fn filled_word(array: *const Self::Array, word: usize) -> u64;
+
+	/// Hint that `index` of every column will be read soon, see `QueryIter::prefetch`
+	/// # Safety
+	/// `array` must come from `get_array` on a live `ECS`.
+	unsafe fn prefetch(_array: *const Self::Array, _index: usize) {}
+}
+
+/// Panics if a Component repeats in `ids`, which could hand out references aliasing a mutable one
+pub(crate) fn assert_unique(ids: &[TypeId]) {
+	for (position, id) in ids.iter().enumerate() {
+		if ids[..position].contains(id) {
+			panic!("Cannot Query for multiple of the same Component type");
+		}
+	}
 }
 
 impl<C: Component> Query for C {
 	type Output<'a> = (EntityID, &'a C);
-	type Array = *const [Slot<C>];
+	type Array = Column<C>;
 
 	unsafe fn get_array(ecs: &ECS) -> Self::Array {
-		ecs.components.get_array::<C>().unwrap()
+		ecs.components.get_column::<C>().unwrap()
     }
 
-	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, array: *const Self::Array) -> Option<Self::Output<'a>> {
-		while (*index) < ecs.capacity {
+	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, end: usize, array: *const Self::Array) -> Option<Self::Output<'a>> {
+		while (*index) < end {
 			let id = ecs.get_index(*index);
-			let element = &(**array)[*index];
+			let element = (*array).get(*index);
 			*index += 1;
-			if element.is_filled() {
-				return Some((id.unwrap(), element.as_option().unwrap()));
+			if let Some(element) = element {
+				return Some((id.unwrap(), element));
 			}
 		}
 		None
     }
+
+	fn component_ids() -> Vec<TypeId> { alloc::vec![TypeId::of::<C>()] }
+
+	unsafe fn get_unchecked<'a>(ecs: &'a ECS, index: usize, array: *const Self::Array) -> Self::Output<'a> {
+		(ecs.get_index(index).unwrap(), (*array).get_unchecked(index))
+	}
+
+	unsafe fn get_packed<'a>(ecs: &'a ECS, index: usize, slot: usize, array: *const Self::Array) -> Self::Output<'a> {
+		(ecs.alive_id(index), (*array).get_slot_unchecked(slot))
+	}
+
+	unsafe fn filled_word(array: *const Self::Array, word: usize) -> u64 { (*array).filled_word(word) }
+
+	unsafe fn prefetch(array: *const Self::Array, index: usize) { (*array).prefetch(index) }
 }
 
 impl<C: Component> QueryMut for C {
 	type Output<'a> = (EntityID, &'a mut C);
-	type Array = *mut [Slot<C>];
+	type Array = Column<C>;
 
 	unsafe fn get_array(ecs: &ECS) -> Self::Array {
-        ecs.components.get_array_mut::<C>().unwrap()
+        ecs.components.get_column::<C>().unwrap()
     }
 	
-	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, array: *const Self::Array) -> Option<Self::Output<'a>> {
-    	while (*index) < ecs.capacity {
+	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, end: usize, array: *const Self::Array) -> Option<Self::Output<'a>> {
+    	while (*index) < end {
 			let id = ecs.get_index(*index);
-			let element = &mut(**array)[*index];
+			let element = (*array).get_mut(*index);
 			*index += 1;
-			if element.is_filled() {
-				return Some((id.unwrap(), element.as_option_mut().unwrap()));
+			if let Some(element) = element {
+				(*array).stamp(*index - 1, ecs.change_tick);
+				return Some((id.unwrap(), element));
 			}
 		}
 		None
     }
+
+	fn component_ids() -> Vec<TypeId> { alloc::vec![TypeId::of::<C>()] }
+
+	unsafe fn get_unchecked<'a>(ecs: &'a ECS, index: usize, array: *const Self::Array) -> Self::Output<'a> {
+		(*array).stamp(index, ecs.change_tick);
+		(ecs.get_index(index).unwrap(), (*array).get_mut_unchecked(index))
+	}
+
+	unsafe fn get_packed<'a>(ecs: &'a ECS, index: usize, slot: usize, array: *const Self::Array) -> Self::Output<'a> {
+		(*array).stamp(index, ecs.change_tick);
+		(ecs.alive_id(index), (*array).get_slot_mut_unchecked(slot))
+	}
+
+	unsafe fn filled_word(array: *const Self::Array, word: usize) -> u64 { (*array).filled_word(word) }
+
+	unsafe fn prefetch(array: *const Self::Array, index: usize) { (*array).prefetch(index) }
 }
 
 impl<C0: Component, C1: Component> Query for (C0, C1) {
 	type Output<'a> = (EntityID, &'a C0, &'a C1);
-	type Array = (*const [Slot<C0>], *const [Slot<C1>]);
+	type Array = (Column<C0>, Column<C1>);
 
 	unsafe fn get_array(ecs: &ECS) -> Self::Array {
-		if TypeId::of::<C0>() == TypeId::of::<C1>() {
-			panic!("Cannot Query for multiple of the same Component type");
-		}
+		assert_unique(&Self::component_ids());
 		
-		let c0_array = ecs.components.get_array::<C0>().unwrap();
-		let c1_array = ecs.components.get_array::<C1>().unwrap();
+		let c0_array = ecs.components.get_column::<C0>().unwrap();
+		let c1_array = ecs.components.get_column::<C1>().unwrap();
 		(c0_array, c1_array)
     }
 
-	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, array: *const Self::Array) -> Option<Self::Output<'a>> {
-		while (*index) < ecs.capacity {
+	unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, end: usize, array: *const Self::Array) -> Option<Self::Output<'a>> {
+		while (*index) < end {
 			let id = ecs.get_index(*index);
-			let element_0 = &(*(*array).0)[*index];
-			let element_1 = &(*(*array).1)[*index];
+			let element_0 = (*array).0.get(*index);
+			let element_1 = (*array).1.get(*index);
 			
 			*index += 1;
-			if element_0.is_filled() && element_1.is_filled() {
-				return Some((id.unwrap(), element_0.as_option().unwrap(), element_1.as_option().unwrap()));
+			if let (Some(element_0), Some(element_1)) = (element_0, element_1) {
+				return Some((id.unwrap(), element_0, element_1));
 			}
 		}
 		None
     }
+
+	fn component_ids() -> Vec<TypeId> { alloc::vec![TypeId::of::<C0>(), TypeId::of::<C1>()] }
+
+	unsafe fn get_unchecked<'a>(ecs: &'a ECS, index: usize, array: *const Self::Array) -> Self::Output<'a> {
+		(ecs.get_index(index).unwrap(), (*array).0.get_unchecked(index), (*array).1.get_unchecked(index))
+	}
+
+	unsafe fn get_packed<'a>(ecs: &'a ECS, index: usize, slot: usize, array: *const Self::Array) -> Self::Output<'a> {
+		(ecs.alive_id(index), (*array).0.get_slot_unchecked(slot), (*array).1.get_slot_unchecked(slot))
+	}
+
+	unsafe fn filled_word(array: *const Self::Array, word: usize) -> u64 {
+		(*array).0.filled_word(word) & (*array).1.filled_word(word)
+	}
+
+	unsafe fn prefetch(array: *const Self::Array, index: usize) {
+		(*array).0.prefetch(index);
+		(*array).1.prefetch(index);
+	}
+}
+
+macro_rules! query_tuple {
+	($($C:ident $i:tt),+) => {
+		impl<$($C: Component),+> Query for ($($C,)+) {
+			type Output<'a> = (EntityID, $(&'a $C),+);
+			type Array = ($(Column<$C>,)+);
+
+			unsafe fn get_array(ecs: &ECS) -> Self::Array {
+				assert_unique(&Self::component_ids());
+				($(ecs.components.get_column::<$C>().unwrap(),)+)
+			}
+
+			unsafe fn next<'a>(ecs: &'a ECS, index: &mut usize, end: usize, array: *const Self::Array) -> Option<Self::Output<'a>> {
+				while (*index) < end {
+					let current = *index;
+					*index += 1;
+					if $((*array).$i.is_filled(current))&&+ {
+						return Some((ecs.get_index(current).unwrap(), $((*array).$i.get_unchecked(current)),+));
+					}
+				}
+				None
+			}
+
+			fn component_ids() -> Vec<TypeId> { alloc::vec![$(TypeId::of::<$C>()),+] }
+
+			unsafe fn get_unchecked<'a>(ecs: &'a ECS, index: usize, array: *const Self::Array) -> Self::Output<'a> {
+				(ecs.get_index(index).unwrap(), $((*array).$i.get_unchecked(index)),+)
+			}
+
+			unsafe fn get_packed<'a>(ecs: &'a ECS, index: usize, slot: usize, array: *const Self::Array) -> Self::Output<'a> {
+				(ecs.alive_id(index), $((*array).$i.get_slot_unchecked(slot)),+)
+			}
+
+			unsafe fn filled_word(array: *const Self::Array, word: usize) -> u64 {
+				$((*array).$i.filled_word(word))&+
+			}
+
+			unsafe fn prefetch(array: *const Self::Array, index: usize) {
+				$((*array).$i.prefetch(index);)+
+			}
+		}
+	};
 }
 
+query_tuple!(C0 0, C1 1, C2 2);
+query_tuple!(C0 0, C1 1, C2 2, C3 3);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12, C13 13);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12, C13 13, C14 14);
+query_tuple!(C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12, C13 13, C14 14, C15 15);
+
 pub struct QueryIter<'a, Q: Query + 'a> {
-	ecs: &'a ECS,
-	index: usize,
-	array: Q::Array,
+	pub(crate) ecs: &'a ECS,
+	/// Held until the iterator and everything split from it is dropped
+	pub(crate) borrow: StorageBorrow,
+	pub(crate) index: usize,
+	pub(crate) end: usize,
+	pub(crate) array: Q::Array,
+	pub(crate) layers: Layers,
+	/// How many indices ahead to prefetch, 0 for none
+	pub(crate) prefetch: usize,
 }
 
 impl<'a, Q: Query> QueryIter<'a, Q> {
 	pub(crate) fn new(ecs: &'a ECS) -> Self {
 		let array = unsafe { Q::get_array(ecs) };
-		QueryIter { ecs, index: 0, array }
+		QueryIter { ecs, borrow: ecs.borrow_storage(), index: 0, end: ecs.capacity, array, layers: Layers::ALL, prefetch: 0 }
 	}
 }
 
 impl<'a, Q: Query> Iterator for QueryIter<'a, Q> {
 	type Item = Q::Output<'a>;
 	fn next(&mut self) -> Option<Self::Item> {
-		unsafe { Q::next(self.ecs, &mut self.index, &self.array) }
+		loop {
+			let output = unsafe { Q::next(self.ecs, &mut self.index, self.end, &self.array) }?;
+			// `next` leaves the index one past the match
+			if self.prefetch != 0 { unsafe { Q::prefetch(&self.array, (self.index - 1).saturating_add(self.prefetch)) }; }
+			if self.layers.admits(self.ecs, self.index - 1) { return Some(output); }
+		}
     }
+
+	/// Walks occupancy a word at a time instead of probing every index, so `sum`, `for_each`, `reduce` and
+	/// other internal iteration skip empty stretches and run a tight loop over the rest
+	fn fold<B, F: FnMut(B, Self::Item) -> B>(self, init: B, mut f: F) -> B {
+		let QueryIter { ecs, borrow: _borrow, index, end, array, layers, prefetch } = self;
+		fold_words(index, end, |word| unsafe { Q::filled_word(&array, word) }, init, |accumulator, index| {
+			if prefetch != 0 { unsafe { Q::prefetch(&array, index.saturating_add(prefetch)) }; }
+			if !layers.admits(ecs, index) { return accumulator; }
+			f(accumulator, unsafe { Q::get_unchecked(ecs, index, &array) })
+		})
+	}
 }
 
 pub struct QueryMutIter<'a, Q: QueryMut + 'a> {
-	ecs: &'a ECS,
-	index: usize,
-	array: Q::Array,
+	pub(crate) ecs: &'a ECS,
+	/// Held until the iterator and everything split from it is dropped
+	pub(crate) borrow: StorageBorrow,
+	pub(crate) index: usize,
+	pub(crate) end: usize,
+	pub(crate) array: Q::Array,
+	pub(crate) layers: Layers,
+	/// How many indices ahead to prefetch, 0 for none
+	pub(crate) prefetch: usize,
 }
 
 impl<'a, Q: QueryMut> QueryMutIter<'a, Q> {
 	pub(crate) fn new(ecs: &'a mut ECS) -> Self {
-		let array = unsafe { Q::get_array(ecs) };
-		QueryMutIter { ecs, index: 0, array }
+		ecs.unshare(&Q::component_ids());
+		unsafe { Self::new_unchecked(ecs) }
+	}
+
+	/// # Safety
+	/// Nothing else may access the Components of `Q` while this or any of its output lives,
+	/// and none of their columns may be shared with a fork.
+	pub(crate) unsafe fn new_unchecked(ecs: &'a ECS) -> Self {
+		assert_unique(&Q::component_ids());
+		let array = Q::get_array(ecs);
+		QueryMutIter { ecs, borrow: ecs.borrow_storage(), index: 0, end: ecs.capacity, array, layers: Layers::ALL, prefetch: 0 }
 	}
 }
 
 impl<'a, Q: QueryMut> Iterator for QueryMutIter<'a, Q> {
 	type Item = Q::Output<'a>;
-	fn next(&mut self) -> Option<Self::Item> {
-    	unsafe { Q::next(self.ecs, &mut self.index, &self.array) }
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let output = unsafe { Q::next(self.ecs, &mut self.index, self.end, &self.array) }?;
+			if self.prefetch != 0 { unsafe { Q::prefetch(&self.array, (self.index - 1).saturating_add(self.prefetch)) }; }
+			if self.layers.admits(self.ecs, self.index - 1) { return Some(output); }
+		}
     }
+
+	/// Same word at a time walk as `QueryIter::fold`
+	fn fold<B, F: FnMut(B, Self::Item) -> B>(self, init: B, mut f: F) -> B {
+		let QueryMutIter { ecs, borrow: _borrow, index, end, array, layers, prefetch } = self;
+		fold_words(index, end, |word| unsafe { Q::filled_word(&array, word) }, init, |accumulator, index| {
+			if prefetch != 0 { unsafe { Q::prefetch(&array, index.saturating_add(prefetch)) }; }
+			if !layers.admits(ecs, index) { return accumulator; }
+			f(accumulator, unsafe { Q::get_unchecked(ecs, index, &array) })
+		})
+	}
+}
+
+/// Fold over every index in `start..end` whose bit is set in `filled(word)`, in ascending order
+fn fold_words<B>(start: usize, end: usize, mut filled: impl FnMut(usize) -> u64, init: B, mut f: impl FnMut(B, usize) -> B) -> B {
+	let mut accumulator = init;
+	let mut base = start - start % 64;
+	while base < end {
+		let mut bits = filled(base / 64);
+		if base < start { bits &= u64::MAX << (start - base); }
+		if end - base < 64 { bits &= (1 << (end - base)) - 1; }
+		while bits != 0 {
+			accumulator = f(accumulator, base + bits.trailing_zeros() as usize);
+			bits &= bits - 1;
+		}
+		base += 64;
+	}
+	accumulator
+}
+
+impl<'a, Q: Query> QueryIter<'a, Q> {
+	/// Only visit entity indices within `range`, clamped to what is left of this iterator
+	pub fn with_range(mut self, range: Range<usize>) -> Self {
+		self.index = self.index.max(range.start);
+		self.end = self.end.min(range.end);
+		self
+	}
+
+	/// Only yield entities tagged with every bit of `mask`, see `ECS::set_tag`
+	pub fn with_layers(mut self, mask: u64) -> Self {
+		self.layers.require |= mask;
+		self
+	}
+
+	/// Skip entities tagged with any bit of `mask`
+	pub fn without_layers(mut self, mask: u64) -> Self {
+		self.layers.exclude |= mask;
+		self
+	}
+
+	/// Hint the CPU to load each column `distance` indices ahead of the one being yielded, 0 to stop.
+	/// Pays off on large Components whose stride outruns the hardware prefetcher. Only emits anything with
+	/// the `prefetch` feature on x86_64 or aarch64, and only while iterating sequentially.
+	pub fn prefetch(mut self, distance: usize) -> Self {
+		self.prefetch = distance;
+		self
+	}
+
+	/// Split the remaining indices into disjoint chunks of at most `chunk_size` indices each, which unlike the
+	/// query itself can be sent to other threads
+	pub fn chunks(self, chunk_size: usize) -> QueryChunks<'a, Q> {
+		assert!(chunk_size > 0, "chunk_size must be positive");
+		QueryChunks { iter: self, chunk_size }
+	}
+}
+
+impl<'a, Q: QueryMut> QueryMutIter<'a, Q> {
+	/// Only visit entity indices within `range`, clamped to what is left of this iterator
+	pub fn with_range(mut self, range: Range<usize>) -> Self {
+		self.index = self.index.max(range.start);
+		self.end = self.end.min(range.end);
+		self
+	}
+
+	/// Only yield entities tagged with every bit of `mask`, see `ECS::set_tag`
+	pub fn with_layers(mut self, mask: u64) -> Self {
+		self.layers.require |= mask;
+		self
+	}
+
+	/// Skip entities tagged with any bit of `mask`
+	pub fn without_layers(mut self, mask: u64) -> Self {
+		self.layers.exclude |= mask;
+		self
+	}
+
+	/// Hint the CPU to load each column `distance` indices ahead of the one being yielded, 0 to stop.
+	/// Pays off on large Components whose stride outruns the hardware prefetcher. Only emits anything with
+	/// the `prefetch` feature on x86_64 or aarch64, and only while iterating sequentially.
+	pub fn prefetch(mut self, distance: usize) -> Self {
+		self.prefetch = distance;
+		self
+	}
+
+	/// Split the remaining indices into disjoint chunks of at most `chunk_size` indices each, which unlike the
+	/// query itself can be sent to other threads
+	pub fn chunks(self, chunk_size: usize) -> QueryMutChunks<'a, Q> {
+		assert!(chunk_size > 0, "chunk_size must be positive");
+		QueryMutChunks { iter: self, chunk_size }
+	}
+}
+
+/// Yields one `QueryChunk` per index range, for dispatching to a job system
+pub struct QueryChunks<'a, Q: Query + 'a> {
+	iter: QueryIter<'a, Q>,
+	chunk_size: usize,
+}
+
+impl<'a, Q: Query> Iterator for QueryChunks<'a, Q> {
+	type Item = QueryChunk<'a, Q>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.iter.index >= self.iter.end { return None; }
+		let start = self.iter.index;
+		let end = self.iter.end.min(start + self.chunk_size);
+		self.iter.index = end;
+		Some(QueryChunk(QueryIter { ecs: self.iter.ecs, borrow: self.iter.borrow.clone(), index: start, end, array: self.iter.array.clone(), layers: self.iter.layers, prefetch: self.iter.prefetch }))
+	}
+}
+
+/// Yields one `QueryMutChunk` per index range, for dispatching to a job system
+pub struct QueryMutChunks<'a, Q: QueryMut + 'a> {
+	iter: QueryMutIter<'a, Q>,
+	chunk_size: usize,
+}
+
+impl<'a, Q: QueryMut> Iterator for QueryMutChunks<'a, Q> {
+	type Item = QueryMutChunk<'a, Q>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.iter.index >= self.iter.end { return None; }
+		let start = self.iter.index;
+		let end = self.iter.end.min(start + self.chunk_size);
+		self.iter.index = end;
+		Some(QueryMutChunk(QueryMutIter { ecs: self.iter.ecs, borrow: self.iter.borrow.clone(), index: start, end, array: self.iter.array.clone(), layers: self.iter.layers, prefetch: self.iter.prefetch }))
+	}
+}
+
+/// The part of a `QueryIter` covering one range of `chunks`, which can be sent to another thread
+pub struct QueryChunk<'a, Q: Query + 'a>(QueryIter<'a, Q>);
+
+impl<'a, Q: Query> Iterator for QueryChunk<'a, Q> {
+	type Item = Q::Output<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> { self.0.next() }
+
+	fn fold<B, F: FnMut(B, Self::Item) -> B>(self, init: B, f: F) -> B { self.0.fold(init, f) }
+}
+
+/// The part of a `QueryMutIter` covering one range of `chunks`, which can be sent to another thread
+pub struct QueryMutChunk<'a, Q: QueryMut + 'a>(QueryMutIter<'a, Q>);
+
+impl<'a, Q: QueryMut> Iterator for QueryMutChunk<'a, Q> {
+	type Item = Q::Output<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> { self.0.next() }
+
+	fn fold<B, F: FnMut(B, Self::Item) -> B>(self, init: B, f: F) -> B { self.0.fold(init, f) }
+}
+
+// A chunk only reads entity generations, tags, and its own indices of the queried columns, none of which
+// can be written while `'a` borrows the world, and its storage borrow is counted atomically. Chunks cover
+// disjoint indices, so sending them to different threads can't alias a mutable borrow either.
+unsafe impl<'a, Q: Query> Send for QueryChunk<'a, Q> where Q::Output<'a>: Send {}
+unsafe impl<'a, Q: QueryMut> Send for QueryMutChunk<'a, Q> where Q::Output<'a>: Send {}
+
+/// Every unordered pair of entities holding `C`, optionally restricted to those also holding a filter Component
+pub struct PairIter<'a, C: Component> {
+	ecs: &'a ECS,
+	column: Column<C>,
+	filter: Option<&'a ComponentArray>,
+	first: Option<(usize, &'a C)>,
+	index: usize,
+	second: usize,
+}
+
+impl<'a, C: Component> PairIter<'a, C> {
+	pub(crate) fn new(ecs: &'a ECS, filter: Option<&'a ComponentArray>) -> Self {
+		let column = ecs.components.get_column::<C>().unwrap();
+		PairIter { ecs, column, filter, first: None, index: 0, second: 0 }
+	}
+
+	fn get(&self, index: usize) -> Option<&'a C> {
+		if let Some(filter) = self.filter {
+			if !filter.is_filled(index) { return None; }
+		}
+		unsafe { self.column.get(index) }
+	}
+}
+
+impl<'a, C: Component> Iterator for PairIter<'a, C> {
+	type Item = (EntityID, &'a C, EntityID, &'a C);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if self.first.is_none() {
+				while self.index < self.ecs.capacity && self.first.is_none() {
+					self.first = self.get(self.index).map(|component| (self.index, component));
+					self.index += 1;
+				}
+				self.first?;
+				self.second = self.index;
+			}
+
+			let (first_index, first) = self.first.unwrap();
+			while self.second < self.ecs.capacity {
+				let second_index = self.second;
+				self.second += 1;
+				if let Some(second) = self.get(second_index) {
+					let first_id = self.ecs.get_index(first_index).unwrap();
+					let second_id = self.ecs.get_index(second_index).unwrap();
+					return Some((first_id, first, second_id, second));
+				}
+			}
+			self.first = None;
+		}
+	}
+}
+
+/// Alive entities that lack `C`. An unregistered `C` is lacked by everyone.
+pub struct WithoutIter<'a, C: Component> {
+	ecs: &'a ECS,
+	column: Option<Column<C>>,
+	index: usize,
+}
+
+impl<'a, C: Component> WithoutIter<'a, C> {
+	pub(crate) fn new(ecs: &'a ECS) -> Self {
+		WithoutIter { ecs, column: ecs.components.get_column::<C>(), index: 0 }
+	}
+}
+
+impl<'a, C: Component> Iterator for WithoutIter<'a, C> {
+	type Item = EntityID;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.index < self.ecs.capacity {
+			let index = self.index;
+			self.index += 1;
+			if let Some(id) = self.ecs.get_index(index) {
+				if !self.column.as_ref().is_some_and(|column| column.is_filled(index)) {
+					return Some(id);
+				}
+			}
+		}
+		None
+	}
+}
+
+/// Matches of `Q` whose entity lacks `C`
+pub struct ExcludingIter<'a, Q: Query + 'a, C: Component> {
+	iter: QueryIter<'a, Q>,
+	column: Option<Column<C>>,
+}
+
+impl<'a, Q: Query, C: Component> ExcludingIter<'a, Q, C> {
+	pub(crate) fn new(ecs: &'a ECS) -> Self {
+		ExcludingIter { iter: QueryIter::new(ecs), column: ecs.components.get_column::<C>() }
+	}
+}
+
+impl<'a, Q: Query, C: Component> Iterator for ExcludingIter<'a, Q, C> {
+	type Item = Q::Output<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let output = self.iter.next()?;
+			// `next` leaves the index one past the match
+			let index = self.iter.index - 1;
+			if !self.column.as_ref().is_some_and(|column| column.is_filled(index)) {
+				return Some(output);
+			}
+		}
+	}
 }
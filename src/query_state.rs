@@ -0,0 +1,117 @@
+use crate::tag::Layers;
+use crate::{Query, QueryIter, QueryMut, QueryMutIter, ECS};
+use core::marker::PhantomData;
+use runtime_id::RuntimeID;
+
+/// Columns of a query resolved once, refreshed only after new Component registrations
+struct Cached<A> {
+	scene_id: RuntimeID,
+	version: usize,
+	array: A,
+}
+
+impl<A: Clone> Cached<A> {
+	fn new(ecs: &ECS, array: A) -> Self {
+		Cached { scene_id: ecs.get_scene_id(), version: ecs.components.version(), array }
+	}
+
+	fn get(&mut self, ecs: &ECS, resolve: impl FnOnce() -> A) -> A {
+		assert!(self.scene_id == ecs.get_scene_id(), "QueryState used with a different ECS");
+		if self.version != ecs.components.version() {
+			self.array = resolve();
+			self.version = ecs.components.version();
+		}
+		self.array.clone()
+	}
+}
+
+/// `Q` prepared by `ECS::query_state` for repeated iteration of the same ECS
+pub struct QueryState<Q: Query> {
+	cached: Cached<Q::Array>,
+	_marker: PhantomData<fn() -> Q>,
+}
+
+impl<Q: Query> QueryState<Q> {
+	pub fn iter<'a>(&mut self, ecs: &'a ECS) -> QueryIter<'a, Q> {
+		let array = self.cached.get(ecs, || unsafe { Q::get_array(ecs) });
+		QueryIter { ecs, borrow: ecs.borrow_storage(), index: 0, end: ecs.capacity, array, layers: Layers::ALL, prefetch: 0 }
+	}
+}
+
+/// `Q` prepared by `ECS::query_mut_state` for repeated iteration of the same ECS
+pub struct QueryMutState<Q: QueryMut> {
+	cached: Cached<Q::Array>,
+	_marker: PhantomData<fn() -> Q>,
+}
+
+impl<Q: QueryMut> QueryMutState<Q> {
+	pub fn iter_mut<'a>(&mut self, ecs: &'a mut ECS) -> QueryMutIter<'a, Q> {
+		ecs.unshare(&Q::component_ids());
+		let array = self.cached.get(ecs, || unsafe { Q::get_array(ecs) });
+		QueryMutIter { ecs, borrow: ecs.borrow_storage(), index: 0, end: ecs.capacity, array, layers: Layers::ALL, prefetch: 0 }
+	}
+}
+
+impl ECS {
+	/// Resolve the Columns of `Q` once instead of on every `query`. Panics like `query` if a Component is unregistered.
+	pub fn query_state<Q: Query>(&self) -> QueryState<Q> {
+		let array = unsafe { Q::get_array(self) };
+		QueryState { cached: Cached::new(self, array), _marker: PhantomData }
+	}
+
+	pub fn query_mut_state<Q: QueryMut>(&self) -> QueryMutState<Q> {
+		let array = unsafe { Q::get_array(self) };
+		QueryMutState { cached: Cached::new(self, array), _marker: PhantomData }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+
+	struct Health(u32);
+	impl Component for Health {}
+	struct Armor(u32);
+	impl Component for Armor {}
+	struct Late;
+	impl Component for Late {}
+
+	#[test]
+	fn cached() {
+		let mut ecs = ECS::new(2);
+		ecs.set_grow_fn(Some(|capacity| capacity * 2));
+		ecs.register::<Health>();
+		ecs.register::<Armor>();
+		let mut read = ecs.query_state::<(Health, Armor)>();
+		let mut write = ecs.query_mut_state::<Health>();
+
+		for value in 0..4 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Health(value));
+			if value % 2 == 0 {
+				ecs.add_component(&entity, Armor(value));
+			}
+		}
+		// Registrations may move columns
+		for _ in 0..32 {
+			ecs.register::<Late>();
+		}
+
+		for (_, health) in write.iter_mut(&mut ecs) {
+			health.0 += 10;
+		}
+		let total: u32 = read.iter(&ecs).map(|(_, health, armor)| health.0 + armor.0).sum();
+		assert_eq!(total, 10 + 12 + 2);
+	}
+
+	#[test]
+	#[should_panic]
+	fn other_world() {
+		let mut ecs = ECS::new(2);
+		ecs.register::<Health>();
+		let mut state = ecs.query_state::<Health>();
+		let mut other = ECS::new(2);
+		other.register::<Health>();
+		state.iter(&other).count();
+	}
+}
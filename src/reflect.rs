@@ -0,0 +1,210 @@
+use crate::component::ComponentID;
+use crate::{Component, EntityID, ECS};
+use alloc::string::{String, ToString};
+use hashbrown::HashMap;
+
+/// Leaf value read or written through a reflection path
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReflectValue {
+	Bool(bool),
+	Int(i64),
+	Float(f64),
+	Str(String),
+}
+
+/// Named field access into a value. Structs return their fields, leaves return a value.
+pub trait Reflect {
+	fn field(&self, _name: &str) -> Option<&dyn Reflect> { None }
+
+	fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> { None }
+
+	/// Every name `field` accepts, for schema export
+	fn field_names() -> &'static [&'static str] where Self: Sized { &[] }
+
+	fn get_value(&self) -> Option<ReflectValue> { None }
+
+	/// Returns false if `value` has the wrong kind for this leaf
+	fn set_value(&mut self, _value: ReflectValue) -> bool { false }
+}
+
+macro_rules! reflect_int {
+	($($int:ty),*) => {$(
+		impl Reflect for $int {
+			fn get_value(&self) -> Option<ReflectValue> { Some(ReflectValue::Int(*self as i64)) }
+
+			fn set_value(&mut self, value: ReflectValue) -> bool {
+				match value {
+					ReflectValue::Int(value) => match <$int>::try_from(value) {
+						Ok(value) => { *self = value; true },
+						Err(_) => false,
+					},
+					_ => false,
+				}
+			}
+		}
+	)*};
+}
+
+reflect_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! reflect_float {
+	($($float:ty),*) => {$(
+		impl Reflect for $float {
+			fn get_value(&self) -> Option<ReflectValue> { Some(ReflectValue::Float(*self as f64)) }
+
+			fn set_value(&mut self, value: ReflectValue) -> bool {
+				match value {
+					ReflectValue::Float(value) => *self = value as $float,
+					ReflectValue::Int(value) => *self = value as $float,
+					_ => return false,
+				}
+				true
+			}
+		}
+	)*};
+}
+
+reflect_float!(f32, f64);
+
+impl Reflect for bool {
+	fn get_value(&self) -> Option<ReflectValue> { Some(ReflectValue::Bool(*self)) }
+
+	fn set_value(&mut self, value: ReflectValue) -> bool {
+		match value {
+			ReflectValue::Bool(value) => { *self = value; true },
+			_ => false,
+		}
+	}
+}
+
+impl Reflect for String {
+	fn get_value(&self) -> Option<ReflectValue> { Some(ReflectValue::Str(self.clone())) }
+
+	fn set_value(&mut self, value: ReflectValue) -> bool {
+		match value {
+			ReflectValue::Str(value) => { *self = value; true },
+			_ => false,
+		}
+	}
+}
+
+type ReflectFn = unsafe fn(*mut u8) -> *mut dyn Reflect;
+
+unsafe fn as_reflect<C: Component + Reflect>(pointer: *mut u8) -> *mut dyn Reflect {
+	pointer as *mut C as *mut dyn Reflect
+}
+
+/// Components reachable by name in reflection paths
+#[derive(Clone, Default)]
+pub(crate) struct ReflectRegistry {
+	map: HashMap<String, (ComponentID, ReflectFn)>,
+}
+
+impl ReflectRegistry {
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		self.map.retain(|_, (component, _)| *component != id);
+	}
+}
+
+impl ECS {
+	/// Make `C` reachable as the first segment `name` of `get_field` and `set_field` paths. Registers `C` if needed.
+	pub fn register_reflect<C: Component + Reflect>(&mut self, name: &str) {
+		if self.components.get_array::<C>().is_none() {
+			self.register::<C>();
+		}
+		self.reflect.map.insert(name.to_string(), (ComponentID::of::<C>(), as_reflect::<C>));
+	}
+
+	/// Component named by the first segment of `path` on `entity`, and the remaining segments
+	fn reflect_root<'p>(&self, entity: &EntityID, path: &'p str) -> Option<(ComponentID, *mut dyn Reflect, core::str::Split<'p, char>)> {
+		if !self.is_valid(entity) { return None; }
+		let mut segments = path.split('.');
+		let (id, reflect) = self.reflect.map.get(segments.next()?)?;
+		let pointer = self.components.get_array_by_id(*id)?.get_ptr(entity.index)?;
+		Some((*id, unsafe { reflect(pointer as *mut u8) }, segments))
+	}
+
+	/// Read the leaf at `path` such as `"Transform.position.x"`, the first segment being a name given to `register_reflect`
+	pub fn get_field(&self, entity: &EntityID, path: &str) -> Option<ReflectValue> {
+		let (_, root, segments) = self.reflect_root(entity, path)?;
+		let mut value: &dyn Reflect = unsafe { &*root };
+		for segment in segments {
+			value = value.field(segment)?;
+		}
+		value.get_value()
+	}
+
+	/// Write the leaf at `path`. Returns false if the path doesn't resolve or the value has the wrong kind.
+	pub fn set_field(&mut self, entity: &EntityID, path: &str, value: ReflectValue) -> bool {
+		let Some((id, _)) = path.split('.').next().and_then(|name| self.reflect.map.get(name)) else { return false };
+		if !self.is_valid(entity) { return false; }
+		self.components.unshare_index(*id, entity.index);
+		let Some((id, root, segments)) = self.reflect_root(entity, path) else { return false };
+		let mut target: &mut dyn Reflect = unsafe { &mut *root };
+		for segment in segments {
+			match target.field_mut(segment) {
+				Some(field) => target = field,
+				None => return false,
+			}
+		}
+		if !target.set_value(value) { return false; }
+		self.on_change(id, entity.index);
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Reflect, ReflectValue};
+	use crate::{Component, ECS};
+
+	#[derive(Default)]
+	struct Vec2 { x: f32, y: f32 }
+	impl Reflect for Vec2 {
+		fn field(&self, name: &str) -> Option<&dyn Reflect> {
+			match name { "x" => Some(&self.x), "y" => Some(&self.y), _ => None }
+		}
+
+		fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+			match name { "x" => Some(&mut self.x), "y" => Some(&mut self.y), _ => None }
+		}
+	}
+
+	#[derive(Default)]
+	struct Transform { position: Vec2, layer: u8 }
+	impl Component for Transform {}
+	impl Reflect for Transform {
+		fn field(&self, name: &str) -> Option<&dyn Reflect> {
+			match name { "position" => Some(&self.position), "layer" => Some(&self.layer), _ => None }
+		}
+
+		fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+			match name { "position" => Some(&mut self.position), "layer" => Some(&mut self.layer), _ => None }
+		}
+
+		fn field_names() -> &'static [&'static str] { &["position", "layer"] }
+	}
+
+	#[test]
+	fn paths() {
+		let mut ecs = ECS::new(4);
+		ecs.register_reflect::<Transform>("Transform");
+		let entity = ecs.create_entity().unwrap();
+		assert_eq!(ecs.get_field(&entity, "Transform.layer"), None);
+		ecs.add_component(&entity, Transform::default());
+
+		assert!(ecs.set_field(&entity, "Transform.position.x", ReflectValue::Float(1.5)));
+		assert!(ecs.set_field(&entity, "Transform.layer", ReflectValue::Int(3)));
+		assert!(!ecs.set_field(&entity, "Transform.layer", ReflectValue::Int(300)));
+		assert!(!ecs.set_field(&entity, "Transform.layer", ReflectValue::Bool(true)));
+		assert!(!ecs.set_field(&entity, "Transform.position.z", ReflectValue::Float(0.0)));
+
+		assert_eq!(ecs.get_field(&entity, "Transform.position.x"), Some(ReflectValue::Float(1.5)));
+		assert_eq!(ecs.get_field(&entity, "Transform.layer"), Some(ReflectValue::Int(3)));
+		assert_eq!(ecs.get_field(&entity, "Transform.position"), None);
+		assert_eq!(ecs.get_field(&entity, "Rigidbody.mass"), None);
+		let transform = ecs.get_component::<Transform>(&entity).unwrap();
+		assert_eq!(transform.position.x, 1.5);
+		assert!(transform.position.field("y").is_some());
+	}
+}
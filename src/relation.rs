@@ -0,0 +1,149 @@
+use crate::component::ComponentID;
+use crate::{Component, EntityID, WeakEntity, ECS};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+/// Component pointing at another entity. `T` distinguishes kinds of relation and carries any extra data.
+pub struct Relation<T: 'static> {
+	target: EntityID,
+	pub data: T,
+}
+
+impl<T: 'static> Relation<T> {
+	pub fn new(target: EntityID, data: T) -> Self {
+		Relation { target, data }
+	}
+
+	pub fn target(&self) -> EntityID { self.target }
+}
+
+impl<T: 'static> Component for Relation<T> {}
+
+/// What happens to a relation when its target is destroyed
+#[derive(Clone, Copy)]
+pub enum RelationPolicy {
+	/// Remove the `Relation` from its source
+	Remove,
+	/// Destroy the source too, cascading through its own relations
+	Destroy,
+	/// Leave the relation dangling and call the hook with `(source, target)`
+	Notify(fn(&mut ECS, EntityID, EntityID)),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RelationKind {
+	id: ComponentID,
+	sources: fn(&ECS, &EntityID) -> Vec<EntityID>,
+	remove: fn(&mut ECS, &EntityID),
+	retarget: fn(&mut ECS, &HashMap<WeakEntity, EntityID>),
+	policy: RelationPolicy,
+}
+
+fn sources<T: 'static>(ecs: &ECS, target: &EntityID) -> Vec<EntityID> {
+	ecs.query::<Relation<T>>()
+		.filter(|(_, relation)| relation.target == *target)
+		.map(|(source, _)| source)
+		.collect()
+}
+
+fn remove<T: 'static>(ecs: &mut ECS, source: &EntityID) {
+	ecs.remove_component::<Relation<T>>(source);
+}
+
+fn retarget<T: 'static>(ecs: &mut ECS, moves: &HashMap<WeakEntity, EntityID>) {
+	for (_, relation) in ecs.query_mut::<Relation<T>>() {
+		if let Some(new) = moves.get(&relation.target.downgrade()) {
+			relation.target = *new;
+		}
+	}
+}
+
+impl ECS {
+	/// Register `Relation<T>` and clean it up with `policy` whenever a target is destroyed.
+	/// Cleanup scans the `Relation<T>` column on every destroy.
+	pub fn register_relation<T: 'static>(&mut self, policy: RelationPolicy) {
+		self.register::<Relation<T>>();
+		self.relations.push(RelationKind { id: ComponentID::of::<Relation<T>>(), sources: sources::<T>, remove: remove::<T>, retarget: retarget::<T>, policy });
+	}
+
+	/// Stop cleaning up the relation stored as `id`, called when its column is unregistered
+	pub(crate) fn forget_relation(&mut self, id: ComponentID) {
+		self.relations.retain(|kind| kind.id != id);
+	}
+
+	/// Called after `compact` moved the entities in `moves`
+	pub(crate) fn retarget_relations(&mut self, moves: &HashMap<WeakEntity, EntityID>) {
+		for kind_index in 0..self.relations.len() {
+			(self.relations[kind_index].retarget)(self, moves);
+		}
+	}
+
+	/// Called after `target` has been destroyed
+	pub(crate) fn cleanup_relations(&mut self, target: EntityID) {
+		for kind_index in 0..self.relations.len() {
+			let kind = self.relations[kind_index];
+			for source in (kind.sources)(self, &target) {
+				match kind.policy {
+					RelationPolicy::Remove => (kind.remove)(self, &source),
+					RelationPolicy::Destroy => self.destroy_entity(source),
+					RelationPolicy::Notify(hook) => hook(self, source, target),
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Relation, RelationPolicy, ECS};
+	use core::sync::atomic::{AtomicUsize, Ordering};
+
+	struct ChildOf;
+	struct Targeting;
+	struct Watching;
+
+	#[test]
+	fn policies() {
+		static NOTIFIED: AtomicUsize = AtomicUsize::new(0);
+
+		let mut ecs = ECS::new(32);
+		ecs.register_relation::<ChildOf>(RelationPolicy::Destroy);
+		ecs.register_relation::<Targeting>(RelationPolicy::Remove);
+		ecs.register_relation::<Watching>(RelationPolicy::Notify(|ecs, source, target| {
+			assert!(ecs.is_valid(&source) && !ecs.is_valid(&target));
+			NOTIFIED.fetch_add(1, Ordering::Relaxed);
+		}));
+
+		let parent = ecs.create_entity().unwrap();
+		let child = ecs.create_entity().unwrap();
+		let grandchild = ecs.create_entity().unwrap();
+		let hunter = ecs.create_entity().unwrap();
+		ecs.add_component(&child, Relation::new(parent, ChildOf));
+		ecs.add_component(&grandchild, Relation::new(child, ChildOf));
+		ecs.add_component(&hunter, Relation::new(child, Targeting));
+		ecs.add_component(&hunter, Relation::new(grandchild, Watching));
+
+		ecs.destroy_entity(parent);
+		assert!(!ecs.is_valid(&child));
+		assert!(!ecs.is_valid(&grandchild));
+		assert!(ecs.is_valid(&hunter));
+		assert!(!ecs.has_component::<Relation<Targeting>>(&hunter));
+		assert!(ecs.has_component::<Relation<Watching>>(&hunter));
+		assert_eq!(NOTIFIED.load(Ordering::Relaxed), 1);
+		assert_eq!(ecs.get_entity_count(), 1);
+	}
+
+	#[test]
+	fn unregister() {
+		let mut ecs = ECS::new(4);
+		ecs.register_relation::<ChildOf>(RelationPolicy::Destroy);
+		let parent = ecs.create_entity().unwrap();
+		let child = ecs.create_entity().unwrap();
+		ecs.add_component(&child, Relation::new(parent, ChildOf));
+
+		assert!(ecs.unregister::<Relation<ChildOf>>());
+		ecs.destroy_entity(parent);
+		assert!(ecs.is_valid(&child));
+		assert_eq!(ecs.get_entity_count(), 1);
+	}
+}
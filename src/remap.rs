@@ -0,0 +1,138 @@
+use crate::{EntityID, WeakEntity, ECS};
+use hashbrown::HashMap;
+use runtime_id::RuntimeID;
+
+/// Records which entity in one scene became which in another, for fixing up stored handles after a transfer
+pub struct SceneRemapper {
+	from: RuntimeID,
+	to: RuntimeID,
+	map: HashMap<WeakEntity, EntityID>,
+}
+
+impl SceneRemapper {
+	pub fn new(from: &ECS, to: &ECS) -> Self {
+		SceneRemapper { from: from.get_scene_id(), to: to.get_scene_id(), map: HashMap::new() }
+	}
+
+	pub const fn get_from(&self) -> RuntimeID { self.from }
+
+	pub const fn get_to(&self) -> RuntimeID { self.to }
+
+	pub fn insert(&mut self, old: EntityID, new: EntityID) {
+		assert!(old.get_scene_id() == self.from, "old entity is not from the source scene");
+		assert!(new.get_scene_id() == self.to, "new entity is not from the destination scene");
+		self.map.insert(old.downgrade(), new);
+	}
+
+	pub fn get(&self, old: &EntityID) -> Option<EntityID> {
+		if old.get_scene_id() != self.from { return None; }
+		self.map.get(&old.downgrade()).copied()
+	}
+
+	/// Rewrite `entity` in place if it was mapped, returns whether it was
+	pub fn remap(&self, entity: &mut EntityID) -> bool {
+		match self.get(entity) {
+			Some(new) => {
+				*entity = new;
+				true
+			},
+			None => false,
+		}
+	}
+
+	pub fn len(&self) -> usize { self.map.len() }
+
+	pub fn is_empty(&self) -> bool { self.map.is_empty() }
+}
+
+impl ECS {
+	/// Move `entity` into `target` along with every Component `target` has registered, recording the mapping.
+	/// Components `target` doesn't know are dropped with the source entity, and relations here that pointed at it
+	/// are cleaned up as if it were destroyed. Returns `None`, leaving both worlds as they were, if `entity` is
+	/// invalid, `target` is full, or another entity in `target` already holds its `Guid`.
+	pub fn transfer_entity(&mut self, entity: EntityID, target: &mut ECS, remapper: &mut SceneRemapper) -> Option<EntityID> {
+		if !self.is_valid(&entity) { return None; }
+		let new = target.create_entity()?;
+		let guid = self.get_guid(&entity);
+		if let Some(guid) = guid {
+			if !target.set_guid(&new, guid) {
+				target.destroy_entity(new);
+				return None;
+			}
+			self.guids.on_destroy(entity.index);
+		}
+
+		self.indexes.on_destroy(entity, &self.components);
+		for id in self.components.move_index(entity.index, &mut target.components, new.index) {
+			self.groups.on_remove(id, entity.index, &mut self.components);
+			target.groups.on_insert(id, new.index, &mut target.components);
+			target.on_change(id, new.index);
+			target.index_insert(id, new.index);
+		}
+		target.tags[new.index] = self.tags[entity.index];
+		remapper.insert(entity, new);
+		self.destroy_entity(entity);
+		Some(new)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, SceneRemapper, ECS};
+
+	struct Health(usize);
+	impl Component for Health {}
+	struct Local;
+	impl Component for Local {}
+
+	#[test]
+	fn transfer() {
+		let mut room = ECS::new(8);
+		room.register::<Health>();
+		room.register::<Local>();
+		let mut lobby = ECS::new(8);
+		lobby.register::<Health>();
+
+		let player = room.create_entity().unwrap();
+		room.add_component(&player, Health(42));
+		room.add_component(&player, Local);
+		let bystander = room.create_entity().unwrap();
+
+		let mut remapper = SceneRemapper::new(&room, &lobby);
+		let moved = room.transfer_entity(player, &mut lobby, &mut remapper).unwrap();
+
+		assert!(!room.is_valid(&player));
+		assert!(lobby.is_valid(&moved));
+		assert!(moved.get_scene_id() == lobby.get_scene_id());
+		assert_eq!(lobby.get_component::<Health>(&moved).unwrap().0, 42);
+
+		let mut handle = player;
+		assert!(remapper.remap(&mut handle));
+		assert!(handle == moved);
+		assert!(remapper.get(&bystander).is_none());
+		assert_eq!(remapper.len(), 1);
+	}
+
+	#[test]
+	fn transfer_guid_taken() {
+		let mut room = ECS::new(4);
+		room.register::<Health>();
+		room.enable_guids(1);
+		let mut lobby = ECS::new(4);
+		lobby.register::<Health>();
+		lobby.enable_guids(1);
+
+		let player = room.create_entity().unwrap();
+		room.add_component(&player, Health(42));
+		let guid = room.get_guid(&player).unwrap();
+		let holder = lobby.create_entity().unwrap();
+		assert!(lobby.get_guid(&holder) == Some(guid));
+
+		let mut remapper = SceneRemapper::new(&room, &lobby);
+		assert!(room.transfer_entity(player, &mut lobby, &mut remapper).is_none());
+		assert_eq!(room.get_component::<Health>(&player).unwrap().0, 42);
+		assert!(room.get_guid(&player) == Some(guid));
+		assert_eq!(lobby.get_entity_count(), 1);
+		assert!(remapper.is_empty());
+	}
+}
@@ -0,0 +1,636 @@
+use crate::component::{combine_hashes, component_hash, fnv, ComponentID};
+use crate::storage::Occupancy;
+use crate::{Component, EntityID, ECS};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::mem;
+use hashbrown::{HashMap, HashSet};
+
+/// A Component that can be sent over the network. The encoding is up to the implementor
+/// but must round trip through `deserialize`.
+pub trait Replicated: Component {
+	fn serialize(&self, out: &mut Vec<u8>);
+	fn deserialize(bytes: &[u8]) -> Option<Self>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplicationError {
+	/// Packet ended in the middle of a field
+	Truncated,
+	/// Wire id not registered with `register_replicated` on this side
+	UnknownComponent(u16),
+	/// `Replicated::deserialize` rejected the bytes for this wire id
+	InvalidValue(u16),
+	/// Receiving ECS could not create another entity
+	Full,
+	/// Component entry is neither a value nor a removal
+	UnknownMarker(u8),
+	/// Sender replicates different Components or wire ids, see `ECS::replication_schema_hash`
+	SchemaMismatch,
+}
+
+/// Server side identity of an entity on the wire
+type WireEntity = (u64, u64);
+
+const REMOVED: u8 = 0;
+const VALUE: u8 = 1;
+
+#[derive(Clone, Copy)]
+struct NetComponent {
+	net_id: u16,
+	id: ComponentID,
+	/// Appends the value if `index` holds one
+	serialize: fn(&ECS, usize, &mut Vec<u8>) -> bool,
+	insert: fn(&mut ECS, &EntityID, &[u8]) -> bool,
+	remove: fn(&mut ECS, &EntityID),
+}
+
+fn serialize<C: Replicated>(ecs: &ECS, index: usize, out: &mut Vec<u8>) -> bool {
+	let Some(array) = ecs.components.get_array::<C>() else { return false };
+	match unsafe { array.get::<C>(index) } {
+		Some(component) => {
+			let mut bytes = Vec::new();
+			component.serialize(&mut bytes);
+			put_u32(out, bytes.len() as u32);
+			out.extend_from_slice(&bytes);
+			true
+		},
+		None => false,
+	}
+}
+
+fn insert<C: Replicated>(ecs: &mut ECS, entity: &EntityID, bytes: &[u8]) -> bool {
+	match C::deserialize(bytes) {
+		Some(component) => {
+			ecs.add_component(entity, component);
+			true
+		},
+		None => false,
+	}
+}
+
+fn remove<C: Replicated>(ecs: &mut ECS, entity: &EntityID) {
+	ecs.remove_component::<C>(entity);
+}
+
+type InterestFn = Box<dyn Fn(&ECS, &EntityID) -> bool + Send + Sync>;
+
+/// Handle to a receiver registered with `add_connection`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConnectionID(usize);
+
+struct Connection {
+	interest: InterestFn,
+	/// Entities this receiver currently mirrors
+	known: HashSet<WireEntity>,
+}
+
+/// Server side bookkeeping of what changed since the last `take_delta`
+pub(crate) struct ReplicationState {
+	components: Vec<NetComponent>,
+	connections: Vec<Option<Connection>>,
+	tracking: bool,
+	created: Vec<WireEntity>,
+	destroyed: Vec<WireEntity>,
+	/// One set per entry in `components`
+	dirty: Vec<Occupancy>,
+	length: usize,
+}
+
+impl ReplicationState {
+	pub(crate) fn new(length: usize) -> Self {
+		ReplicationState { components: Vec::new(), connections: Vec::new(), tracking: false, created: Vec::new(), destroyed: Vec::new(), dirty: Vec::new(), length }
+	}
+
+	fn position(&self, id: ComponentID) -> Option<usize> {
+		self.components.iter().position(|component| component.id == id)
+	}
+
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		if let Some(position) = self.position(id) {
+			self.components.remove(position);
+			self.dirty.remove(position);
+		}
+	}
+
+	pub(crate) fn on_create(&mut self, index: usize, generation: usize) {
+		if !self.tracking { return; }
+		self.created.push((index as u64, generation as u64));
+	}
+
+	pub(crate) fn on_destroy(&mut self, index: usize, generation: usize) {
+		if !self.tracking { return; }
+		let wire = (index as u64, generation as u64);
+		for dirty in &mut self.dirty {
+			dirty.set(index, false);
+		}
+		// Never seen by clients, so there's nothing to tell them
+		match self.created.iter().position(|created| *created == wire) {
+			Some(position) => { self.created.swap_remove(position); },
+			None => self.destroyed.push(wire),
+		}
+	}
+
+	pub(crate) fn on_change(&mut self, id: ComponentID, index: usize) {
+		if !self.tracking { return; }
+		if let Some(position) = self.position(id) {
+			self.dirty[position].set(index, true);
+		}
+	}
+
+	pub(crate) fn resize(&mut self, new_length: usize) {
+		self.length = new_length;
+		for dirty in &mut self.dirty {
+			dirty.resize(new_length);
+		}
+	}
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) { out.extend_from_slice(&value.to_le_bytes()); }
+
+fn put_u32(out: &mut Vec<u8>, value: u32) { out.extend_from_slice(&value.to_le_bytes()); }
+
+fn put_u64(out: &mut Vec<u8>, value: u64) { out.extend_from_slice(&value.to_le_bytes()); }
+
+fn put_entity(out: &mut Vec<u8>, (index, generation): WireEntity) {
+	put_u64(out, index);
+	put_u64(out, generation);
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, length: usize) -> Result<&'a [u8], ReplicationError> {
+		if self.bytes.len() < length { return Err(ReplicationError::Truncated); }
+		let (taken, rest) = self.bytes.split_at(length);
+		self.bytes = rest;
+		Ok(taken)
+	}
+
+	fn u8(&mut self) -> Result<u8, ReplicationError> { Ok(self.take(1)?[0]) }
+
+	fn u16(&mut self) -> Result<u16, ReplicationError> { Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap())) }
+
+	fn u32(&mut self) -> Result<u32, ReplicationError> { Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap())) }
+
+	fn u64(&mut self) -> Result<u64, ReplicationError> { Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap())) }
+
+	fn entity(&mut self) -> Result<WireEntity, ReplicationError> { Ok((self.u64()?, self.u64()?)) }
+}
+
+/// Changed Components of one entity, as positions into `ReplicationState::components`
+struct Update {
+	entity: WireEntity,
+	components: Vec<usize>,
+}
+
+/// Packet layout, all little endian:
+/// `replication_schema_hash` (u64), created count (u32) then (index u64, generation u64) each,
+/// destroyed count and entities the same way,
+/// update count (u32) then the updates as written by `encode_update`.
+fn encode(ecs: &ECS, created: &[WireEntity], destroyed: &[WireEntity], update_count: usize, updates: &[u8]) -> Vec<u8> {
+	let mut out = Vec::new();
+	put_u64(&mut out, ecs.replication_schema_hash());
+	put_u32(&mut out, created.len() as u32);
+	for entity in created {
+		put_entity(&mut out, *entity);
+	}
+	put_u32(&mut out, destroyed.len() as u32);
+	for entity in destroyed {
+		put_entity(&mut out, *entity);
+	}
+	put_u32(&mut out, update_count as u32);
+	out.extend_from_slice(updates);
+	out
+}
+
+/// One update: the entity, a Component count (u32), and per Component its wire id (u16)
+/// followed by either REMOVED or VALUE with a u32 length and the serialized bytes.
+fn encode_update(ecs: &ECS, update: &Update, out: &mut Vec<u8>) {
+	put_entity(out, update.entity);
+	put_u32(out, update.components.len() as u32);
+	for position in &update.components {
+		let component = ecs.replication.components[*position];
+		put_u16(out, component.net_id);
+		let marker = out.len();
+		out.push(VALUE);
+		if !(component.serialize)(ecs, update.entity.0 as usize, out) {
+			out[marker] = REMOVED;
+		}
+	}
+}
+
+fn encode_updates(ecs: &ECS, updates: &[Update]) -> Vec<u8> {
+	let mut out = Vec::new();
+	for update in updates {
+		encode_update(ecs, update, &mut out);
+	}
+	out
+}
+
+impl ECS {
+	/// Register `C` for replication under a wire id shared by every peer
+	pub fn register_replicated<C: Replicated>(&mut self, net_id: u16) {
+		let id = ComponentID::of::<C>();
+		assert!(self.replication.components.iter().all(|component| component.net_id != net_id && component.id != id), "Component or wire id already replicated");
+		self.register::<C>();
+		self.replication.components.push(NetComponent { net_id, id, serialize: serialize::<C>, insert: insert::<C>, remove: remove::<C> });
+		self.replication.dirty.push(Occupancy::new(self.replication.length));
+	}
+
+	/// Identifies the replicated Components by stable name, layout, and wire id. Every packet starts with it,
+	/// and `Replica::apply` rejects packets from a sender whose hash differs.
+	pub fn replication_schema_hash(&self) -> u64 {
+		let hashes = self.replication.components.iter().filter_map(|component| {
+			let info = self.components.get_array_by_id(component.id)?.info();
+			Some(fnv(component_hash(info), &component.net_id.to_le_bytes()))
+		});
+		combine_hashes(hashes.collect())
+	}
+
+	/// Start or stop recording changes for `take_delta`. Only the sending side needs this.
+	pub fn set_replication_tracking(&mut self, tracking: bool) {
+		self.replication.tracking = tracking;
+	}
+
+	/// Register a receiver that only mirrors entities `interest` accepts. Relevance is
+	/// re-evaluated every `take_connection_deltas`, entities entering it are sent in full
+	/// and entities leaving it are sent as destroyed. Turns on replication tracking.
+	pub fn add_connection<F: Fn(&ECS, &EntityID) -> bool + Send + Sync + 'static>(&mut self, interest: F) -> ConnectionID {
+		self.replication.tracking = true;
+		let connection = Some(Connection { interest: Box::new(interest), known: HashSet::new() });
+		let connections = &mut self.replication.connections;
+		match connections.iter().position(|slot| slot.is_none()) {
+			Some(index) => {
+				connections[index] = connection;
+				ConnectionID(index)
+			},
+			None => {
+				connections.push(connection);
+				ConnectionID(connections.len() - 1)
+			},
+		}
+	}
+
+	/// `add_connection` with relevance decided by a single Component, entities without `C` are never sent
+	pub fn add_connection_by<C: Component, F: Fn(&C) -> bool + Send + Sync + 'static>(&mut self, interest: F) -> ConnectionID {
+		self.add_connection(move |ecs, entity| ecs.get_component::<C>(entity).is_some_and(&interest))
+	}
+
+	pub fn remove_connection(&mut self, connection: ConnectionID) {
+		if let Some(slot) = self.replication.connections.get_mut(connection.0) {
+			*slot = None;
+		}
+	}
+
+	/// Encode one packet per connection holding only what is relevant to it, then reset
+	/// the tracking. Use either this or `take_delta` on a given ECS, not both.
+	pub fn take_connection_deltas(&mut self) -> Vec<(ConnectionID, Vec<u8>)> {
+		self.flush();
+		let updates = self.dirty_updates();
+		// Serialized once, every connection copies the bytes of the entities it knows
+		let mut dirty: HashMap<WireEntity, Vec<u8>> = HashMap::new();
+		for update in &updates {
+			let mut bytes = Vec::new();
+			encode_update(self, update, &mut bytes);
+			dirty.insert(update.entity, bytes);
+		}
+		let destroyed = mem::take(&mut self.replication.destroyed);
+		let mut connections = mem::take(&mut self.replication.connections);
+
+		let mut packets = Vec::new();
+		for (index, connection) in connections.iter_mut().enumerate() {
+			let Some(connection) = connection else { continue };
+			let mut connection_created = Vec::new();
+			let mut connection_destroyed = Vec::new();
+			let mut update_count = 0;
+			let mut connection_updates = Vec::new();
+
+			for wire in &destroyed {
+				if connection.known.remove(wire) {
+					connection_destroyed.push(*wire);
+				}
+			}
+			for entity_index in 0..self.capacity {
+				let Some(entity) = self.get_index(entity_index) else { continue };
+				let wire = (entity_index as u64, entity.generation as u64);
+				let relevant = (connection.interest)(self, &entity);
+				match (relevant, connection.known.contains(&wire)) {
+					(true, false) => {
+						connection.known.insert(wire);
+						connection_created.push(wire);
+						let components = (0..self.replication.components.len()).collect();
+						encode_update(self, &Update { entity: wire, components }, &mut connection_updates);
+						update_count += 1;
+					},
+					(true, true) => {
+						if let Some(bytes) = dirty.get(&wire) {
+							connection_updates.extend_from_slice(bytes);
+							update_count += 1;
+						}
+					},
+					(false, true) => {
+						connection.known.remove(&wire);
+						connection_destroyed.push(wire);
+					},
+					(false, false) => {},
+				}
+			}
+			let packet = encode(self, &connection_created, &connection_destroyed, update_count, &connection_updates);
+			packets.push((ConnectionID(index), packet));
+		}
+
+		self.replication.connections = connections;
+		self.reset_replication();
+		packets
+	}
+
+	/// Encode everything that changed since the last call and reset the tracking
+	pub fn take_delta(&mut self) -> Vec<u8> {
+		self.flush();
+		let updates = self.dirty_updates();
+		let packet = encode(self, &self.replication.created, &self.replication.destroyed, updates.len(), &encode_updates(self, &updates));
+		self.reset_replication();
+		packet
+	}
+
+	fn dirty_updates(&self) -> Vec<Update> {
+		let mut updates: Vec<Update> = Vec::new();
+		// Position in `updates` of each dirty entity index
+		let mut by_index: HashMap<usize, usize> = HashMap::new();
+		for position in 0..self.replication.components.len() {
+			for index in self.replication.dirty[position].iter() {
+				match by_index.get(&index) {
+					Some(update) => updates[*update].components.push(position),
+					None => {
+						by_index.insert(index, updates.len());
+						let entity = (index as u64, self.entities[index].generation as u64);
+						updates.push(Update { entity, components: alloc::vec![position] });
+					},
+				}
+			}
+		}
+		updates
+	}
+
+	fn reset_replication(&mut self) {
+		self.replication.created.clear();
+		self.replication.destroyed.clear();
+		for dirty in &mut self.replication.dirty {
+			*dirty = Occupancy::new(self.capacity);
+		}
+	}
+
+	/// Encode every alive entity and replicated Component, for a peer that has seen nothing yet
+	pub fn full_state(&self) -> Vec<u8> {
+		let mut created = Vec::new();
+		let mut updates = Vec::new();
+		for index in 0..self.capacity {
+			if let Some(entity) = self.get_index(index) {
+				let wire = (index as u64, entity.generation as u64);
+				created.push(wire);
+				let components = (0..self.replication.components.len()).collect();
+				updates.push(Update { entity: wire, components });
+			}
+		}
+		encode(self, &created, &[], updates.len(), &encode_updates(self, &updates))
+	}
+}
+
+/// Receiving side mapping from the sender's entities to local ones
+#[derive(Default)]
+pub struct Replica {
+	entities: HashMap<WireEntity, EntityID>,
+}
+
+impl Replica {
+	pub fn new() -> Self {
+		Replica::default()
+	}
+
+	/// Local entity mirroring the sender's entity at `index` and `generation`
+	pub fn get(&self, index: usize, generation: usize) -> Option<EntityID> {
+		self.entities.get(&(index as u64, generation as u64)).copied()
+	}
+
+	fn entity(&mut self, ecs: &mut ECS, wire: WireEntity) -> Result<EntityID, ReplicationError> {
+		if let Some(entity) = self.entities.get(&wire) {
+			return Ok(*entity);
+		}
+		let entity = ecs.create_entity().ok_or(ReplicationError::Full)?;
+		self.entities.insert(wire, entity);
+		Ok(entity)
+	}
+
+	/// Apply a packet from `take_delta` or `full_state`. Replicated Components must be registered under the same wire ids.
+	pub fn apply(&mut self, ecs: &mut ECS, packet: &[u8]) -> Result<(), ReplicationError> {
+		let mut reader = Reader { bytes: packet };
+		if reader.u64()? != ecs.replication_schema_hash() { return Err(ReplicationError::SchemaMismatch); }
+
+		for _ in 0..reader.u32()? {
+			let wire = reader.entity()?;
+			self.entity(ecs, wire)?;
+		}
+		for _ in 0..reader.u32()? {
+			let wire = reader.entity()?;
+			if let Some(entity) = self.entities.remove(&wire) {
+				ecs.destroy_entity(entity);
+			}
+		}
+		for _ in 0..reader.u32()? {
+			let entity = self.entity(ecs, reader.entity()?)?;
+			for _ in 0..reader.u32()? {
+				let net_id = reader.u16()?;
+				let component = *ecs.replication.components.iter()
+					.find(|component| component.net_id == net_id)
+					.ok_or(ReplicationError::UnknownComponent(net_id))?;
+				match reader.u8()? {
+					VALUE => {
+						let length = reader.u32()? as usize;
+						let bytes = reader.take(length)?;
+						if !(component.insert)(ecs, &entity, bytes) {
+							return Err(ReplicationError::InvalidValue(net_id));
+						}
+					},
+					REMOVED => (component.remove)(ecs, &entity),
+					marker => return Err(ReplicationError::UnknownMarker(marker)),
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, Replica, Replicated, ReplicationError, ECS};
+	use alloc::vec::Vec;
+
+	#[derive(Debug, PartialEq, Eq)]
+	struct Health(u32);
+	impl Component for Health {}
+	impl Replicated for Health {
+		fn serialize(&self, out: &mut Vec<u8>) { out.extend_from_slice(&self.0.to_le_bytes()); }
+		fn deserialize(bytes: &[u8]) -> Option<Self> { Some(Health(u32::from_le_bytes(bytes.try_into().ok()?))) }
+	}
+
+	#[derive(Debug, PartialEq, Eq)]
+	struct Stunned;
+	impl Component for Stunned {}
+	impl Replicated for Stunned {
+		fn serialize(&self, _: &mut Vec<u8>) {}
+		fn deserialize(_: &[u8]) -> Option<Self> { Some(Stunned) }
+	}
+
+	fn peer() -> ECS {
+		let mut ecs = ECS::new(16);
+		ecs.register_replicated::<Health>(1);
+		ecs.register_replicated::<Stunned>(2);
+		ecs
+	}
+
+	#[test]
+	fn delta() {
+		let mut server = peer();
+		server.set_replication_tracking(true);
+		let mut client = peer();
+		let mut replica = Replica::new();
+
+		let a = server.create_entity().unwrap();
+		let b = server.create_entity().unwrap();
+		server.add_component(&a, Health(100));
+		server.add_component(&b, Stunned);
+		let short_lived = server.create_entity().unwrap();
+		server.destroy_entity(short_lived);
+		replica.apply(&mut client, &server.take_delta()).unwrap();
+
+		assert_eq!(client.get_entity_count(), 2);
+		let client_a = replica.get(a.index, a.generation).unwrap();
+		let client_b = replica.get(b.index, b.generation).unwrap();
+		assert_eq!(client.get_component::<Health>(&client_a), Some(&Health(100)));
+		assert!(client.has_component::<Stunned>(&client_b));
+
+		server.get_component_mut::<Health>(&a).unwrap().0 = 60;
+		server.remove_component::<Stunned>(&b);
+		let delta = server.take_delta();
+		replica.apply(&mut client, &delta).unwrap();
+		assert_eq!(client.get_component::<Health>(&client_a), Some(&Health(60)));
+		assert!(!client.has_component::<Stunned>(&client_b));
+
+		server.destroy_entity(a);
+		replica.apply(&mut client, &server.take_delta()).unwrap();
+		assert!(!client.is_valid(&client_a));
+
+		// Nothing changed
+		let empty = server.take_delta();
+		assert_eq!(empty.len(), 20);
+		assert_eq!(replica.apply(&mut client, &delta[..delta.len() - 1]), Err(ReplicationError::Truncated));
+	}
+
+	#[test]
+	fn unknown_marker() {
+		let mut server = peer();
+		let entity = server.create_entity().unwrap();
+		server.add_component(&entity, Health(1));
+		let mut packet = server.full_state();
+		// Header, created, destroyed, update count and entity, Component count, wire id, then the marker
+		let marker = 8 + 4 + 16 + 4 + 4 + 16 + 4 + 2;
+		assert_eq!(packet[marker], 1);
+		packet[marker] = 7;
+		let mut client = peer();
+		assert_eq!(Replica::new().apply(&mut client, &packet), Err(ReplicationError::UnknownMarker(7)));
+	}
+
+	#[test]
+	fn absent_not_dirty() {
+		let mut server = peer();
+		let entity = server.create_entity().unwrap();
+		server.set_replication_tracking(true);
+		server.take_delta();
+		assert!(server.get_component_mut::<Health>(&entity).is_none());
+		assert_eq!(server.take_delta().len(), 20);
+	}
+
+	#[derive(Debug, PartialEq, Eq)]
+	struct Position(i32);
+	impl Component for Position {}
+
+	#[test]
+	fn interest() {
+		let mut server = peer();
+		server.register::<Position>();
+		let near = server.add_connection_by::<Position, _>(|position| position.0.abs() < 10);
+		let everything = server.add_connection(|_, _| true);
+		let mut near_client = peer();
+		let mut near_replica = Replica::new();
+		let mut far_client = peer();
+		let mut far_replica = Replica::new();
+		let mut apply = |server: &mut ECS, near_client: &mut ECS, far_client: &mut ECS| {
+			for (connection, packet) in server.take_connection_deltas() {
+				if connection == near {
+					near_replica.apply(near_client, &packet).unwrap();
+				}
+				if connection == everything {
+					far_replica.apply(far_client, &packet).unwrap();
+				}
+			}
+		};
+
+		let a = server.create_entity().unwrap();
+		server.add_component(&a, Position(0));
+		server.add_component(&a, Health(10));
+		let b = server.create_entity().unwrap();
+		server.add_component(&b, Position(50));
+		server.add_component(&b, Health(20));
+		apply(&mut server, &mut near_client, &mut far_client);
+		assert_eq!(near_client.get_entity_count(), 1);
+		assert_eq!(far_client.get_entity_count(), 2);
+
+		// b walks into range and a walks out
+		server.get_component_mut::<Position>(&b).unwrap().0 = 5;
+		server.get_component_mut::<Position>(&a).unwrap().0 = -50;
+		server.get_component_mut::<Health>(&b).unwrap().0 = 15;
+		apply(&mut server, &mut near_client, &mut far_client);
+		let values: Vec<_> = near_client.query::<Health>().map(|(_, health)| health.0).collect();
+		assert_eq!(values, [15]);
+		assert_eq!(far_client.get_entity_count(), 2);
+
+		server.remove_connection(near);
+		server.destroy_entity(b);
+		let packets = server.take_connection_deltas();
+		assert_eq!(packets.len(), 1);
+		far_replica.apply(&mut far_client, &packets[0].1).unwrap();
+		assert_eq!(far_client.get_entity_count(), 1);
+	}
+
+	#[test]
+	fn schema_mismatch() {
+		let server = peer();
+		let mut swapped = ECS::new(16);
+		swapped.register_replicated::<Health>(2);
+		swapped.register_replicated::<Stunned>(1);
+		assert_eq!(Replica::new().apply(&mut swapped, &server.full_state()), Err(ReplicationError::SchemaMismatch));
+
+		let mut renamed = peer();
+		renamed.register_stable_name::<Health>("health");
+		assert_ne!(renamed.replication_schema_hash(), server.replication_schema_hash());
+		assert!(Replica::new().apply(&mut peer(), &server.full_state()).is_ok());
+	}
+
+	#[test]
+	fn late_join() {
+		let mut server = peer();
+		for value in 0..4 {
+			let entity = server.create_entity().unwrap();
+			server.add_component(&entity, Health(value));
+		}
+
+		let mut client = peer();
+		Replica::new().apply(&mut client, &server.full_state()).unwrap();
+		let mut values: Vec<_> = client.query::<Health>().map(|(_, health)| health.0).collect();
+		values.sort();
+		assert_eq!(values, [0, 1, 2, 3]);
+	}
+}
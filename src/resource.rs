@@ -0,0 +1,91 @@
+use crate::ECS;
+use alloc::boxed::Box;
+use core::any::{Any, TypeId};
+use hashbrown::HashMap;
+
+/// Singletons stored by type alongside the entities
+#[derive(Default)]
+pub(crate) struct Resources {
+	map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+	/// Never leave the thread that inserted them, since the `ECS` is neither `Send` nor `Sync`
+	non_send: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl ECS {
+	/// Store `resource`, returning any previous value of the same type
+	pub fn insert_resource<R: Send + Sync + 'static>(&mut self, resource: R) -> Option<R> {
+		let old = self.resources.map.insert(TypeId::of::<R>(), Box::new(resource))?;
+		Some(*old.downcast::<R>().unwrap())
+	}
+
+	pub fn remove_resource<R: Send + Sync + 'static>(&mut self) -> Option<R> {
+		let old = self.resources.map.remove(&TypeId::of::<R>())?;
+		Some(*old.downcast::<R>().unwrap())
+	}
+
+	pub fn has_resource<R: Send + Sync + 'static>(&self) -> bool {
+		self.resources.map.contains_key(&TypeId::of::<R>())
+	}
+
+	pub fn get_resource<R: Send + Sync + 'static>(&self) -> Option<&R> {
+		self.resources.map.get(&TypeId::of::<R>())?.downcast_ref()
+	}
+
+	pub fn get_resource_mut<R: Send + Sync + 'static>(&mut self) -> Option<&mut R> {
+		self.resources.map.get_mut(&TypeId::of::<R>())?.downcast_mut()
+	}
+
+	/// Store a resource that must stay on the current thread, such as a window handle. The `ECS` is neither
+	/// `Send` nor `Sync` and `Schedule::run` runs every system on the calling thread, so it never leaves the thread
+	/// that inserted it.
+	pub fn insert_non_send<R: 'static>(&mut self, resource: R) -> Option<R> {
+		let old = self.resources.non_send.insert(TypeId::of::<R>(), Box::new(resource))?;
+		Some(*old.downcast::<R>().unwrap())
+	}
+
+	pub fn remove_non_send<R: 'static>(&mut self) -> Option<R> {
+		let old = self.resources.non_send.remove(&TypeId::of::<R>())?;
+		Some(*old.downcast::<R>().unwrap())
+	}
+
+	pub fn get_non_send<R: 'static>(&self) -> Option<&R> {
+		self.resources.non_send.get(&TypeId::of::<R>())?.downcast_ref()
+	}
+
+	pub fn get_non_send_mut<R: 'static>(&mut self) -> Option<&mut R> {
+		self.resources.non_send.get_mut(&TypeId::of::<R>())?.downcast_mut()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::ECS;
+
+	struct Time(f32);
+
+	#[test]
+	fn resources() {
+		let mut ecs = ECS::new(1);
+		assert!(ecs.get_resource::<Time>().is_none());
+		assert!(ecs.insert_resource(Time(0.0)).is_none());
+		ecs.get_resource_mut::<Time>().unwrap().0 += 0.5;
+		assert_eq!(ecs.insert_resource(Time(2.0)).unwrap().0, 0.5);
+		assert!(ecs.has_resource::<Time>());
+		assert_eq!(ecs.remove_resource::<Time>().unwrap().0, 2.0);
+		assert!(!ecs.has_resource::<Time>());
+	}
+
+	#[test]
+	fn non_send() {
+		use alloc::rc::Rc;
+		let mut ecs = ECS::new(1);
+		let window = Rc::new(7);
+		ecs.insert_non_send(window.clone());
+		assert_eq!(**ecs.get_non_send::<Rc<i32>>().unwrap(), 7);
+		assert_eq!(Rc::strong_count(&window), 2);
+		assert!(ecs.remove_non_send::<Rc<i32>>().is_some());
+		ecs.insert_non_send(window.clone());
+		drop(ecs);
+		assert_eq!(Rc::strong_count(&window), 1);
+	}
+}
@@ -0,0 +1,140 @@
+use crate::storage::Column;
+use crate::tag::Layers;
+use crate::{Component, QueryIter, QueryMutIter, StorageBorrow, ECS};
+use core::ops::Range;
+
+/// Maximal runs of adjacent indices holding `C`, see `QueryIter::runs`
+pub struct Runs<'a, C: Component> {
+	ecs: &'a ECS,
+	_borrow: StorageBorrow,
+	index: usize,
+	end: usize,
+	array: Column<C>,
+	layers: Layers,
+}
+
+impl<'a, C: Component> Iterator for Runs<'a, C> {
+	/// Index of the first value and the values, stored back to back
+	type Item = (usize, &'a [C]);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (ecs, array, layers) = (self.ecs, self.array, self.layers);
+		let mut run = next_run(&mut self.index, self.end, |word| layers.admits_word(ecs, word, array.filled_word(word)))?;
+		// Columns are only contiguous within a page
+		run.end = run.end.min(array.contiguous_end(run.start));
+		self.index = run.end;
+		Some((run.start, unsafe { array.slice(run) }))
+	}
+}
+
+/// Maximal runs of adjacent indices holding `C`, see `QueryMutIter::runs`
+pub struct RunsMut<'a, C: Component> {
+	ecs: &'a ECS,
+	_borrow: StorageBorrow,
+	index: usize,
+	end: usize,
+	array: Column<C>,
+	layers: Layers,
+}
+
+impl<'a, C: Component> Iterator for RunsMut<'a, C> {
+	type Item = (usize, &'a mut [C]);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (ecs, array, layers) = (self.ecs, self.array, self.layers);
+		let mut run = next_run(&mut self.index, self.end, |word| layers.admits_word(ecs, word, array.filled_word(word)))?;
+		run.end = run.end.min(array.contiguous_end(run.start));
+		self.index = run.end;
+		for index in run.clone() {
+			unsafe { array.stamp(index, ecs.change_tick) };
+		}
+		// Runs never overlap, so neither do the slices
+		Some((run.start, unsafe { array.slice_mut(run) }))
+	}
+}
+
+/// The first run of set bits at or after `index` and below `end`, leaving `index` just past it
+fn next_run(index: &mut usize, end: usize, filled: impl Fn(usize) -> u64) -> Option<Range<usize>> {
+	while *index < end {
+		let bits = filled(*index / 64) >> (*index % 64);
+		if bits != 0 {
+			*index += bits.trailing_zeros() as usize;
+			break;
+		}
+		*index = (*index / 64 + 1) * 64;
+	}
+	if *index >= end { return None; }
+
+	let start = *index;
+	loop {
+		let offset = *index % 64;
+		let ones = (filled(*index / 64) >> offset).trailing_ones() as usize;
+		*index += ones;
+		// Only a run reaching the end of its word can carry on into the next
+		if ones < 64 - offset || *index >= end { break; }
+	}
+	*index = (*index).min(end);
+	Some(start..*index)
+}
+
+impl<'a, C: Component> QueryIter<'a, C> {
+	/// Yield slices of adjacent values instead of one entity at a time, for batch or SIMD processing.
+	/// Dense worlds give long runs, sparse ones degrade to short runs, and no run crosses a page of storage.
+	/// Entities come from `ECS::get_index`.
+	pub fn runs(self) -> Runs<'a, C> {
+		Runs { ecs: self.ecs, _borrow: self.borrow, index: self.index, end: self.end, array: self.array, layers: self.layers }
+	}
+}
+
+impl<'a, C: Component> QueryMutIter<'a, C> {
+	/// Mutable counterpart of `QueryIter::runs`
+	pub fn runs(self) -> RunsMut<'a, C> {
+		RunsMut { ecs: self.ecs, _borrow: self.borrow, index: self.index, end: self.end, array: self.array, layers: self.layers }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+	use alloc::vec::Vec;
+
+	struct Position(f32);
+	impl Component for Position {}
+
+	#[test]
+	fn runs() {
+		let mut ecs = ECS::new(200);
+		ecs.register::<Position>();
+		for index in 0..200 {
+			let entity = ecs.create_entity().unwrap();
+			if !(10..20).contains(&index) && index != 150 {
+				ecs.add_component(&entity, Position(index as f32));
+			}
+			if index == 5 {
+				ecs.set_tag(&entity, 1);
+			}
+		}
+
+		let runs: Vec<_> = ecs.query::<Position>().runs().map(|(start, run)| (start, run.len())).collect();
+		assert_eq!(runs, [(0, 10), (20, 130), (151, 49)]);
+		let runs: Vec<_> = ecs.query::<Position>().with_range(60..155).runs().map(|(start, run)| (start, run.len())).collect();
+		assert_eq!(runs, [(60, 90), (151, 4)]);
+		let runs: Vec<_> = ecs.query::<Position>().without_layers(1).runs().map(|(start, run)| (start, run.len())).collect();
+		assert_eq!(runs[..2], [(0, 5), (6, 4)]);
+
+		for (_, run) in ecs.query_mut::<Position>().runs() {
+			run.iter_mut().for_each(|position| position.0 *= 2.0);
+		}
+		let (start, run) = ecs.query::<Position>().runs().nth(1).unwrap();
+		assert_eq!((start, run[0].0, run[129].0), (20, 40.0, 298.0));
+
+		let mut ecs = ECS::new(600);
+		ecs.register_paged::<Position>();
+		for index in 0..600 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(index as f32));
+		}
+		let runs: Vec<_> = ecs.query::<Position>().runs().map(|(start, run)| (start, run.len())).collect();
+		assert_eq!(runs, [(0, 256), (256, 256), (512, 88)]);
+	}
+}
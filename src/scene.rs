@@ -0,0 +1,584 @@
+use crate::{Component, ComponentRegistry, EntityID, Guid, ECS};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Parsed value from a scene file. Both JSON and the RON subset map onto this:
+/// `{ "a": 1 }` and `(a: 1)` are `Map`, `[1, 2]` and `(1, 2)` are `List`, a struct or
+/// tuple name such as `Position(x: 1)` is ignored, and bare identifiers are `Str`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneValue {
+	Unit,
+	Bool(bool),
+	Int(i64),
+	Float(f64),
+	Str(String),
+	List(Vec<SceneValue>),
+	Map(Vec<(String, SceneValue)>),
+}
+
+impl SceneValue {
+	pub fn as_bool(&self) -> Option<bool> {
+		match self { SceneValue::Bool(value) => Some(*value), _ => None }
+	}
+
+	pub fn as_i64(&self) -> Option<i64> {
+		match self { SceneValue::Int(value) => Some(*value), _ => None }
+	}
+
+	/// Integers are accepted too, `1` is a valid float in a hand written file
+	pub fn as_f64(&self) -> Option<f64> {
+		match self {
+			SceneValue::Float(value) => Some(*value),
+			SceneValue::Int(value) => Some(*value as f64),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self { SceneValue::Str(value) => Some(value), _ => None }
+	}
+
+	pub fn as_list(&self) -> Option<&[SceneValue]> {
+		match self { SceneValue::List(values) => Some(values), _ => None }
+	}
+
+	/// Field of a `Map`
+	pub fn get(&self, field: &str) -> Option<&SceneValue> {
+		match self {
+			SceneValue::Map(fields) => fields.iter().find(|(name, _)| name == field).map(|(_, value)| value),
+			_ => None,
+		}
+	}
+}
+
+/// A Component that can be written in a scene file
+pub trait FromScene: Component {
+	fn from_scene(value: &SceneValue) -> Option<Self>;
+}
+
+pub(crate) type SceneFn = fn(&mut ECS, &EntityID, &SceneValue) -> bool;
+
+/// Inserts a Component upgraded from a value written by an older scene version
+pub(crate) type MigrateFn = Arc<dyn Fn(&mut ECS, &EntityID, &SceneValue) -> bool + Send + Sync>;
+
+pub(crate) fn insert<C: FromScene>(ecs: &mut ECS, entity: &EntityID, value: &SceneValue) -> bool {
+	match C::from_scene(value) {
+		Some(component) => {
+			ecs.add_component(entity, component);
+			true
+		},
+		None => false,
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SceneError {
+	/// Malformed text at this line, counting from 1
+	Syntax(usize),
+	/// Entity list or entity entry has the wrong shape
+	Layout,
+	/// No `register_scene` under this name
+	UnknownComponent(String),
+	/// `FromScene::from_scene` rejected the value for this name
+	InvalidValue(String),
+	/// The ECS could not create another entity
+	Full,
+	/// Another entity already holds this `Guid`
+	DuplicateGuid(u64),
+	/// The scene declares a `schema` other than the registry's `schema_hash`
+	SchemaMismatch,
+}
+
+impl fmt::Display for SceneError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SceneError::Syntax(line) => write!(f, "syntax error on line {}", line),
+			SceneError::Layout => write!(f, "expected a list of entities with a components map"),
+			SceneError::UnknownComponent(name) => write!(f, "unknown component {}", name),
+			SceneError::InvalidValue(name) => write!(f, "invalid value for component {}", name),
+			SceneError::Full => write!(f, "ECS is full"),
+			SceneError::DuplicateGuid(guid) => write!(f, "guid {} is already in use", guid),
+			SceneError::SchemaMismatch => write!(f, "scene was written for other components"),
+		}
+	}
+}
+
+impl core::error::Error for SceneError {}
+
+/// Lists, maps and parentheses nested deeper than this are a syntax error, so hostile
+/// input can't exhaust the stack
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+	text: &'a [u8],
+	position: usize,
+	depth: usize,
+}
+
+impl Parser<'_> {
+	fn error(&self) -> SceneError {
+		let line = self.text[..self.position.min(self.text.len())].iter().filter(|byte| **byte == b'\n').count();
+		SceneError::Syntax(line + 1)
+	}
+
+	fn skip_whitespace(&mut self) {
+		while let Some(byte) = self.text.get(self.position) {
+			if byte.is_ascii_whitespace() {
+				self.position += 1;
+			} else if self.text[self.position..].starts_with(b"//") {
+				while self.text.get(self.position).is_some_and(|byte| *byte != b'\n') {
+					self.position += 1;
+				}
+			} else {
+				break;
+			}
+		}
+	}
+
+	fn peek(&mut self) -> Option<u8> {
+		self.skip_whitespace();
+		self.text.get(self.position).copied()
+	}
+
+	fn expect(&mut self, byte: u8) -> Result<(), SceneError> {
+		if self.peek() != Some(byte) { return Err(self.error()); }
+		self.position += 1;
+		Ok(())
+	}
+
+	/// Consumes a separating comma, trailing ones are allowed
+	fn separator(&mut self, close: u8) -> Result<bool, SceneError> {
+		match self.peek() {
+			Some(b',') => {
+				self.position += 1;
+				Ok(self.peek() != Some(close))
+			},
+			Some(byte) if byte == close => Ok(false),
+			_ => Err(self.error()),
+		}
+	}
+
+	fn value(&mut self) -> Result<SceneValue, SceneError> {
+		if self.depth == MAX_DEPTH { return Err(self.error()); }
+		self.depth += 1;
+		let value = self.nested();
+		self.depth -= 1;
+		value
+	}
+
+	fn nested(&mut self) -> Result<SceneValue, SceneError> {
+		match self.peek().ok_or_else(|| self.error())? {
+			b'"' => Ok(SceneValue::Str(self.string()?)),
+			b'[' => {
+				self.position += 1;
+				let mut values = Vec::new();
+				if self.peek() != Some(b']') {
+					loop {
+						values.push(self.value()?);
+						if !self.separator(b']')? { break; }
+					}
+				}
+				self.expect(b']')?;
+				Ok(SceneValue::List(values))
+			},
+			b'{' => {
+				self.position += 1;
+				let mut fields = Vec::new();
+				if self.peek() != Some(b'}') {
+					loop {
+						let name = match self.peek() {
+							Some(b'"') => self.string()?,
+							_ => self.identifier().ok_or_else(|| self.error())?,
+						};
+						self.expect(b':')?;
+						fields.push((name, self.value()?));
+						if !self.separator(b'}')? { break; }
+					}
+				}
+				self.expect(b'}')?;
+				Ok(SceneValue::Map(fields))
+			},
+			b'(' => self.parenthesized(),
+			byte if byte == b'-' || byte.is_ascii_digit() => self.number(),
+			_ => {
+				let identifier = self.identifier().ok_or_else(|| self.error())?;
+				match identifier.as_str() {
+					"true" => Ok(SceneValue::Bool(true)),
+					"false" => Ok(SceneValue::Bool(false)),
+					"null" | "None" => Ok(SceneValue::Unit),
+					_ if self.peek() == Some(b'(') => self.parenthesized(),
+					_ => Ok(SceneValue::Str(identifier)),
+				}
+			},
+		}
+	}
+
+	/// `()`, `(a, b)` or `(name: a)`
+	fn parenthesized(&mut self) -> Result<SceneValue, SceneError> {
+		self.expect(b'(')?;
+		if self.peek() == Some(b')') {
+			self.position += 1;
+			return Ok(SceneValue::Unit);
+		}
+
+		let start = self.position;
+		let named = self.identifier().is_some() && self.peek() == Some(b':');
+		self.position = start;
+
+		let value = if named {
+			let mut fields = Vec::new();
+			loop {
+				let name = self.identifier().ok_or_else(|| self.error())?;
+				self.expect(b':')?;
+				fields.push((name, self.value()?));
+				if !self.separator(b')')? { break; }
+			}
+			SceneValue::Map(fields)
+		} else {
+			let mut values = Vec::new();
+			loop {
+				values.push(self.value()?);
+				if !self.separator(b')')? { break; }
+			}
+			SceneValue::List(values)
+		};
+		self.expect(b')')?;
+		Ok(value)
+	}
+
+	fn identifier(&mut self) -> Option<String> {
+		self.skip_whitespace();
+		let start = self.position;
+		while self.text.get(self.position).is_some_and(|byte| byte.is_ascii_alphanumeric() || *byte == b'_') {
+			self.position += 1;
+		}
+		if start == self.position || self.text[start].is_ascii_digit() {
+			self.position = start;
+			return None;
+		}
+		Some(String::from_utf8_lossy(&self.text[start..self.position]).into_owned())
+	}
+
+	fn string(&mut self) -> Result<String, SceneError> {
+		self.expect(b'"')?;
+		let mut bytes = Vec::new();
+		loop {
+			let byte = *self.text.get(self.position).ok_or_else(|| self.error())?;
+			self.position += 1;
+			match byte {
+				b'"' => break,
+				b'\\' => {
+					let escaped = *self.text.get(self.position).ok_or_else(|| self.error())?;
+					self.position += 1;
+					let character = match escaped {
+						b'n' => '\n',
+						b't' => '\t',
+						b'r' => '\r',
+						b'b' => '\u{8}',
+						b'f' => '\u{c}',
+						b'"' | b'\\' | b'/' => escaped as char,
+						b'u' => self.unicode()?,
+						_ => return Err(self.error()),
+					};
+					bytes.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes());
+				},
+				_ => bytes.push(byte),
+			}
+		}
+		String::from_utf8(bytes).map_err(|_| self.error())
+	}
+
+	/// The rest of a `\uXXXX` escape, joining a surrogate pair with the `\uXXXX` after it
+	fn unicode(&mut self) -> Result<char, SceneError> {
+		let high = self.hex4()?;
+		let code = if (0xD800..0xDC00).contains(&high) {
+			if !self.text[self.position..].starts_with(b"\\u") { return Err(self.error()); }
+			self.position += 2;
+			let low = self.hex4()?;
+			if !(0xDC00..0xE000).contains(&low) { return Err(self.error()); }
+			0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+		} else {
+			high
+		};
+		char::from_u32(code).ok_or_else(|| self.error())
+	}
+
+	fn hex4(&mut self) -> Result<u32, SceneError> {
+		let digits = self.text.get(self.position..self.position + 4).ok_or_else(|| self.error())?;
+		let digits = core::str::from_utf8(digits).map_err(|_| self.error())?;
+		let code = u32::from_str_radix(digits, 16).map_err(|_| self.error())?;
+		self.position += 4;
+		Ok(code)
+	}
+
+	fn number(&mut self) -> Result<SceneValue, SceneError> {
+		let start = self.position;
+		if self.text[self.position] == b'-' {
+			self.position += 1;
+		}
+		let mut float = false;
+		while let Some(byte) = self.text.get(self.position) {
+			match byte {
+				b'0'..=b'9' => {},
+				b'.' | b'e' | b'E' | b'+' => float = true,
+				b'-' if float => {},
+				_ => break,
+			}
+			self.position += 1;
+		}
+		let text = core::str::from_utf8(&self.text[start..self.position]).map_err(|_| self.error())?;
+		let value = if float { text.parse().map(SceneValue::Float).ok() } else { text.parse().map(SceneValue::Int).ok() };
+		value.ok_or_else(|| self.error())
+	}
+}
+
+/// Parse a JSON or RON scene without instantiating it
+pub fn parse_scene(text: &str) -> Result<SceneValue, SceneError> {
+	let mut parser = Parser { text: text.as_bytes(), position: 0, depth: 0 };
+	let value = parser.value()?;
+	if parser.peek().is_some() { return Err(parser.error()); }
+	Ok(value)
+}
+
+impl ComponentRegistry {
+	/// Register `C` under `name` for `ECS::load_scene`. Keeps an existing registration, so
+	/// `register_cloneable` may come before or after this.
+	pub fn register_scene<C: FromScene>(&mut self, name: &str) {
+		if !self.components.contains_key(&crate::component::ComponentID::of::<C>()) {
+			self.register::<C>();
+		}
+		self.scene.insert(name.to_string(), (crate::component::ComponentID::of::<C>(), insert::<C>));
+	}
+
+	/// Also load `C` from components keyed `previous`, so scenes saved before a rename still load.
+	/// Returns false if `C` has no `register_scene` name yet or `previous` already loads another Component.
+	pub fn register_scene_alias<C: FromScene>(&mut self, previous: &str) -> bool {
+		let id = crate::component::ComponentID::of::<C>();
+		let Some(load) = self.scene.values().find(|(component, _)| *component == id).copied() else { return false };
+		match self.scene.get(previous) {
+			Some((component, _)) => *component == id,
+			None => {
+				self.scene.insert(previous.to_string(), load);
+				true
+			},
+		}
+	}
+
+	/// Read values of `C` from scenes declaring `version` through `upgrade` instead of `FromScene`, for
+	/// layouts that have since changed. Scenes without a version, or with one no migration is registered
+	/// for, load through `FromScene`. Replaces any earlier migration for the same version.
+	pub fn register_scene_migration<C: FromScene, F>(&mut self, version: u32, upgrade: F)
+	where F: Fn(&SceneValue) -> Option<C> + Send + Sync + 'static {
+		let migrate = move |ecs: &mut ECS, entity: &EntityID, value: &SceneValue| match upgrade(value) {
+			Some(component) => {
+				ecs.add_component(entity, component);
+				true
+			},
+			None => false,
+		};
+		self.migrations.insert((crate::component::ComponentID::of::<C>(), version), Arc::new(migrate));
+	}
+}
+
+impl ECS {
+	/// Spawn every entity described in `text`. The scene is either a list of entities or a map
+	/// with an `entities` list, each entity holding an optional `guid` and a `components` map keyed
+	/// by the names given to `register_scene` or `register_scene_alias`. The map form may also carry a
+	/// `version` selecting migrations, see `register_scene_migration`, and a `schema`, the hex `schema_hash` of
+	/// the registry it was written with, which must match `registry`. Nothing is spawned if any part fails.
+	pub fn load_scene(&mut self, registry: &ComponentRegistry, text: &str) -> Result<Vec<EntityID>, SceneError> {
+		let scene = parse_scene(text)?;
+		let entities = match scene.get("entities").unwrap_or(&scene) {
+			SceneValue::List(entities) => entities,
+			_ => return Err(SceneError::Layout),
+		};
+		let version = match scene.get("version") {
+			Some(SceneValue::Int(version)) => u32::try_from(*version).ok(),
+			Some(_) => return Err(SceneError::Layout),
+			None => None,
+		};
+		match scene.get("schema") {
+			Some(SceneValue::Str(schema)) if u64::from_str_radix(schema, 16) != Ok(registry.schema_hash()) => return Err(SceneError::SchemaMismatch),
+			Some(SceneValue::Str(_)) | None => {},
+			Some(_) => return Err(SceneError::Layout),
+		}
+
+		let mut spawned = Vec::new();
+		for description in entities {
+			match self.load_entity(registry, version, description) {
+				Ok(entity) => spawned.push(entity),
+				Err((entity, error)) => {
+					spawned.extend(entity);
+					for entity in spawned {
+						self.destroy_entity(entity);
+					}
+					return Err(error);
+				},
+			}
+		}
+		Ok(spawned)
+	}
+
+	/// On failure also hands back the entity if it was already created
+	fn load_entity(&mut self, registry: &ComponentRegistry, version: Option<u32>, description: &SceneValue) -> Result<EntityID, (Option<EntityID>, SceneError)> {
+		if !matches!(description, SceneValue::Map(_)) { return Err((None, SceneError::Layout)); }
+		let entity = self.create_entity().ok_or((None, SceneError::Full))?;
+		let fail = |error| Err((Some(entity), error));
+
+		match description.get("guid") {
+			Some(SceneValue::Int(guid)) if !self.set_guid(&entity, Guid(*guid as u64)) => return fail(SceneError::DuplicateGuid(*guid as u64)),
+			Some(SceneValue::Int(_)) | None => {},
+			Some(_) => return fail(SceneError::Layout),
+		}
+
+		let components = match description.get("components") {
+			Some(SceneValue::Map(components)) => components.as_slice(),
+			Some(_) => return fail(SceneError::Layout),
+			None => &[],
+		};
+		for (name, value) in components {
+			let Some((id, insert)) = registry.scene.get(name) else { return fail(SceneError::UnknownComponent(name.clone())) };
+			let inserted = match version.and_then(|version| registry.migrations.get(&(*id, version))) {
+				Some(migrate) => migrate(self, &entity, value),
+				None => insert(self, &entity, value),
+			};
+			if !inserted { return fail(SceneError::InvalidValue(name.clone())); }
+		}
+		Ok(entity)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{parse_scene, FromScene, SceneError, SceneValue, MAX_DEPTH};
+	use crate::{Component, ComponentRegistry, Guid, ECS};
+	use alloc::string::{String, ToString};
+
+	#[derive(Debug, PartialEq)]
+	struct Position { x: f64, y: f64 }
+	impl Component for Position {}
+	impl FromScene for Position {
+		fn from_scene(value: &SceneValue) -> Option<Self> {
+			Some(Position { x: value.get("x")?.as_f64()?, y: value.get("y")?.as_f64()? })
+		}
+	}
+
+	#[derive(Debug, PartialEq)]
+	struct Name(String);
+	impl Component for Name {}
+	impl FromScene for Name {
+		fn from_scene(value: &SceneValue) -> Option<Self> {
+			Some(Name(value.as_str()?.to_string()))
+		}
+	}
+
+	fn registry() -> ComponentRegistry {
+		let mut registry = ComponentRegistry::new();
+		registry.register_scene::<Position>("Position");
+		registry.register_scene::<Name>("Name");
+		registry
+	}
+
+	#[test]
+	fn ron() {
+		let registry = registry();
+		let mut ecs = ECS::from_registry(&registry, 4);
+		let scene = r#"
+			// Two entities, the first with a fixed guid
+			Scene(
+				entities: [
+					(guid: 12, components: { "Position": Position(x: 1, y: -2.5), "Name": "player" }),
+					(components: { "Name": "crate" }),
+				],
+			)
+		"#;
+		let entities = ecs.load_scene(&registry, scene).unwrap();
+		assert_eq!(entities.len(), 2);
+		assert!(ecs.find_by_guid(Guid(12)) == Some(entities[0]));
+		assert_eq!(ecs.get_component::<Position>(&entities[0]), Some(&Position { x: 1.0, y: -2.5 }));
+		assert_eq!(ecs.get_component::<Name>(&entities[1]), Some(&Name("crate".to_string())));
+	}
+
+	#[test]
+	fn alias() {
+		let mut registry = registry();
+		assert!(registry.register_scene_alias::<Position>("game::Location"));
+		assert!(!registry.register_scene_alias::<Position>("Name"));
+		let mut ecs = ECS::from_registry(&registry, 4);
+		let entities = ecs.load_scene(&registry, r#"[{"components": {"game::Location": {"x": 1, "y": 2}}}]"#).unwrap();
+		assert_eq!(ecs.get_component::<Position>(&entities[0]), Some(&Position { x: 1.0, y: 2.0 }));
+
+		registry.unregister::<Position>();
+		assert!(ecs.load_scene(&registry, r#"[{"components": {"game::Location": {"x": 1, "y": 2}}}]"#).is_err());
+	}
+
+	#[test]
+	fn migration() {
+		let mut registry = registry();
+		// Version 1 stored positions as a pair
+		registry.register_scene_migration::<Position, _>(1, |value| {
+			let pair = value.as_list()?;
+			Some(Position { x: pair.first()?.as_f64()?, y: pair.get(1)?.as_f64()? })
+		});
+		let mut ecs = ECS::from_registry(&registry, 4);
+		let old = r#"{"version": 1, "entities": [{"components": {"Position": [5, 6], "Name": "old"}}]}"#;
+		let entities = ecs.load_scene(&registry, old).unwrap();
+		assert_eq!(ecs.get_component::<Position>(&entities[0]), Some(&Position { x: 5.0, y: 6.0 }));
+
+		let current = r#"{"version": 2, "entities": [{"components": {"Position": {"x": 1, "y": 2}}}]}"#;
+		let entities = ecs.load_scene(&registry, current).unwrap();
+		assert_eq!(ecs.get_component::<Position>(&entities[0]), Some(&Position { x: 1.0, y: 2.0 }));
+		assert_eq!(ecs.load_scene(&registry, r#"{"version": "1", "entities": []}"#).err(), Some(SceneError::Layout));
+	}
+
+	#[test]
+	fn schema() {
+		let registry = registry();
+		let mut ecs = ECS::from_registry(&registry, 4);
+		let scene = alloc::format!(r#"{{"schema": "{:x}", "entities": [{{"components": {{"Name": "a"}}}}]}}"#, registry.schema_hash());
+		assert_eq!(ecs.load_scene(&registry, &scene).unwrap().len(), 1);
+		let stale = alloc::format!(r#"{{"schema": "{:x}", "entities": []}}"#, registry.schema_hash() ^ 1);
+		assert_eq!(ecs.load_scene(&registry, &stale).err(), Some(SceneError::SchemaMismatch));
+	}
+
+	#[test]
+	fn json() {
+		let registry = registry();
+		let mut ecs = ECS::from_registry(&registry, 4);
+		let scene = r#"[{"components": {"Position": {"x": 3, "y": 4}, "Name": "a\"b"}}]"#;
+		let entities = ecs.load_scene(&registry, scene).unwrap();
+		assert_eq!(ecs.get_component::<Position>(&entities[0]), Some(&Position { x: 3.0, y: 4.0 }));
+		assert_eq!(ecs.get_component::<Name>(&entities[0]), Some(&Name("a\"b".to_string())));
+	}
+
+	#[test]
+	fn errors() {
+		let registry = registry();
+		let mut ecs = ECS::from_registry(&registry, 4);
+		assert_eq!(parse_scene("[1,\n2,\n}"), Err(SceneError::Syntax(3)));
+		assert_eq!(parse_scene("(1, -2e1, [], ())"), Ok(SceneValue::List(alloc::vec![SceneValue::Int(1), SceneValue::Float(-20.0), SceneValue::List(alloc::vec![]), SceneValue::Unit])));
+
+		let unknown = r#"[(components: {"Name": "kept"}), (components: {"Velocity": 1})]"#;
+		assert_eq!(ecs.load_scene(&registry, unknown).err(), Some(SceneError::UnknownComponent("Velocity".to_string())));
+		let invalid = r#"[(components: {"Position": (x: 1)})]"#;
+		assert_eq!(ecs.load_scene(&registry, invalid).err(), Some(SceneError::InvalidValue("Position".to_string())));
+		assert_eq!(ecs.get_entity_count(), 0);
+	}
+
+	#[test]
+	fn unicode_escapes() {
+		let parsed = parse_scene(r#""caf\u00e9 \ud83d\ude00\r""#);
+		assert_eq!(parsed, Ok(SceneValue::Str("caf\u{e9} \u{1f600}\r".to_string())));
+		assert_eq!(parse_scene(r#""\ud83d""#), Err(SceneError::Syntax(1)));
+		assert_eq!(parse_scene(r#""\u00g0""#), Err(SceneError::Syntax(1)));
+	}
+
+	#[test]
+	fn depth() {
+		let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+		assert!(parse_scene(&nested(MAX_DEPTH)).is_ok());
+		assert_eq!(parse_scene(&nested(100_000)), Err(SceneError::Syntax(1)));
+	}
+}
@@ -0,0 +1,436 @@
+use crate::state::Transitions;
+use crate::{Access, SubWorld, ECS};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::TypeId;
+use core::ops::{Deref, DerefMut};
+
+type ConditionFn = Box<dyn Fn(&ECS) -> bool>;
+type EventUpdateFn = fn(&mut ECS);
+
+enum SystemFn {
+	/// Limited to its declared `Access`
+	World(Box<dyn FnMut(&mut SubWorld)>),
+	/// Free to make any change, including structural ones, and never overlaps another system
+	Exclusive(Box<dyn FnMut(&mut ECS)>),
+}
+
+pub(crate) struct System {
+	access: Access,
+	run: SystemFn,
+	conditions: Vec<ConditionFn>,
+	/// Sets this system belongs to, for other systems' `before` and `after`
+	labels: Vec<&'static str>,
+	before: Vec<&'static str>,
+	after: Vec<&'static str>,
+}
+
+impl System {
+	pub(crate) fn exclusive(run: Box<dyn FnMut(&mut ECS)>) -> Self {
+		System::new(Access::new(), SystemFn::Exclusive(run))
+	}
+
+	fn new(access: Access, run: SystemFn) -> Self {
+		System { access, run, conditions: Vec::new(), labels: Vec::new(), before: Vec::new(), after: Vec::new() }
+	}
+
+	fn has_label(&self, label: &str) -> bool {
+		self.labels.contains(&label)
+	}
+
+	/// True if this system is constrained to run before `other`
+	fn precedes(&self, other: &System) -> bool {
+		self.before.iter().any(|label| other.has_label(label)) || other.after.iter().any(|label| self.has_label(label))
+	}
+
+	fn run(&mut self, ecs: &mut ECS) {
+		if !self.conditions.iter().all(|condition| condition(ecs)) { return; }
+		match &mut self.run {
+			SystemFn::World(run) => run(&mut SubWorld::new(ecs, self.access.clone())),
+			SystemFn::Exclusive(run) => run(ecs),
+		}
+	}
+
+}
+
+/// Returned by `Schedule::add_system` to configure the system just added
+pub struct SystemConfig<'a> {
+	system: &'a mut System,
+}
+
+impl<'a> SystemConfig<'a> {
+	/// Only run while `condition` holds. Several conditions must all hold.
+	pub fn run_if<F: Fn(&ECS) -> bool + 'static>(self, condition: F) -> Self {
+		self.system.conditions.push(Box::new(condition));
+		self
+	}
+
+	/// Add this system to the set `label`. Any number of systems may share a label.
+	pub fn label(self, label: &'static str) -> Self {
+		self.system.labels.push(label);
+		self
+	}
+
+	/// Run before every system in the set `label` within the same stage
+	pub fn before(self, label: &'static str) -> Self {
+		self.system.before.push(label);
+		self
+	}
+
+	/// Run after every system in the set `label` within the same stage
+	pub fn after(self, label: &'static str) -> Self {
+		self.system.after.push(label);
+		self
+	}
+}
+
+/// State owned by a single system and kept between its runs, for timers, caches, and the like that
+/// no other system should see. Starts as `T::default()`.
+#[derive(Default, Debug)]
+pub struct Local<T>(T);
+
+impl<T> Deref for Local<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T { &self.0 }
+}
+
+impl<T> DerefMut for Local<T> {
+	fn deref_mut(&mut self) -> &mut T { &mut self.0 }
+}
+
+pub(crate) struct Stage {
+	name: &'static str,
+	systems: Vec<System>,
+	/// Checked once before any of the stage's systems
+	condition: Option<ConditionFn>,
+	/// Positions in `systems` satisfying every ordering constraint, empty after adding a system
+	order: Vec<usize>,
+}
+
+impl Stage {
+	pub(crate) fn new(name: &'static str) -> Self {
+		Stage { name, systems: Vec::new(), condition: None, order: Vec::new() }
+	}
+
+	pub(crate) fn push(&mut self, system: System) -> SystemConfig<'_> {
+		self.order.clear();
+		self.systems.push(system);
+		SystemConfig { system: self.systems.last_mut().unwrap() }
+	}
+
+	/// Topological order of the constraints, ties broken by registration order. Labels nobody carries
+	/// constrain nothing. Panics on a cycle.
+	fn sort(&mut self) {
+		let count = self.systems.len();
+		let mut blockers: Vec<usize> = (0..count)
+			.map(|system| (0..count).filter(|other| self.systems[*other].precedes(&self.systems[system])).count())
+			.collect();
+		let mut done = alloc::vec![false; count];
+		self.order.clear();
+		while self.order.len() < count {
+			let Some(next) = (0..count).find(|system| !done[*system] && blockers[*system] == 0) else {
+				panic!("Ordering cycle between systems in stage {}", self.name);
+			};
+			done[next] = true;
+			self.order.push(next);
+			for (other, blocked) in blockers.iter_mut().enumerate() {
+				if self.systems[next].precedes(&self.systems[other]) {
+					*blocked -= 1;
+				}
+			}
+		}
+	}
+
+	pub(crate) fn run(&mut self, ecs: &mut ECS) {
+		if self.condition.as_ref().is_some_and(|condition| !condition(ecs)) { return; }
+		if self.order.len() != self.systems.len() {
+			self.sort();
+		}
+		for position in &self.order {
+			self.systems[*position].run(ecs);
+		}
+		ecs.apply_commands();
+	}
+}
+
+/// Named stages of systems run in order every `run`, plus a startup stage run only by the first `run`.
+/// Systems within a stage run in the order they were added unless constrained with `before` and `after`,
+/// one at a time, so an exclusive system never overlaps any other. Commands queued during a stage are
+/// applied when it ends, so its systems all see the same entities and the next stage sees every change.
+pub struct Schedule {
+	startup: Stage,
+	started: bool,
+	stages: Vec<Stage>,
+	/// One per `States<T>` with enter or exit systems
+	pub(crate) transitions: Vec<Box<dyn Transitions>>,
+	/// Swap the buffers of each `Events<E>`, see `add_event`
+	pub(crate) event_updates: Vec<(TypeId, EventUpdateFn)>,
+}
+
+impl Default for Schedule {
+	fn default() -> Self {
+		Schedule::new()
+	}
+}
+
+impl Schedule {
+	pub const UPDATE: &'static str = "update";
+	pub const LATE_UPDATE: &'static str = "late_update";
+
+	/// Schedule with the `UPDATE` and `LATE_UPDATE` stages
+	pub fn new() -> Self {
+		let stages = [Self::UPDATE, Self::LATE_UPDATE].map(Stage::new);
+		Schedule { startup: Stage::new("startup"), started: false, stages: stages.into(), transitions: Vec::new(), event_updates: Vec::new() }
+	}
+
+	fn position(&self, name: &str) -> usize {
+		self.stages.iter().position(|stage| stage.name == name).unwrap_or_else(|| panic!("No stage named {}", name))
+	}
+
+	fn insert_stage(&mut self, position: usize, name: &'static str) {
+		assert!(self.stages.iter().all(|stage| stage.name != name), "Stage {} already exists", name);
+		self.stages.insert(position, Stage::new(name));
+	}
+
+	/// Append a stage that runs after every existing one
+	pub fn add_stage(&mut self, name: &'static str) {
+		self.insert_stage(self.stages.len(), name);
+	}
+
+	pub fn add_stage_before(&mut self, before: &str, name: &'static str) {
+		self.insert_stage(self.position(before), name);
+	}
+
+	pub fn add_stage_after(&mut self, after: &str, name: &'static str) {
+		self.insert_stage(self.position(after) + 1, name);
+	}
+
+	pub fn stage_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+		self.stages.iter().map(|stage| stage.name)
+	}
+
+	/// Skip the whole stage unless `condition` holds, replacing any previous stage condition
+	pub fn set_run_criteria<F: Fn(&ECS) -> bool + 'static>(&mut self, stage: &str, condition: F) {
+		let position = self.position(stage);
+		self.stages[position].condition = Some(Box::new(condition));
+	}
+
+	/// Panics if `stage` doesn't exist
+	pub fn add_system<F: FnMut(&mut SubWorld) + 'static>(&mut self, stage: &str, access: Access, system: F) -> SystemConfig<'_> {
+		let position = self.position(stage);
+		self.stages[position].push(System::new(access, SystemFn::World(Box::new(system))))
+	}
+
+	/// Add a system given the whole ECS, for structural changes or anything else no `Access` can describe.
+	/// Runs on the thread calling `run`, like every system.
+	pub fn add_exclusive_system<F: FnMut(&mut ECS) + 'static>(&mut self, stage: &str, system: F) -> SystemConfig<'_> {
+		let position = self.position(stage);
+		self.stages[position].push(System::exclusive(Box::new(system)))
+	}
+
+	/// `add_system` with a `Local<T>` handed to every run of `system`
+	pub fn add_system_with_local<T, F>(&mut self, stage: &str, access: Access, mut system: F) -> SystemConfig<'_>
+	where T: Default + 'static, F: FnMut(&mut SubWorld, &mut Local<T>) + 'static {
+		let mut local = Local::default();
+		self.add_system(stage, access, move |world| system(world, &mut local))
+	}
+
+	pub fn add_exclusive_system_with_local<T, F>(&mut self, stage: &str, mut system: F) -> SystemConfig<'_>
+	where T: Default + 'static, F: FnMut(&mut ECS, &mut Local<T>) + 'static {
+		let mut local = Local::default();
+		self.add_exclusive_system(stage, move |ecs| system(ecs, &mut local))
+	}
+
+	pub fn add_startup_system<F: FnMut(&mut SubWorld) + 'static>(&mut self, access: Access, system: F) -> SystemConfig<'_> {
+		self.startup.push(System::new(access, SystemFn::World(Box::new(system))))
+	}
+
+	pub fn add_startup_exclusive_system<F: FnMut(&mut ECS) + 'static>(&mut self, system: F) -> SystemConfig<'_> {
+		self.startup.push(System::exclusive(Box::new(system)))
+	}
+
+	/// Run every stage once, preceded by the startup stage on the first call, event updates, and pending
+	/// `States` transitions. Every system runs on the calling thread, which non-Send resources rely on.
+	pub fn run(&mut self, ecs: &mut ECS) {
+		ecs.advance_tick();
+		for (_, update) in &self.event_updates {
+			update(ecs);
+		}
+		if !self.started {
+			self.started = true;
+			self.startup.run(ecs);
+		}
+		for transitions in &mut self.transitions {
+			transitions.apply(ecs);
+		}
+		for stage in &mut self.stages {
+			stage.run(ecs);
+		}
+		ecs.run_set_hooks();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Access, Component, Local, Schedule, ECS};
+	use core::sync::atomic::{AtomicBool, Ordering};
+	use alloc::vec::Vec;
+
+	struct Log(Vec<&'static str>);
+	impl Component for Log {}
+
+	fn log(name: &'static str) -> impl FnMut(&mut crate::SubWorld) {
+		move |world| {
+			for (_, log) in world.query_mut::<Log>() {
+				log.0.push(name);
+			}
+		}
+	}
+
+	#[test]
+	fn stages() {
+		let mut ecs = ECS::new(1);
+		ecs.register::<Log>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Log(Vec::new()));
+
+		let mut schedule = Schedule::new();
+		schedule.add_stage_after(Schedule::UPDATE, "physics");
+		schedule.add_stage_before(Schedule::UPDATE, "input");
+		schedule.add_stage("render");
+		let access = || Access::new().write::<Log>();
+		schedule.add_system("render", access(), log("render"));
+		schedule.add_system(Schedule::LATE_UPDATE, access(), log("late"));
+		schedule.add_system("physics", access(), log("physics"));
+		schedule.add_system(Schedule::UPDATE, access(), log("gameplay"));
+		schedule.add_system("input", access(), log("input"));
+		schedule.add_startup_system(access(), log("startup"));
+
+		let names: Vec<_> = schedule.stage_names().collect();
+		assert_eq!(names, ["input", "update", "physics", "late_update", "render"]);
+		schedule.run(&mut ecs);
+		schedule.run(&mut ecs);
+		assert_eq!(ecs.get_component::<Log>(&entity).unwrap().0, [
+			"startup", "input", "gameplay", "physics", "late", "render",
+			"input", "gameplay", "physics", "late", "render",
+		]);
+	}
+
+	#[test]
+	fn run_criteria() {
+		static PAUSED: AtomicBool = AtomicBool::new(false);
+		let mut ecs = ECS::new(1);
+		ecs.register::<Log>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Log(Vec::new()));
+
+		let mut schedule = Schedule::new();
+		let access = || Access::new().write::<Log>();
+		schedule.add_system(Schedule::UPDATE, access(), log("playing")).run_if(|_| !PAUSED.load(Ordering::Relaxed));
+		schedule.add_system(Schedule::UPDATE, access(), log("populated")).run_if(|ecs| ecs.get_entity_count() > 0).run_if(|_| true);
+		schedule.add_system(Schedule::LATE_UPDATE, access(), log("late"));
+		schedule.set_run_criteria(Schedule::LATE_UPDATE, |_| !PAUSED.load(Ordering::Relaxed));
+
+		schedule.run(&mut ecs);
+		PAUSED.store(true, Ordering::Relaxed);
+		schedule.run(&mut ecs);
+		assert_eq!(ecs.get_component::<Log>(&entity).unwrap().0, ["playing", "populated", "late", "populated"]);
+	}
+
+	#[test]
+	fn ordering() {
+		let mut ecs = ECS::new(1);
+		ecs.register::<Log>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Log(Vec::new()));
+
+		let mut schedule = Schedule::new();
+		let access = || Access::new().write::<Log>();
+		schedule.add_system(Schedule::UPDATE, access(), log("render")).after("physics");
+		schedule.add_system(Schedule::UPDATE, access(), log("integrate")).label("physics");
+		schedule.add_system(Schedule::UPDATE, access(), log("input")).before("gameplay").before("missing");
+		schedule.add_system(Schedule::UPDATE, access(), log("collide")).label("physics").after("integrate");
+		schedule.add_system(Schedule::UPDATE, access(), log("ai")).label("gameplay").label("integrate").before("physics");
+		schedule.run(&mut ecs);
+		assert_eq!(ecs.get_component::<Log>(&entity).unwrap().0, ["input", "ai", "integrate", "collide", "render"]);
+	}
+
+	#[test]
+	fn exclusive() {
+		let mut ecs = ECS::new(1);
+		ecs.set_grow_fn(Some(|capacity| capacity * 2));
+		ecs.register::<Log>();
+
+		let mut schedule = Schedule::new();
+		schedule.add_startup_exclusive_system(|ecs| {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Log(Vec::new()));
+		});
+		schedule.add_system(Schedule::UPDATE, Access::new().write::<Log>(), log("update"));
+		schedule.add_exclusive_system(Schedule::UPDATE, |ecs| {
+			ecs.create_entity().unwrap();
+		}).before("missing");
+		schedule.run(&mut ecs);
+		schedule.run(&mut ecs);
+		assert_eq!(ecs.get_entity_count(), 3);
+		assert_eq!(ecs.query::<Log>().next().unwrap().1.0, ["update", "update"]);
+	}
+
+	#[test]
+	fn locals() {
+		let mut ecs = ECS::new(1);
+		ecs.register::<Log>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Log(Vec::new()));
+
+		let mut schedule = Schedule::new();
+		let every_other = |world: &mut crate::SubWorld, tick: &mut Local<usize>| {
+			**tick += 1;
+			if (**tick).is_multiple_of(2) {
+				log("even")(world);
+			}
+		};
+		schedule.add_system_with_local(Schedule::UPDATE, Access::new().write::<Log>(), every_other);
+		schedule.add_system_with_local(Schedule::UPDATE, Access::new().write::<Log>(), every_other);
+		schedule.add_exclusive_system_with_local(Schedule::LATE_UPDATE, |ecs, runs: &mut Local<usize>| {
+			**runs += 1;
+			ecs.insert_resource(**runs);
+		});
+		for _ in 0..4 {
+			schedule.run(&mut ecs);
+		}
+		assert_eq!(ecs.get_component::<Log>(&entity).unwrap().0.len(), 4);
+		assert_eq!(ecs.get_resource::<usize>(), Some(&4));
+	}
+
+	#[test]
+	fn sync_points() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Log>();
+
+		let mut schedule = Schedule::new();
+		let count = |expected: usize| move |world: &mut crate::SubWorld| assert_eq!(world.query::<Log>().count(), expected);
+		schedule.add_startup_system(Access::new(), |world| {
+			world.commands().spawn(|ecs, entity| ecs.add_component(entity, Log(Vec::new())));
+		});
+		schedule.add_system(Schedule::UPDATE, Access::new().read::<Log>(), count(1));
+		schedule.add_system(Schedule::UPDATE, Access::new(), |world| {
+			world.commands().spawn(|ecs, entity| ecs.add_component(entity, Log(Vec::new())));
+		});
+		schedule.add_system(Schedule::UPDATE, Access::new().read::<Log>(), count(1));
+		schedule.add_system(Schedule::LATE_UPDATE, Access::new().read::<Log>(), count(2));
+		schedule.run(&mut ecs);
+		assert_eq!(ecs.get_entity_count(), 2);
+	}
+
+	#[test]
+	#[should_panic]
+	fn cycle() {
+		let mut ecs = ECS::new(1);
+		let mut schedule = Schedule::new();
+		schedule.add_system(Schedule::UPDATE, Access::new(), |_| {}).label("a").after("b");
+		schedule.add_system(Schedule::UPDATE, Access::new(), |_| {}).label("b").after("a");
+		schedule.run(&mut ecs);
+	}
+}
@@ -0,0 +1,84 @@
+use crate::component::ComponentID;
+use crate::ComponentRegistry;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// One registered Component as seen by tools outside the program, see `ComponentRegistry::export_schema`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentSchema {
+	/// `core::any::type_name`, not stable across compiler versions
+	pub name: &'static str,
+	pub size: usize,
+	pub align: usize,
+	/// Empty unless registered with `register_reflect`
+	pub fields: &'static [&'static str],
+	/// Names `ECS::load_scene` accepts for it, including aliases, sorted
+	pub scene_names: Vec<String>,
+	/// Scene versions with a migration registered, ascending
+	pub versions: Vec<u32>,
+	pub cloneable: bool,
+}
+
+impl ComponentRegistry {
+	/// Describe every registered Component, sorted by name so the output is stable between runs
+	pub fn export_schema(&self) -> Vec<ComponentSchema> {
+		let mut schema: Vec<ComponentSchema> = self.components.iter().map(|(id, info)| {
+			let (scene_names, versions) = self.scene_schema(*id);
+			ComponentSchema {
+				name: info.name(),
+				size: info.layout.size(),
+				align: info.layout.align(),
+				fields: info.field_names(),
+				scene_names,
+				versions,
+				cloneable: info.is_cloneable(),
+			}
+		}).collect();
+		schema.sort_unstable_by_key(|component| component.name);
+		schema
+	}
+
+	fn scene_schema(&self, id: ComponentID) -> (Vec<String>, Vec<u32>) {
+		let mut names: Vec<String> = self.scene.iter().filter(|(_, (component, _))| *component == id).map(|(name, _)| name.clone()).collect();
+		names.sort_unstable();
+		let mut versions: Vec<u32> = self.migrations.keys().filter(|(component, _)| *component == id).map(|(_, version)| *version).collect();
+		versions.sort_unstable();
+		(names, versions)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ComponentRegistry, Reflect};
+
+	#[derive(Clone)]
+	struct Health { current: u32, max: u32 }
+	impl Component for Health {}
+	impl Reflect for Health {
+		fn field(&self, name: &str) -> Option<&dyn Reflect> {
+			match name { "current" => Some(&self.current), "max" => Some(&self.max), _ => None }
+		}
+
+		fn field_names() -> &'static [&'static str] { &["current", "max"] }
+	}
+
+	struct Marker;
+	impl Component for Marker {}
+
+	#[test]
+	fn export() {
+		let mut registry = ComponentRegistry::new();
+		registry.register::<Marker>();
+		registry.register_cloneable::<Health>();
+		registry.register_reflect::<Health>();
+
+		let schema = registry.export_schema();
+		assert_eq!(schema.len(), 2);
+		let health = &schema[0];
+		assert!(health.name.ends_with("Health"));
+		assert_eq!((health.size, health.align), (8, 4));
+		assert_eq!(health.fields, ["current", "max"]);
+		assert!(health.cloneable);
+		assert!(schema[1].name.ends_with("Marker") && schema[1].fields.is_empty());
+	}
+}
@@ -0,0 +1,246 @@
+use crate::component::ComponentID;
+use crate::ecs::Entity;
+use crate::guid::Guids;
+use crate::storage::ComponentArray;
+use crate::ECS;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+/// Handle to a saved world state, see `ECS::save_checkpoint`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Checkpoint(u64);
+
+struct Snapshot {
+	checkpoint: Checkpoint,
+	entity_count: usize,
+	entities: Vec<Entity>,
+	tags: Vec<u64>,
+	free: Vec<usize>,
+	guids: Guids,
+	/// `ECS::schema_hash` when saved
+	schema: u64,
+	/// Cloneable columns, shared with the world until either writes to them
+	columns: Vec<(ComponentID, Rc<ComponentArray>)>,
+}
+
+/// Ring of the most recent snapshots
+pub(crate) struct Checkpoints {
+	ring: VecDeque<Snapshot>,
+	limit: usize,
+	next: u64,
+}
+
+impl Checkpoints {
+	pub(crate) fn new() -> Self {
+		Checkpoints { ring: VecDeque::new(), limit: 8, next: 0 }
+	}
+
+	/// Stop restoring the column of `id`, as if it had never been cloneable
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		for snapshot in &mut self.ring {
+			snapshot.columns.retain(|(column, _)| *column != id);
+		}
+	}
+}
+
+impl ECS {
+	pub fn get_checkpoint_limit(&self) -> usize { self.checkpoints.limit }
+
+	/// How many checkpoints are kept before the oldest is dropped
+	pub fn set_checkpoint_limit(&mut self, limit: usize) {
+		self.checkpoints.limit = limit;
+		while self.checkpoints.ring.len() > limit {
+			self.checkpoints.ring.pop_front();
+		}
+	}
+
+	/// Snapshot all entities and every cloneable Component. Columns are shared with the checkpoint rather than
+	/// cloned, so saving is cheap and the first write to a column afterwards copies it, as with `fork`.
+	/// Components registered with plain `register` are not captured, see `rollback_to`.
+	pub fn save_checkpoint(&mut self) -> Checkpoint {
+		self.flush();
+		let checkpoint = Checkpoint(self.checkpoints.next);
+		self.checkpoints.next += 1;
+
+		let snapshot = Snapshot {
+			checkpoint,
+			entity_count: self.entity_count,
+			entities: self.entities.clone(),
+			tags: self.tags.clone(),
+			free: self.free.clone(),
+			guids: self.guids.clone(),
+			schema: self.schema_hash(),
+			columns: self.components.snapshot(),
+		};
+		self.checkpoints.ring.push_back(snapshot);
+		if self.checkpoints.ring.len() > self.checkpoints.limit {
+			self.checkpoints.ring.pop_front();
+		}
+		checkpoint
+	}
+
+	pub fn has_checkpoint(&self, checkpoint: Checkpoint) -> bool {
+		self.checkpoints.ring.iter().any(|snapshot| snapshot.checkpoint == checkpoint)
+	}
+
+	/// Restore the world to `checkpoint`, discarding any checkpoints saved after it. Returns false if it has
+	/// already left the ring, or without touching anything if Components were registered or unregistered
+	/// since it was saved. Capacity never shrinks. Non-cloneable Components are kept only on entities
+	/// that are the same in both states.
+	pub fn rollback_to(&mut self, checkpoint: Checkpoint) -> bool {
+		self.flush();
+		let Some(position) = self.checkpoints.ring.iter().position(|snapshot| snapshot.checkpoint == checkpoint) else {
+			return false;
+		};
+		if self.checkpoints.ring[position].schema != self.schema_hash() { return false; }
+		self.checkpoints.ring.truncate(position + 1);
+
+		// Cloneable values are all replaced, the rest only go where the entity changed
+		let saved = &self.checkpoints.ring[position].entities;
+		let dropped: Vec<(ComponentID, usize)> = self.drop_hooks.ids().into_iter().flat_map(|id| {
+			let cloneable = self.components.get_array_by_id(id).is_some_and(|array| array.info().clone.is_some());
+			let entities = &self.entities;
+			(0..self.capacity).filter(move |index| cloneable || saved.get(*index) != Some(&entities[*index])).map(move |index| (id, index))
+		}).collect();
+		for (id, index) in dropped {
+			self.run_drop_hook(id, index);
+		}
+		let snapshot = &self.checkpoints.ring[position];
+
+		let current = &self.entities;
+		let keep = |index: usize| current[index].alive && snapshot.entities.get(index) == Some(&current[index]);
+		self.components.restore(&snapshot.columns, self.capacity, keep);
+
+		let saved_capacity = snapshot.entities.len();
+		self.entities[..saved_capacity].copy_from_slice(&snapshot.entities);
+		self.tags[..saved_capacity].copy_from_slice(&snapshot.tags);
+		self.tags[saved_capacity..].fill(0);
+		for entity in &mut self.entities[saved_capacity..] {
+			entity.alive = false;
+		}
+		self.free.clear();
+		self.free.extend((saved_capacity..self.capacity).rev());
+		self.free.extend_from_slice(&snapshot.free);
+		*self.free_cursor.get_mut() = self.free.len() as isize;
+		self.entity_count = snapshot.entity_count;
+		self.guids = snapshot.guids.clone();
+		self.guids.resize(self.capacity);
+
+		let entities = &self.entities;
+		self.groups.rebuild(&mut self.components, self.capacity, |index| entities[index].alive);
+		self.change_tick += 1;
+		self.components.set_restored_tick(self.change_tick);
+		self.rebuild_indexes();
+		self.prune_watches();
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+
+	#[derive(Clone, Debug, PartialEq, Eq)]
+	struct Health(alloc::boxed::Box<usize>);
+	impl Component for Health {}
+
+	struct Handle(usize);
+	impl Component for Handle {}
+
+	#[test]
+	fn rollback() {
+		let mut ecs = ECS::new(4);
+		ecs.set_grow_fn(Some(|capacity| capacity * 2));
+		ecs.register_cloneable::<Health>();
+		ecs.register::<Handle>();
+		ecs.create_group::<Health>();
+
+		let survivor = ecs.create_entity().unwrap();
+		ecs.add_component(&survivor, Health(alloc::boxed::Box::new(10)));
+		ecs.add_component(&survivor, Handle(1));
+		let victim = ecs.create_entity().unwrap();
+		ecs.add_component(&victim, Health(alloc::boxed::Box::new(20)));
+
+		let checkpoint = ecs.save_checkpoint();
+
+		*ecs.get_component_mut::<Health>(&survivor).unwrap().0 = 5;
+		ecs.destroy_entity(victim);
+		for _ in 0..8 {
+			let spawned = ecs.create_entity().unwrap();
+			ecs.add_component(&spawned, Health(alloc::boxed::Box::new(0)));
+			ecs.add_component(&spawned, Handle(2));
+		}
+		let later = ecs.save_checkpoint();
+		assert!(ecs.get_capacity() > 4);
+
+		assert!(ecs.rollback_to(checkpoint));
+		assert!(!ecs.has_checkpoint(later));
+		assert_eq!(ecs.get_entity_count(), 2);
+		assert!(ecs.is_valid(&survivor) && ecs.is_valid(&victim));
+		assert_eq!(*ecs.get_component::<Health>(&survivor).unwrap().0, 10);
+		assert_eq!(*ecs.get_component::<Health>(&victim).unwrap().0, 20);
+		assert_eq!(ecs.get_component::<Handle>(&survivor).unwrap().0, 1);
+		assert_eq!(ecs.query::<Handle>().count(), 1);
+		assert_eq!(ecs.query_group::<Health>().count(), 2);
+
+		// Rolling back again restores the same state
+		ecs.destroy_entity(survivor);
+		assert!(ecs.rollback_to(checkpoint));
+		assert_eq!(*ecs.get_component::<Health>(&survivor).unwrap().0, 10);
+		assert!(ecs.get_component::<Handle>(&survivor).is_none());
+	}
+
+	#[test]
+	fn shared() {
+		use crate::component::ComponentID;
+
+		let mut ecs = ECS::new(4);
+		ecs.register_cloneable::<Health>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Health(alloc::boxed::Box::new(1)));
+		let checkpoint = ecs.save_checkpoint();
+		assert!(ecs.components.is_shared(ComponentID::of::<Health>()));
+
+		*ecs.get_component_mut::<Health>(&entity).unwrap().0 = 2;
+		assert!(!ecs.components.is_shared(ComponentID::of::<Health>()));
+		let mut cursor = ecs.change_cursor();
+		assert!(ecs.rollback_to(checkpoint));
+		assert!(ecs.components.is_shared(ComponentID::of::<Health>()));
+		assert_eq!(*ecs.get_component::<Health>(&entity).unwrap().0, 1);
+		assert_eq!(ecs.query_changed::<Health>(&mut cursor).count(), 1);
+		assert_eq!(ecs.query_changed::<Health>(&mut cursor).count(), 0);
+	}
+
+	#[test]
+	fn ring() {
+		let mut ecs = ECS::new(4);
+		ecs.set_checkpoint_limit(2);
+		let first = ecs.save_checkpoint();
+		let second = ecs.save_checkpoint();
+		let third = ecs.save_checkpoint();
+		assert!(!ecs.has_checkpoint(first));
+		assert!(ecs.has_checkpoint(second) && ecs.has_checkpoint(third));
+		assert!(!ecs.rollback_to(first));
+	}
+
+	#[test]
+	fn schema_changed() {
+		#[derive(Clone)]
+		struct Added;
+		impl Component for Added {}
+
+		let mut ecs = ECS::new(4);
+		ecs.register_cloneable::<Health>();
+		let checkpoint = ecs.save_checkpoint();
+		let hash = ecs.schema_hash();
+		ecs.register_cloneable::<Added>();
+		assert_ne!(ecs.schema_hash(), hash);
+		assert!(!ecs.rollback_to(checkpoint));
+		assert!(ecs.has_checkpoint(checkpoint));
+
+		ecs.unregister::<Added>();
+		assert_eq!(ecs.schema_hash(), hash);
+		assert!(ecs.rollback_to(checkpoint));
+	}
+}
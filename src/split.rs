@@ -0,0 +1,116 @@
+use crate::{EntityID, ECS};
+
+/// A struct whose fields are each stored as their own Component, so queries only touch the fields they name.
+/// Implemented by `split_component!`.
+pub trait SplitComponent: Sized {
+	/// Register the Component of every field
+	fn register(ecs: &mut ECS);
+
+	/// Add every field to `entity`
+	fn insert(self, ecs: &mut ECS, entity: &EntityID);
+
+	/// Remove and reassemble the fields of `entity`, `None` without touching anything if one is missing
+	fn take(ecs: &mut ECS, entity: &EntityID) -> Option<Self>;
+}
+
+/// Declare a struct whose fields live in separate columns, naming the Component each field is stored as.
+/// The struct's attributes are repeated on every field Component.
+/// ```ignore
+/// split_component! {
+///     #[derive(Clone, Debug)]
+///     pub struct Transform {
+///         pub position: Position([f32; 3]),
+///         pub rotation: Rotation([f32; 4]),
+///     }
+/// }
+/// ecs.register_split::<Transform>();
+/// for (_, position) in ecs.query::<Position>() {}
+/// ```
+#[macro_export]
+macro_rules! split_component {
+	(
+		$(#[$meta:meta])*
+		$vis:vis struct $name:ident {
+			$($field_vis:vis $field:ident: $column:ident($type:ty)),* $(,)?
+		}
+	) => {
+		$(#[$meta])*
+		$vis struct $name {
+			$($field_vis $field: $type,)*
+		}
+
+		$crate::split_component!(@columns [$(#[$meta])*] $vis, $($column($type)),*);
+
+		impl $crate::SplitComponent for $name {
+			fn register(ecs: &mut $crate::ECS) {
+				$(ecs.register::<$column>();)*
+			}
+
+			fn insert(self, ecs: &mut $crate::ECS, entity: &$crate::EntityID) {
+				$(ecs.add_component(entity, $column(self.$field));)*
+			}
+
+			fn take(ecs: &mut $crate::ECS, entity: &$crate::EntityID) -> Option<Self> {
+				if !(true $(&& ecs.has_component::<$column>(entity))*) { return None; }
+				Some($name { $($field: ecs.take_component::<$column>(entity)?.0,)* })
+			}
+		}
+	};
+	// The attributes travel as one token tree so they can repeat once per column
+	(@columns $meta:tt $vis:vis, $($column:ident($type:ty)),*) => {
+		$($crate::split_component!(@column $meta $vis $column $type);)*
+	};
+	(@column [$(#[$meta:meta])*] $vis:vis $column:ident $type:ty) => {
+		$(#[$meta])*
+		$vis struct $column(pub $type);
+		impl $crate::Component for $column {}
+	};
+}
+
+impl ECS {
+	pub fn register_split<T: SplitComponent>(&mut self) {
+		T::register(self);
+	}
+
+	/// Add each field of `value` as its own Component
+	pub fn add_split<T: SplitComponent>(&mut self, entity: &EntityID, value: T) {
+		if !self.is_valid(entity) { return; }
+		value.insert(self, entity);
+	}
+
+	pub fn take_split<T: SplitComponent>(&mut self, entity: &EntityID) -> Option<T> {
+		T::take(self, entity)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::ECS;
+
+	split_component! {
+		#[derive(Clone, Debug, PartialEq)]
+		struct Transform {
+			position: Position([f32; 3]),
+			scale: Scale(f32),
+		}
+	}
+
+	#[test]
+	fn split() {
+		let mut ecs = ECS::new(8);
+		ecs.register_split::<Transform>();
+		let entity = ecs.create_entity().unwrap();
+		let transform = Transform { position: [1.0, 2.0, 3.0], scale: 2.0 };
+		ecs.add_split(&entity, transform.clone());
+
+		for (_, position) in ecs.query_mut::<Position>() {
+			position.0[0] += 1.0;
+		}
+		assert_eq!(ecs.get_component::<Scale>(&entity), Some(&Scale(2.0)));
+
+		let taken = ecs.take_split::<Transform>(&entity).unwrap();
+		assert_eq!(taken, Transform { position: [2.0, 2.0, 3.0], ..transform });
+		assert!(!ecs.has_component::<Position>(&entity));
+		assert!(ecs.take_split::<Transform>(&entity).is_none());
+	}
+}
@@ -0,0 +1,162 @@
+use crate::schedule::{Stage, System, SystemConfig};
+use crate::{Access, Schedule, ECS};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// Resource holding the current value of a state machine. Changes requested with `set`
+/// take effect at the start of the next `Schedule::run`.
+pub struct States<T> {
+	current: T,
+	next: Option<T>,
+}
+
+impl<T: Eq> States<T> {
+	pub fn new(initial: T) -> Self {
+		States { current: initial, next: None }
+	}
+
+	pub fn current(&self) -> &T { &self.current }
+
+	/// Queue a transition, replacing any not yet applied
+	pub fn set(&mut self, next: T) {
+		self.next = Some(next);
+	}
+}
+
+/// Run condition for `SystemConfig::run_if` holding while `States<T>` is `value`
+pub fn in_state<T: Eq + Send + Sync + 'static>(value: T) -> impl Fn(&ECS) -> bool + 'static {
+	move |ecs| ecs.get_resource::<States<T>>().is_some_and(|states| states.current == value)
+}
+
+/// Type erased enter and exit systems of one `States<T>`
+pub(crate) trait Transitions {
+	fn as_any_mut(&mut self) -> &mut dyn Any;
+
+	fn apply(&mut self, ecs: &mut ECS);
+}
+
+pub(crate) struct StateTransitions<T> {
+	on_enter: Vec<(T, Stage)>,
+	on_exit: Vec<(T, Stage)>,
+	/// Whether the initial state's enter systems have run
+	entered: bool,
+}
+
+impl<T: Eq> StateTransitions<T> {
+	fn run(stages: &mut [(T, Stage)], value: &T, ecs: &mut ECS) {
+		for (_, stage) in stages.iter_mut().filter(|(stage_value, _)| stage_value == value) {
+			stage.run(ecs);
+		}
+	}
+
+	fn stage(stages: &mut Vec<(T, Stage)>, value: T) -> &mut Stage {
+		let position = match stages.iter().position(|(stage_value, _)| *stage_value == value) {
+			Some(position) => position,
+			None => {
+				stages.push((value, Stage::new("transition")));
+				stages.len() - 1
+			},
+		};
+		&mut stages[position].1
+	}
+}
+
+impl<T: Eq + Clone + Send + Sync + 'static> Transitions for StateTransitions<T> {
+	fn as_any_mut(&mut self) -> &mut dyn Any { self }
+
+	fn apply(&mut self, ecs: &mut ECS) {
+		let Some(states) = ecs.get_resource_mut::<States<T>>() else { return };
+		let current = states.current.clone();
+		let next = states.next.take();
+		if !self.entered {
+			self.entered = true;
+			Self::run(&mut self.on_enter, &current, ecs);
+		}
+		let Some(next) = next else { return };
+		if next == current { return; }
+
+		Self::run(&mut self.on_exit, &current, ecs);
+		ecs.get_resource_mut::<States<T>>().unwrap().current = next.clone();
+		Self::run(&mut self.on_enter, &next, ecs);
+	}
+}
+
+impl Schedule {
+	fn transitions<T: Eq + Clone + Send + Sync + 'static>(&mut self) -> &mut StateTransitions<T> {
+		let position = self.transitions.iter_mut().position(|transitions| transitions.as_any_mut().is::<StateTransitions<T>>());
+		let position = position.unwrap_or_else(|| {
+			self.transitions.push(Box::new(StateTransitions::<T> { on_enter: Vec::new(), on_exit: Vec::new(), entered: false }));
+			self.transitions.len() - 1
+		});
+		self.transitions[position].as_any_mut().downcast_mut().unwrap()
+	}
+
+	/// Run `system` whenever `States<T>` becomes `value`, including when the schedule first sees it
+	/// in that state. Like any stage, the exit and enter systems each apply their commands when done.
+	pub fn add_on_enter<T, F>(&mut self, value: T, system: F) -> SystemConfig<'_>
+	where T: Eq + Clone + Send + Sync + 'static, F: FnMut(&mut ECS) + 'static {
+		let stage = StateTransitions::stage(&mut self.transitions::<T>().on_enter, value);
+		stage.push(System::exclusive(Box::new(system)))
+	}
+
+	/// Run `system` whenever `States<T>` leaves `value`
+	pub fn add_on_exit<T, F>(&mut self, value: T, system: F) -> SystemConfig<'_>
+	where T: Eq + Clone + Send + Sync + 'static, F: FnMut(&mut ECS) + 'static {
+		let stage = StateTransitions::stage(&mut self.transitions::<T>().on_exit, value);
+		stage.push(System::exclusive(Box::new(system)))
+	}
+
+	/// `add_system` limited to while `States<T>` is `value`
+	pub fn add_system_in_state<T, F>(&mut self, stage: &str, value: T, access: Access, system: F) -> SystemConfig<'_>
+	where T: Eq + Send + Sync + 'static, F: FnMut(&mut crate::SubWorld) + 'static {
+		self.add_system(stage, access, system).run_if(in_state(value))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Access, Component, Schedule, States, ECS};
+	use alloc::vec::Vec;
+
+	#[derive(Clone, Debug, PartialEq, Eq)]
+	enum Mode { Menu, Playing }
+
+	struct Log(Vec<&'static str>);
+
+	struct Button;
+	impl Component for Button {}
+
+	fn log(name: &'static str) -> impl FnMut(&mut ECS) {
+		move |ecs| ecs.get_resource_mut::<Log>().unwrap().0.push(name)
+	}
+
+	#[test]
+	fn transitions() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Button>();
+		ecs.insert_resource(States::new(Mode::Menu));
+		ecs.insert_resource(Log(Vec::new()));
+
+		let mut schedule = Schedule::new();
+		schedule.add_on_enter(Mode::Menu, log("enter menu"));
+		schedule.add_on_enter(Mode::Menu, |ecs| {
+			ecs.commands().spawn(|ecs, entity| ecs.add_component(entity, Button));
+		});
+		schedule.add_on_exit(Mode::Menu, log("exit menu"));
+		schedule.add_on_enter(Mode::Playing, log("enter playing"));
+		schedule.add_system_in_state(Schedule::UPDATE, Mode::Menu, Access::new(), |_| {});
+		schedule.add_exclusive_system(Schedule::UPDATE, log("menu")).run_if(crate::in_state(Mode::Menu));
+		schedule.add_exclusive_system(Schedule::UPDATE, log("playing")).run_if(crate::in_state(Mode::Playing));
+
+		schedule.run(&mut ecs);
+		assert_eq!(ecs.query::<Button>().count(), 1);
+		ecs.get_resource_mut::<States<Mode>>().unwrap().set(Mode::Playing);
+		schedule.run(&mut ecs);
+		ecs.get_resource_mut::<States<Mode>>().unwrap().set(Mode::Playing);
+		schedule.run(&mut ecs);
+
+		assert_eq!(ecs.get_resource::<States<Mode>>().unwrap().current(), &Mode::Playing);
+		assert_eq!(ecs.get_resource::<Log>().unwrap().0, ["enter menu", "menu", "exit menu", "enter playing", "playing", "playing"]);
+	}
+}
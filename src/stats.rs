@@ -0,0 +1,130 @@
+use crate::component::ComponentID;
+use crate::ECS;
+use alloc::vec::Vec;
+
+/// Counters since the last `reset_stats`, see `ECS::stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+	/// Entities created, including reserved ones activated by `flush`
+	pub spawned: usize,
+	pub despawned: usize,
+	pub inserted: usize,
+	/// Components actually removed, destroying an entity counts each Component it held
+	pub removed: usize,
+	/// ComponentArrays extended by growing capacity
+	pub reallocations: usize,
+}
+
+/// Footprint and churn of one Component, see `ECS::component_stats`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComponentStats {
+	pub id: ComponentID,
+	/// `core::any::type_name`
+	pub name: &'static str,
+	/// Entities currently holding it
+	pub count: usize,
+	/// Heap memory of the column's storage. Default pages span the whole capacity rounded up to a page unless it is paged.
+	pub allocated_bytes: usize,
+	/// Heap memory of the column's pages besides the values: occupancy, and change ticks unless it has custom storage
+	pub page_bytes: usize,
+	/// Bytes taken by the values actually present
+	pub used_bytes: usize,
+	/// Values written since the last `reset_stats`, overwrites included
+	pub inserted: usize,
+	/// Values removed since the last `reset_stats`, whether dropped, taken or destroyed with their entity.
+	/// Overwritten values aren't counted, so `inserted - overwritten - removed` is the change in `count`.
+	pub removed: usize,
+	/// Writes since the last `reset_stats` that replaced a value already present
+	pub overwritten: usize,
+}
+
+impl ECS {
+	pub fn stats(&self) -> Stats { self.stats }
+
+	/// Every registered Component sorted by name. Cheap enough to sample each frame, counting occupancy a word at a time.
+	/// Storage is one column per Component, so there are no archetypes to break these down further.
+	pub fn component_stats(&self) -> Vec<ComponentStats> {
+		let mut stats: Vec<ComponentStats> = self.components.entries().map(|(id, array)| {
+			let (inserted, removed, overwritten) = array.churn();
+			let (inserted_base, removed_base, overwritten_base) = self.stats_baseline.get(&id).copied().unwrap_or_default();
+			let count = array.filled();
+			ComponentStats {
+				id,
+				name: array.info().name(),
+				count,
+				allocated_bytes: array.allocated_bytes(),
+				page_bytes: array.page_bytes(),
+				used_bytes: count * array.info().layout.size(),
+				inserted: inserted.saturating_sub(inserted_base),
+				removed: removed.saturating_sub(removed_base),
+				overwritten: overwritten.saturating_sub(overwritten_base),
+			}
+		}).collect();
+		stats.sort_unstable_by_key(|component| component.name);
+		stats
+	}
+
+	/// Zero every counter, typically once per frame
+	pub fn reset_stats(&mut self) {
+		self.stats = Stats::default();
+		self.stats_baseline = self.components.entries().map(|(id, array)| (id, array.churn())).collect();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, Stats, ECS};
+
+	struct Health;
+	impl Component for Health {}
+
+	struct Position(u32, u32);
+	impl Component for Position {}
+
+	#[test]
+	fn counters() {
+		let mut ecs = ECS::new(1);
+		ecs.set_grow_fn(Some(|capacity| capacity * 2));
+		ecs.register::<Health>();
+
+		let first = ecs.create_entity().unwrap();
+		let second = ecs.create_entity().unwrap();
+		ecs.reserve_entity();
+		ecs.flush();
+		ecs.add_component(&first, Health);
+		ecs.add_component(&second, Health);
+		ecs.remove_component::<Health>(&first);
+		ecs.remove_component::<Health>(&first);
+		ecs.destroy_entity(second);
+
+		assert_eq!(ecs.stats(), Stats { spawned: 3, despawned: 1, inserted: 2, removed: 2, reallocations: 2 });
+		ecs.reset_stats();
+		assert_eq!(ecs.stats(), Stats::default());
+	}
+
+	#[test]
+	fn component_stats() {
+		let mut ecs = ECS::new(4);
+		ecs.register::<Health>();
+		ecs.register::<Position>();
+		let first = ecs.create_entity().unwrap();
+		let second = ecs.create_entity().unwrap();
+		ecs.add_component(&first, Position(1, 2));
+		ecs.add_component(&second, Position(3, 4));
+		ecs.add_component(&second, Position(5, 6));
+		ecs.destroy_entity(first);
+
+		let stats = ecs.component_stats();
+		assert_eq!(stats.len(), 2);
+		assert!(stats[0].name.ends_with("Health") && stats[0].count == 0);
+		let position = stats[1];
+		assert_eq!((position.count, position.used_bytes, position.allocated_bytes), (1, 8, 256 * 8));
+		assert_eq!((position.inserted, position.removed, position.overwritten), (3, 1, 1));
+
+		ecs.reset_stats();
+		let Position(x, y) = ecs.take_component::<Position>(&second).unwrap();
+		assert_eq!((x, y), (5, 6));
+		let position = ecs.component_stats()[1];
+		assert_eq!((position.count, position.inserted, position.removed), (0, 0, 1));
+	}
+}
@@ -1,168 +1,946 @@
-use crate::component::ComponentID;
+use crate::backend::ComponentStorage;
+use crate::component::{ComponentID, DebugFn, EqFn};
 use crate::{Component, component::ComponentInfo};
-use alloc::alloc::{alloc_zeroed, dealloc};
+use alloc::alloc::{alloc, dealloc};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::alloc::Layout;
-use core::ptr::{copy_nonoverlapping, slice_from_raw_parts, slice_from_raw_parts_mut};
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::ops::Range;
+use core::ptr::copy_nonoverlapping;
 use core::any::TypeId;
 use hashbrown::HashMap;
 
-#[repr(u8)]
-#[derive(Debug, PartialEq, Eq)]
-pub enum Slot<C: Component> {
-	Empty = 0,
-	Filled(C),
+/// One bit per index
+#[derive(Clone)]
+pub(crate) struct Occupancy {
+	words: Vec<u64>,
 }
 
-impl<C: Component> Slot<C> {
-	pub(crate) fn is_filled(&self) -> bool {
-		match self {
-		    Slot::Empty => false,
-		    Slot::Filled(_) => true,
-		}
+impl Occupancy {
+	pub(crate) fn new(length: usize) -> Self {
+		Occupancy { words: vec![0; length.div_ceil(64)] }
 	}
 
-	pub(crate) fn as_option(&self) -> Option<&C> {
-		match self {
-			Slot::Empty => None,
-			Slot::Filled(component) => Some(component),
+	pub(crate) fn resize(&mut self, new_length: usize) {
+		self.words.resize(new_length.div_ceil(64), 0);
+	}
+
+	pub(crate) fn get(&self, index: usize) -> bool {
+		self.words[index / 64] & (1 << (index % 64)) != 0
+	}
+
+	/// Indices of every set bit in ascending order
+	pub(crate) fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+		self.words.iter().enumerate().flat_map(|(word_index, word)| {
+			let mut word = *word;
+			core::iter::from_fn(move || {
+				if word == 0 { return None; }
+				let bit = word.trailing_zeros() as usize;
+				word &= word - 1;
+				Some(word_index * 64 + bit)
+			})
+		})
+	}
+
+	pub(crate) fn set(&mut self, index: usize, value: bool) {
+		let word = &mut self.words[index / 64];
+		if value {
+			*word |= 1 << (index % 64);
+		} else {
+			*word &= !(1 << (index % 64));
 		}
 	}
-	
-	pub(crate) fn as_option_mut(&mut self) -> Option<&mut C> {
-		match self {
-			Slot::Empty => None,
-			Slot::Filled(component) => Some(component),
+}
+
+/// Where each index's value physically sits in a column a group owns, see `ECS::create_group`
+#[derive(Clone)]
+pub(crate) struct Order {
+	slot_of: Vec<usize>,
+	index_of: Vec<usize>,
+}
+
+impl Order {
+	fn new(length: usize) -> Self {
+		Order { slot_of: (0..length).collect(), index_of: (0..length).collect() }
+	}
+
+	fn resize(&mut self, new_length: usize) {
+		let length = self.slot_of.len();
+		self.slot_of.extend(length..new_length);
+		self.index_of.extend(length..new_length);
+	}
+}
+
+/// Indices per page, a whole number of occupancy words
+const PAGE_LENGTH: usize = 256;
+const PAGE_WORDS: usize = PAGE_LENGTH / 64;
+
+/// `PAGE_LENGTH` slots of a column's values, with the occupancy and change ticks of as many indices.
+/// Growing only appends pages, so a value stays at the same address for as long as it is stored.
+/// Forks and checkpoints share pages, and whichever side writes to one first takes its own copy.
+struct Page {
+	/// Null under custom storage, and for Components registered as paged until the page holds a value
+	values: *mut u8,
+	/// Slots holding a value, the same bits as `occupancy` unless a group reordered the column
+	held: [u64; PAGE_WORDS],
+	occupancy: [u64; PAGE_WORDS],
+	/// `ECS` change tick of the last write to each index, none under custom storage. Cells so mutable queries
+	/// can stamp them through the shared pages they were handed.
+	ticks: Option<Box<Ticks>>,
+}
+
+type Ticks = [Cell<u64>; PAGE_LENGTH];
+
+impl Page {
+	fn new(ticked: bool) -> Self {
+		let ticks = ticked.then(|| Box::new([const { Cell::new(0) }; PAGE_LENGTH]));
+		Page { values: core::ptr::null_mut(), held: [0; PAGE_WORDS], occupancy: [0; PAGE_WORDS], ticks }
+	}
+
+	fn tick(&self, offset: usize) -> u64 {
+		self.ticks.as_ref().map_or(0, |ticks| ticks[offset].get())
+	}
+
+	fn stamp(&self, offset: usize, tick: u64) {
+		if let Some(ticks) = &self.ticks { ticks[offset].set(tick); }
+	}
+
+	fn get(words: &[u64; PAGE_WORDS], offset: usize) -> bool {
+		words[offset / 64] & (1 << (offset % 64)) != 0
+	}
+
+	fn set(words: &mut [u64; PAGE_WORDS], offset: usize, value: bool) {
+		if value {
+			words[offset / 64] |= 1 << (offset % 64);
+		} else {
+			words[offset / 64] &= !(1 << (offset % 64));
 		}
 	}
+
+	/// Offsets of every set bit in ascending order
+	fn bits(words: [u64; PAGE_WORDS]) -> impl Iterator<Item = usize> {
+		words.into_iter().enumerate().flat_map(|(word_index, mut word)| {
+			core::iter::from_fn(move || {
+				if word == 0 { return None; }
+				let bit = word.trailing_zeros() as usize;
+				word &= word - 1;
+				Some(word_index * 64 + bit)
+			})
+		})
+	}
 }
 
-struct ComponentArray {
-	array: *mut u8,
+/// Raw `C` values in pages, or in whatever `ComponentStorage` the Component was registered with. Occupancy
+/// is paged either way so every backend iterates the same way, change ticks only for the default storage.
+pub(crate) struct ComponentArray {
+	pages: Vec<Rc<Page>>,
+	/// Holds the values instead of the pages if the Component was registered with its own storage
+	custom: Option<Box<dyn ComponentStorage>>,
 	length: usize,
-	array_layout: Layout,
 	component_info: ComponentInfo,
+	/// Slot of each index once a group owns this array, otherwise every index is its own slot.
+	/// Occupancy and ticks stay by index, only values move.
+	order: Option<Order>,
+	/// Values written, removed and overwritten over this array's life, see `ECS::component_stats`
+	inserted: usize,
+	removed: usize,
+	overwritten: usize,
 }
 
 impl ComponentArray {
 	fn new(component_info: ComponentInfo, length: usize) -> Self {
-		let array_layout = Layout::from_size_align(component_info.stride * length, component_info.layout.align()).unwrap();
-		let array = unsafe { alloc_zeroed(array_layout) };
-		assert!(!array.is_null());
+		let custom = component_info.storage.map(|storage| storage(&component_info, length));
+		let mut array = ComponentArray { pages: Vec::new(), custom, length: 0, component_info, order: None, inserted: 0, removed: 0, overwritten: 0 };
+		array.add_pages(length);
+		array.length = length;
+		array
+	}
+
+	/// Every page is whole, even past the length, so growing never has to move one
+	fn page_layout(&self) -> Layout {
+		Layout::from_size_align(self.component_info.stride * PAGE_LENGTH, self.component_info.layout.align()).unwrap()
+	}
 
-		ComponentArray { array, length, array_layout, component_info }
+	fn allocate(layout: Layout) -> *mut u8 {
+		// Dangling but aligned, never dereferenced for more than zero bytes
+		if layout.size() == 0 { return core::ptr::without_provenance_mut(layout.align()); }
+		let values = unsafe { alloc(layout) };
+		assert!(!values.is_null());
+		values
+	}
+
+	/// Allocate values up front unless the Component is paged or brings its own storage
+	fn eager(&self) -> bool {
+		self.custom.is_none() && !self.component_info.paged
+	}
+
+	/// Append whole pages until they cover `new_length` indices
+	fn add_pages(&mut self, new_length: usize) {
+		while self.pages.len() * PAGE_LENGTH < new_length {
+			let mut page = Page::new(self.custom.is_none());
+			if self.eager() { page.values = Self::allocate(self.page_layout()); }
+			self.pages.push(Rc::new(page));
+		}
+	}
+
+	fn free_values(page: &mut Page, layout: Layout) {
+		if page.values.is_null() { return; }
+		if layout.size() != 0 { unsafe { dealloc(page.values, layout) }; }
+		page.values = core::ptr::null_mut();
+	}
+
+	/// Exclusive access to a page, copying it first if a fork or checkpoint still shares it.
+	/// Only cloneable arrays are ever shared.
+	fn page_mut(&mut self, page: usize) -> &mut Page {
+		if Rc::get_mut(&mut self.pages[page]).is_none() {
+			let source = &self.pages[page];
+			let mut copy = Page { values: core::ptr::null_mut(), held: source.held, occupancy: source.occupancy, ticks: source.ticks.clone() };
+			if !source.values.is_null() {
+				copy.values = Self::allocate(self.page_layout());
+				let (clone, stride) = (self.component_info.clone.unwrap(), self.component_info.stride);
+				for offset in Page::bits(source.held) {
+					unsafe { clone(source.values.add(offset * stride), copy.values.add(offset * stride)) };
+				}
+			}
+			self.pages[page] = Rc::new(copy);
+		}
+		Rc::get_mut(&mut self.pages[page]).unwrap()
+	}
+
+	/// Copy out of any fork or checkpoint the pages holding `index`, before its value is written through a pointer
+	pub(crate) fn unshare_index(&mut self, index: usize) {
+		self.page_mut(index / PAGE_LENGTH);
+		self.page_mut(self.slot(index) / PAGE_LENGTH);
+	}
+
+	/// `unshare_index` for every index, before handing out pointers to all of them
+	pub(crate) fn unshare(&mut self) {
+		for page in 0..self.pages.len() {
+			self.page_mut(page);
+		}
 	}
 
 	fn resize(&mut self, new_length: usize) {
-		let new_layout = Layout::from_size_align(self.component_info.stride * new_length, self.component_info.layout.align()).unwrap();
-		unsafe {
-			let new_array = alloc_zeroed(new_layout);
-			copy_nonoverlapping(self.array, new_array, self.component_info.stride * self.length);
-			dealloc(self.array, self.array_layout);
+		if let Some(custom) = &mut self.custom { custom.resize(new_length); }
+		self.add_pages(new_length);
+		self.length = new_length;
+		if let Some(order) = &mut self.order { order.resize(new_length); }
+	}
 
-			self.array = new_array;
-			self.length = new_length;
-			self.array_layout = new_layout;
+	/// Where the value of `index` is stored
+	#[inline]
+	pub(crate) fn slot(&self, index: usize) -> usize {
+		match &self.order {
+			Some(order) => order.slot_of[index],
+			None => index,
 		}
 	}
 
-	fn delete_index(&mut self, index: usize) {
-		unsafe {
-			let ptr = ((self.array as usize) + (index * self.component_info.stride)) as *mut u8;
-			(self.component_info.drop)(ptr);
-			*ptr = 0;
+	/// Offsets from the page itself so the pointer keeps its provenance.
+	/// Null based for a slot on a page without values, which only empty slots can be.
+	#[inline]
+	fn value_ptr(&self, slot: usize) -> *mut u8 {
+		match &self.custom {
+			Some(custom) => custom.get(slot),
+			None => self.pages[slot / PAGE_LENGTH].values.wrapping_add(slot % PAGE_LENGTH * self.component_info.stride),
 		}
 	}
 
-	/// Get slice of internal array. DOES NOT VALIDATE THAT INTERNAL ARRAY IS OF TYPE C
-	unsafe fn get_slice<C: Component>(&self) -> &[Slot<C>] {
-		&*slice_from_raw_parts(self.array as *const Slot<C>, self.length)
+	#[inline]
+	fn element_ptr(&self, index: usize) -> *mut u8 {
+		self.value_ptr(self.slot(index))
 	}
 
-	/// Get mut slice of internal array. DOES NOT VALIDATE AND WILL ALIAS MUTS
-	unsafe fn get_slice_mut<C: Component>(&self) -> &mut [Slot<C>] {
-		&mut *slice_from_raw_parts_mut(self.array as *mut Slot<C>, self.length)
+	/// Memory for a value about to be written at `slot`, which is empty
+	fn insert_slot(&mut self, slot: usize) -> *mut u8 {
+		let (layout, custom) = (self.page_layout(), self.custom.is_some());
+		let page = self.page_mut(slot / PAGE_LENGTH);
+		if !custom && page.values.is_null() {
+			page.values = Self::allocate(layout);
+		}
+		Page::set(&mut page.held, slot % PAGE_LENGTH, true);
+		match &mut self.custom {
+			Some(custom) => custom.insert(slot),
+			None => self.value_ptr(slot),
+		}
+	}
+
+	/// The value at `slot` has been dropped or moved out
+	fn remove_slot(&mut self, slot: usize) {
+		let (layout, lazy) = (self.page_layout(), self.component_info.paged);
+		let page = self.page_mut(slot / PAGE_LENGTH);
+		Page::set(&mut page.held, slot % PAGE_LENGTH, false);
+		if lazy && page.held == [0; PAGE_WORDS] {
+			Self::free_values(page, layout);
+		}
+		if let Some(custom) = &mut self.custom { custom.remove(slot); }
+	}
+
+	fn set_filled(&mut self, index: usize, value: bool) {
+		Page::set(&mut self.page_mut(index / PAGE_LENGTH).occupancy, index % PAGE_LENGTH, value);
+	}
+
+	/// Memory to write a value at `index` into, dropping whatever was there. The caller fills it and marks it.
+	fn slot_ptr(&mut self, index: usize) -> *mut u8 {
+		self.unshare_index(index);
+		if !self.is_filled(index) { return self.insert_slot(self.slot(index)); }
+		self.set_filled(index, false);
+		self.overwritten += 1;
+		unsafe { (self.component_info.drop)(self.element_ptr(index)) };
+		self.element_ptr(index)
+	}
+
+	/// Index one past the last that is stored contiguously with `index`
+	pub(crate) fn contiguous_end(&self, index: usize) -> usize {
+		if self.order.is_some() { return index + 1; }
+		match &self.custom {
+			Some(custom) => custom.contiguous_end(index),
+			None => (index / PAGE_LENGTH + 1) * PAGE_LENGTH,
+		}
+	}
+
+	/// Index stored at each slot, if a group has taken over this array's order
+	pub(crate) fn order(&self) -> Option<&[usize]> {
+		self.order.as_ref().map(|order| &order.index_of[..])
+	}
+
+	/// Exchange where two slots' values are stored, leaving every index holding the same value
+	pub(crate) fn swap_slots(&mut self, a: usize, b: usize) {
+		if a == b { return; }
+		let order = self.order.get_or_insert_with(|| Order::new(self.length));
+		let (index_a, index_b) = (order.index_of[a], order.index_of[b]);
+		order.index_of.swap(a, b);
+		order.slot_of.swap(index_a, index_b);
+		self.page_mut(a / PAGE_LENGTH);
+		self.page_mut(b / PAGE_LENGTH);
+		let stride = self.component_info.stride;
+		match (self.is_filled(index_a), self.is_filled(index_b)) {
+			(true, true) => unsafe { core::ptr::swap_nonoverlapping(self.value_ptr(a), self.value_ptr(b), stride) },
+			(true, false) | (false, true) => {
+				let (from, to) = if self.is_filled(index_a) { (a, b) } else { (b, a) };
+				let destination = self.insert_slot(to);
+				unsafe { copy_nonoverlapping(self.value_ptr(from), destination, stride) };
+				self.remove_slot(from);
+			},
+			(false, false) => {},
+		}
+	}
+
+	/// Move values so that slot `n` stores `index_of[n]`
+	pub(crate) fn set_order(&mut self, index_of: &[usize]) {
+		self.order.get_or_insert_with(|| Order::new(self.length));
+		for (slot, index) in index_of.iter().enumerate() {
+			let current = self.slot(*index);
+			self.swap_slots(slot, current);
+		}
+	}
+
+	pub(crate) fn is_filled(&self, index: usize) -> bool {
+		Page::get(&self.pages[index / PAGE_LENGTH].occupancy, index % PAGE_LENGTH)
+	}
+
+	/// Occupancy of the indices `64 * word..64 * word + 64`
+	pub(crate) fn word(&self, word: usize) -> u64 {
+		self.pages[word / PAGE_WORDS].occupancy[word % PAGE_WORDS]
+	}
+
+	/// Every filled index in ascending order
+	fn filled_indices(&self) -> impl Iterator<Item = usize> + '_ {
+		self.pages.iter().enumerate().flat_map(|(page, data)| Page::bits(data.occupancy).map(move |offset| page * PAGE_LENGTH + offset))
+	}
+
+	pub(crate) fn info(&self) -> &ComponentInfo {
+		&self.component_info
+	}
+
+	pub(crate) fn get_tick(&self, index: usize) -> u64 { self.pages[index / PAGE_LENGTH].tick(index % PAGE_LENGTH) }
+
+	pub(crate) fn filled(&self) -> usize {
+		self.pages.iter().flat_map(|page| page.occupancy).map(|word| word.count_ones() as usize).sum()
+	}
+
+	pub(crate) fn allocated_bytes(&self) -> usize {
+		match &self.custom {
+			Some(custom) => custom.allocated_bytes(),
+			None => self.pages.iter().filter(|page| !page.values.is_null()).count() * self.page_layout().size(),
+		}
+	}
+
+	/// Heap memory of the pages themselves, without the values they point to
+	pub(crate) fn page_bytes(&self) -> usize {
+		let ticks = self.pages.iter().filter(|page| page.ticks.is_some()).count() * core::mem::size_of::<Ticks>();
+		self.pages.len() * core::mem::size_of::<Page>() + ticks
+	}
+
+	/// Cumulative `(inserted, removed, overwritten)`
+	pub(crate) fn churn(&self) -> (usize, usize, usize) { (self.inserted, self.removed, self.overwritten) }
+
+	pub(crate) fn set_tick(&mut self, index: usize, tick: u64) {
+		self.page_mut(index / PAGE_LENGTH).stamp(index % PAGE_LENGTH, tick);
+	}
+
+	/// `set_tick` for a mutable query, whose columns were unshared before it started
+	pub(crate) fn stamp(&self, index: usize, tick: u64) {
+		self.pages[index / PAGE_LENGTH].stamp(index % PAGE_LENGTH, tick);
+	}
+
+	pub(crate) fn get_ptr(&self, index: usize) -> Option<*const u8> {
+		self.is_filled(index).then(|| self.element_ptr(index) as *const u8)
+	}
+
+	/// Returns whether there was a value to drop
+	pub(crate) fn delete_index(&mut self, index: usize) -> bool {
+		if !self.is_filled(index) { return false; }
+		self.unshare_index(index);
+		self.set_filled(index, false);
+		self.removed += 1;
+		unsafe { (self.component_info.drop)(self.element_ptr(index)) };
+		self.remove_slot(self.slot(index));
+		true
+	}
+
+	/// Move the value at `index` into `target` without dropping it, overwriting anything at `target_index`.
+	/// Both arrays must hold the same Component type.
+	fn move_index(&mut self, index: usize, target: &mut ComponentArray, target_index: usize) -> bool {
+		if !self.is_filled(index) { return false; }
+		self.unshare_index(index);
+		let destination = target.slot_ptr(target_index);
+		unsafe { copy_nonoverlapping(self.element_ptr(index), destination, self.component_info.stride) };
+		self.set_filled(index, false);
+		target.set_filled(target_index, true);
+		self.removed += 1;
+		target.inserted += 1;
+		self.remove_slot(self.slot(index));
+		true
+	}
+
+	/// Copy sharing every page until either side writes to it, or holding nothing if the Component isn't cloneable.
+	/// Custom storage can't share, so its values are cloned up front.
+	fn duplicate(&self) -> ComponentArray {
+		let info = self.component_info;
+		let mut copy = match info.clone {
+			Some(_) => {
+				let custom = info.storage.map(|storage| storage(&info, self.length));
+				ComponentArray { pages: self.pages.clone(), custom, length: self.length, component_info: info, order: None, inserted: 0, removed: 0, overwritten: 0 }
+			},
+			None => ComponentArray::new(info, self.length),
+		};
+		copy.order = self.order.clone();
+		(copy.inserted, copy.removed, copy.overwritten) = (self.inserted, self.removed, self.overwritten);
+		if let (Some(clone), Some(values)) = (info.clone, &mut copy.custom) {
+			for index in self.filled_indices() {
+				let slot = self.slot(index);
+				unsafe { clone(self.value_ptr(slot), values.insert(slot)) };
+			}
+		}
+		copy
+	}
+
+	/// Array of `new_info` holding `convert`ed copies of every value, which this array gives up without dropping.
+	/// `convert` must fully initialize a value of the new layout at the pointer it is given.
+	unsafe fn migrate(&mut self, new_info: ComponentInfo, convert: &mut dyn FnMut(&[u8], *mut u8)) -> ComponentArray {
+		self.unshare();
+		let mut migrated = ComponentArray::new(new_info, self.length);
+		migrated.order = self.order.clone();
+		for index in self.filled_indices().collect::<Vec<_>>() {
+			let old = core::slice::from_raw_parts(self.element_ptr(index), self.component_info.layout.size());
+			convert(old, migrated.slot_ptr(index));
+			migrated.set_filled(index, true);
+			migrated.set_tick(index, self.get_tick(index));
+			self.set_filled(index, false);
+			self.remove_slot(self.slot(index));
+		}
+		migrated
+	}
+
+	/// Overwrite index, dropping any previous value. DOES NOT VALIDATE THAT INTERNAL ARRAY IS OF TYPE C
+	pub(crate) unsafe fn insert<C: Component>(&mut self, index: usize, component: C) {
+		(self.slot_ptr(index) as *mut C).write(component);
+		self.set_filled(index, true);
+		self.inserted += 1;
+	}
+
+	/// Exchange the values and occupancy of two indices
+	pub(crate) fn swap(&mut self, a: usize, b: usize) {
+		if a == b { return; }
+		self.unshare_index(a);
+		self.unshare_index(b);
+		let stride = self.component_info.stride;
+		let (filled_a, filled_b) = (self.is_filled(a), self.is_filled(b));
+		match (filled_a, filled_b) {
+			(true, true) => unsafe { core::ptr::swap_nonoverlapping(self.element_ptr(a), self.element_ptr(b), stride) },
+			(true, false) | (false, true) => {
+				let (from, to) = if filled_a { (a, b) } else { (b, a) };
+				let destination = self.insert_slot(self.slot(to));
+				unsafe { copy_nonoverlapping(self.element_ptr(from), destination, stride) };
+				self.remove_slot(self.slot(from));
+			},
+			(false, false) => {},
+		}
+		self.set_filled(a, filled_b);
+		self.set_filled(b, filled_a);
+		let (tick_a, tick_b) = (self.get_tick(a), self.get_tick(b));
+		self.set_tick(a, tick_b);
+		self.set_tick(b, tick_a);
+	}
+
+	/// Move the value out, leaving index empty. DOES NOT VALIDATE THAT INTERNAL ARRAY IS OF TYPE C
+	pub(crate) unsafe fn take<C: Component>(&mut self, index: usize) -> Option<C> {
+		if !self.is_filled(index) { return None; }
+		self.unshare_index(index);
+		self.set_filled(index, false);
+		self.removed += 1;
+		let component = (self.element_ptr(index) as *const C).read();
+		self.remove_slot(self.slot(index));
+		Some(component)
+	}
+
+	/// DOES NOT VALIDATE THAT INTERNAL ARRAY IS OF TYPE C
+	pub(crate) unsafe fn get<C: Component>(&self, index: usize) -> Option<&C> {
+		self.is_filled(index).then(|| &*(self.element_ptr(index) as *const C))
+	}
+
+	/// DOES NOT VALIDATE AND WILL ALIAS MUTS, nor copy a shared page, see `unshare_index`
+	#[allow(clippy::mut_from_ref)]
+	pub(crate) unsafe fn get_mut<C: Component>(&self, index: usize) -> Option<&mut C> {
+		self.is_filled(index).then(|| &mut *(self.element_ptr(index) as *mut C))
+	}
+
+	/// Every value in memory order with the index each belongs to, if the storage packs them.
+	/// DOES NOT VALIDATE THAT INTERNAL ARRAY IS OF TYPE C
+	pub(crate) unsafe fn packed<C: Component>(&self) -> Option<(&[usize], &[C])> {
+		let (values, indices) = self.custom.as_ref()?.packed()?;
+		Some((indices, core::slice::from_raw_parts(values as *const C, indices.len())))
+	}
+
+	/// DOES NOT VALIDATE AND WILL ALIAS MUTS
+	#[allow(clippy::mut_from_ref)]
+	pub(crate) unsafe fn packed_mut<C: Component>(&self) -> Option<(&[usize], &mut [C])> {
+		let (values, indices) = self.custom.as_ref()?.packed()?;
+		Some((indices, core::slice::from_raw_parts_mut(values as *mut C, indices.len())))
+	}
+
+	/// Like `get` but also skips the occupancy check
+	pub(crate) unsafe fn get_unchecked<C: Component>(&self, index: usize) -> &C {
+		&*(self.element_ptr(index) as *const C)
+	}
+
+	/// Like `get_mut` but also skips the occupancy check
+	#[allow(clippy::mut_from_ref)]
+	pub(crate) unsafe fn get_mut_unchecked<C: Component>(&self, index: usize) -> &mut C {
+		&mut *(self.element_ptr(index) as *mut C)
 	}
 }
 
 impl Drop for ComponentArray {
     fn drop(&mut self) {
-		for index in 0..self.length {
-			self.delete_index(index);
+		let (drop, layout) = (self.component_info.drop, self.page_layout());
+		for page in 0..self.pages.len() {
+			// The values of a page still shared are left to whoever holds it last
+			if self.custom.is_none() && Rc::strong_count(&self.pages[page]) > 1 { continue; }
+			for offset in Page::bits(self.pages[page].held) {
+				let slot = page * PAGE_LENGTH + offset;
+				unsafe { drop(self.value_ptr(slot)) };
+				if let Some(custom) = &mut self.custom { custom.remove(slot); }
+			}
+			if let Some(page) = Rc::get_mut(&mut self.pages[page]) {
+				Self::free_values(page, layout);
+			}
 		}
-
-		unsafe { dealloc(self.array, self.array_layout) }
     }
 }
 
+/// Typed handle to a ComponentArray for query iteration
+pub struct Column<C: Component> {
+	array: *const ComponentArray,
+	_marker: PhantomData<fn() -> C>,
+}
+
+impl<C: Component> Clone for Column<C> {
+	fn clone(&self) -> Self { *self }
+}
+
+impl<C: Component> Copy for Column<C> {}
+
+impl<C: Component> Column<C> {
+	pub(crate) fn is_filled(&self, index: usize) -> bool {
+		unsafe { (*self.array).is_filled(index) }
+	}
+
+	pub(crate) fn filled_word(&self, word: usize) -> u64 {
+		unsafe { (*self.array).word(word) }
+	}
+
+	/// Index one past the last stored contiguously with `index`, bounding the slices `slice` can take
+	pub(crate) fn contiguous_end(&self, index: usize) -> usize {
+		unsafe { (*self.array).contiguous_end(index) }
+	}
+
+	/// Hint that `index` will be read soon, ignored past the end or if empty
+	#[inline]
+	pub(crate) fn prefetch(&self, index: usize) {
+		let array = unsafe { &*self.array };
+		if index < array.length && array.is_filled(index) { prefetch(array.element_ptr(index)); }
+	}
+
+	/// Caller picks the lifetime, it must not outlive the ComponentArray and every index of `range` must be filled
+	pub(crate) unsafe fn slice<'a>(&self, range: Range<usize>) -> &'a [C] {
+		core::slice::from_raw_parts((*self.array).element_ptr(range.start) as *const C, range.len())
+	}
+
+	/// Caller picks the lifetime, it must not outlive or alias the ComponentArray and every index of `range` must be filled
+	pub(crate) unsafe fn slice_mut<'a>(&self, range: Range<usize>) -> &'a mut [C] {
+		core::slice::from_raw_parts_mut((*self.array).element_ptr(range.start) as *mut C, range.len())
+	}
+
+	/// Caller picks the lifetime, it must not outlive the ComponentArray
+	pub(crate) unsafe fn get<'a>(&self, index: usize) -> Option<&'a C> {
+		(*self.array).get::<C>(index)
+	}
+
+	/// Caller picks the lifetime, it must not outlive or alias the ComponentArray
+	pub(crate) unsafe fn get_mut<'a>(&self, index: usize) -> Option<&'a mut C> {
+		(*self.array).get_mut::<C>(index)
+	}
+
+	pub(crate) unsafe fn get_unchecked<'a>(&self, index: usize) -> &'a C {
+		(*self.array).get_unchecked::<C>(index)
+	}
+
+	pub(crate) unsafe fn get_mut_unchecked<'a>(&self, index: usize) -> &'a mut C {
+		(*self.array).get_mut_unchecked::<C>(index)
+	}
+
+	/// The value stored at `slot` rather than index, which must be filled
+	pub(crate) unsafe fn get_slot_unchecked<'a>(&self, slot: usize) -> &'a C {
+		&*((*self.array).value_ptr(slot) as *const C)
+	}
+
+	/// The value stored at `slot` rather than index, which must be filled.
+	/// Caller picks the lifetime, it must not outlive or alias the ComponentArray.
+	pub(crate) unsafe fn get_slot_mut_unchecked<'a>(&self, slot: usize) -> &'a mut C {
+		&mut *((*self.array).value_ptr(slot) as *mut C)
+	}
+
+	/// Record that `index` was borrowed mutably at `tick`, see `ComponentArray::stamp`
+	pub(crate) unsafe fn stamp(&self, index: usize, tick: u64) {
+		(*self.array).stamp(index, tick)
+	}
+}
+
+/// Ask the CPU to pull `pointer` into cache. Never faults, whatever the address.
+#[inline(always)]
+fn prefetch(pointer: *const u8) {
+	#[cfg(all(feature = "prefetch", target_arch = "x86_64"))]
+	unsafe { core::arch::x86_64::_mm_prefetch::<{ core::arch::x86_64::_MM_HINT_T0 }>(pointer as *const i8) };
+	#[cfg(all(feature = "prefetch", target_arch = "aarch64"))]
+	unsafe { core::arch::asm!("prfm pldl1keep, [{}]", in(reg) pointer, options(nostack, preserves_flags, readonly)) };
+	#[cfg(not(all(feature = "prefetch", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+	let _ = pointer;
+}
+
+/// ComponentArrays by id. Only cloneable arrays are ever shared, between worlds split by `fork` and with
+/// checkpoints. Anything writing to one first takes its own copy with `unique`, which still shares the pages
+/// until each is written.
 pub(crate) struct ComponentMap {
-	map: HashMap<ComponentID, ComponentArray>,
+	map: HashMap<ComponentID, Rc<ComponentArray>>,
+	/// Bumped by every `register`, `unregister`, and copy out of a shared array, which may move
+	/// ComponentArrays and so invalidate `Column`s
+	version: usize,
+	/// Change tick every value counts as written at, set when `restore` replaces them wholesale
+	/// so shared arrays needn't be copied to stamp each one
+	restored_tick: u64,
+}
+
+/// Exclusive access to `array`, copying its page table first if another world still shares it
+fn unique<'a>(array: &'a mut Rc<ComponentArray>, version: &mut usize) -> &'a mut ComponentArray {
+	if Rc::get_mut(array).is_none() {
+		*array = Rc::new(array.duplicate());
+		*version += 1;
+	}
+	Rc::get_mut(array).unwrap()
 }
 
 impl ComponentMap {
 	pub(crate) fn new() -> Self {
-		ComponentMap { map: HashMap::new() }
+		ComponentMap { map: HashMap::new(), version: 0, restored_tick: 0 }
 	}
 
 	// Length must be uniform across ComponentArrays
 	pub(crate) fn register(&mut self, id: ComponentID, info: ComponentInfo, length: usize) {
 		let array = ComponentArray::new(info, length);
-		self.map.insert(id, array);
+		self.map.insert(id, Rc::new(array));
+		self.version += 1;
+	}
+
+	/// Remove the ComponentArray of `id`, which drops its values along with it
+	pub(crate) fn unregister(&mut self, id: ComponentID) -> Option<Rc<ComponentArray>> {
+		let array = self.map.remove(&id)?;
+		self.version += 1;
+		Some(array)
+	}
+
+	pub(crate) fn version(&self) -> usize { self.version }
+
+	pub(crate) fn schema_hash(&self) -> u64 {
+		crate::component::schema_hash(self.map.values().map(|array| array.info()))
 	}
 
 	pub(crate) fn resize(&mut self, new_length: usize) {
 		for component_array in self.map.values_mut() {
-			component_array.resize(new_length);
+			unique(component_array, &mut self.version).resize(new_length);
 		}
 	}
 
-	pub(crate) fn delete_index(&mut self, index: usize) {
-		for component in self.map.values_mut() {
-			component.delete_index(index);
+	/// Returns how many Components were dropped
+	pub(crate) fn delete_index(&mut self, index: usize) -> usize {
+		let filled = self.map.values_mut().filter(|component| component.is_filled(index));
+		filled.map(|component| unique(component, &mut self.version).delete_index(index) as usize).sum()
+	}
+
+	pub(crate) fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Exchange everything held at `a` and `b`
+	pub(crate) fn swap_index(&mut self, a: usize, b: usize) {
+		for array in self.map.values_mut() {
+			if array.is_filled(a) || array.is_filled(b) {
+				unique(array, &mut self.version).swap(a, b);
+			}
 		}
 	}
 
-	pub(crate) fn get_array<C: Component>(&self) -> Option<&[Slot<C>]> {
-		let array = self.map.get(&TypeId::of::<C>())?;
-		unsafe { Some(array.get_slice::<C>()) }
+	/// Count every value as written at `tick`, as after values were replaced wholesale
+	pub(crate) fn set_restored_tick(&mut self, tick: u64) {
+		self.restored_tick = tick;
 	}
 
-	/// DO NOT LET THIS ALIAS COMPONENTARRAYS
-	pub(crate) unsafe fn get_array_mut<C: Component>(&self) -> Option<&mut [Slot<C>]> {
-		let array = self.map.get(&TypeId::of::<C>())?;
-		Some(array.get_slice_mut::<C>())
+	/// `ECS` change tick of the last write to `index` of `array`, which must be one of these
+	pub(crate) fn get_tick(&self, array: &ComponentArray, index: usize) -> u64 {
+		array.get_tick(index).max(self.restored_tick)
+	}
+
+	/// Exchange two slots in every array of `ids`, see `ComponentArray::swap_slots`
+	pub(crate) fn swap_slots(&mut self, ids: &[ComponentID], a: usize, b: usize) {
+		if a == b { return; }
+		for id in ids {
+			if let Some(array) = self.map.get_mut(id) {
+				unique(array, &mut self.version).swap_slots(a, b);
+			}
+		}
+	}
+
+	/// Store every array of `ids` in the order `index_of`, see `ComponentArray::set_order`
+	pub(crate) fn set_order(&mut self, ids: &[ComponentID], index_of: &[usize]) {
+		for id in ids {
+			if let Some(array) = self.map.get_mut(id).filter(|array| array.order() != Some(index_of)) {
+				unique(array, &mut self.version).set_order(index_of);
+			}
+		}
+	}
+
+	pub(crate) fn set_tick(&mut self, id: ComponentID, index: usize, tick: u64) {
+		if let Some(array) = self.map.get_mut(&id) {
+			unique(array, &mut self.version).set_tick(index, tick);
+		}
+	}
+
+	pub(crate) fn arrays_filled(&self, index: usize) -> Vec<ComponentID> {
+		self.map.iter().filter(|(_, array)| array.is_filled(index)).map(|(id, _)| *id).collect()
+	}
+
+	/// Same Components registered, holding clones of every cloneable one's values. Nothing is left shared.
+	pub(crate) fn duplicate(&self) -> ComponentMap {
+		let map = self.map.iter().map(|(id, array)| {
+			let mut copy = array.duplicate();
+			copy.unshare();
+			(*id, Rc::new(copy))
+		}).collect();
+		ComponentMap { map, version: 0, restored_tick: self.restored_tick }
+	}
+
+	/// Like `duplicate` but sharing every cloneable array until either side writes to it
+	pub(crate) fn fork(&self) -> ComponentMap {
+		let map = self.map.iter().map(|(id, array)| {
+			let array = match array.component_info.clone {
+				Some(_) => array.clone(),
+				None => Rc::new(ComponentArray::new(array.component_info, array.length)),
+			};
+			(*id, array)
+		}).collect();
+		ComponentMap { map, version: 0, restored_tick: self.restored_tick }
+	}
+
+	/// Replace the array of `id` with one of `new_info`, see `ComponentArray::migrate`. Returns false if `id` is unregistered.
+	pub(crate) unsafe fn migrate(&mut self, id: ComponentID, new_info: ComponentInfo, convert: &mut dyn FnMut(&[u8], *mut u8)) -> bool {
+		let Some(array) = self.map.get_mut(&id) else { return false };
+		let migrated = unique(array, &mut self.version).migrate(new_info, convert);
+		*array = Rc::new(migrated);
+		self.version += 1;
+		true
+	}
+
+	/// `unshare` for just the pages holding `index`, see `ComponentArray::unshare_index`
+	pub(crate) fn unshare_index(&mut self, id: ComponentID, index: usize) {
+		if let Some(array) = self.map.get_mut(&id) {
+			unique(array, &mut self.version).unshare_index(index);
+		}
+	}
+
+	/// Stop sharing the array of `id` and its pages with any fork, before handing out pointers that will be written through
+	pub(crate) fn unshare(&mut self, id: ComponentID) {
+		if let Some(array) = self.map.get_mut(&id) {
+			unique(array, &mut self.version).unshare();
+		}
+	}
+
+	#[cfg(test)]
+	pub(crate) fn is_shared(&self, id: ComponentID) -> bool {
+		self.map.get(&id).is_some_and(|array| Rc::strong_count(array) > 1)
+	}
+
+	/// Every cloneable ComponentArray, shared until either side writes to it
+	pub(crate) fn snapshot(&self) -> Vec<(ComponentID, Rc<ComponentArray>)> {
+		self.map.iter().filter(|(_, array)| array.component_info.clone.is_some()).map(|(id, array)| (*id, array.clone())).collect()
+	}
+
+	/// Share the arrays of `columns` again, grown to `length`. Arrays that weren't snapshot only keep values at indices where `keep` holds.
+	pub(crate) fn restore(&mut self, columns: &[(ComponentID, Rc<ComponentArray>)], length: usize, keep: impl Fn(usize) -> bool) {
+		for (id, array) in self.map.iter_mut() {
+			match columns.iter().find(|(column_id, _)| column_id == id) {
+				Some((_, snapshot)) => {
+					*array = snapshot.clone();
+					if array.length < length {
+						unique(array, &mut self.version).resize(length);
+					}
+				},
+				None => {
+					let array = unique(array, &mut self.version);
+					for index in 0..array.length {
+						if !keep(index) {
+							array.delete_index(index);
+						}
+					}
+				},
+			}
+		}
+		self.version += 1;
+	}
+
+	/// Move every Component at `index` that `target` also has registered, returning the ids moved
+	pub(crate) fn move_index(&mut self, index: usize, target: &mut ComponentMap, target_index: usize) -> Vec<ComponentID> {
+		let mut moved = Vec::new();
+		for (id, array) in self.map.iter_mut() {
+			if !array.is_filled(index) { continue; }
+			if let Some(target_array) = target.map.get_mut(id) {
+				let target_array = unique(target_array, &mut target.version);
+				if unique(array, &mut self.version).move_index(index, target_array, target_index) {
+					moved.push(*id);
+				}
+			}
+		}
+		moved
+	}
+
+	pub(crate) fn set_stable_name(&mut self, id: ComponentID, name: &'static str) -> bool {
+		match self.map.get_mut(&id) {
+			Some(array) => {
+				unique(array, &mut self.version).component_info.stable_name = Some(name);
+				true
+			},
+			None => false,
+		}
+	}
+
+	pub(crate) fn set_debug(&mut self, id: ComponentID, debug: DebugFn) -> bool {
+		match self.map.get_mut(&id) {
+			Some(array) => {
+				unique(array, &mut self.version).component_info.debug = Some(debug);
+				true
+			},
+			None => false,
+		}
+	}
+
+	pub(crate) fn set_eq(&mut self, id: ComponentID, eq: EqFn) -> bool {
+		match self.map.get_mut(&id) {
+			Some(array) => {
+				unique(array, &mut self.version).component_info.eq = Some(eq);
+				true
+			},
+			None => false,
+		}
+	}
+
+	pub(crate) fn arrays(&self) -> impl Iterator<Item = &ComponentArray> {
+		self.map.values().map(|array| &**array)
+	}
+
+	pub(crate) fn entries(&self) -> impl Iterator<Item = (ComponentID, &ComponentArray)> {
+		self.map.iter().map(|(id, array)| (*id, &**array))
+	}
+
+	pub(crate) fn is_filled(&self, id: ComponentID, index: usize) -> bool {
+		match self.map.get(&id) {
+			Some(array) => array.is_filled(index),
+			None => false,
+		}
+	}
+
+	pub(crate) fn get_array_by_id(&self, id: ComponentID) -> Option<&ComponentArray> {
+		self.map.get(&id).map(|array| &**array)
+	}
+
+	pub(crate) fn get_array<C: Component>(&self) -> Option<&ComponentArray> {
+		self.get_array_by_id(TypeId::of::<C>())
+	}
+
+	pub(crate) fn get_array_mut<C: Component>(&mut self) -> Option<&mut ComponentArray> {
+		let array = self.map.get_mut(&TypeId::of::<C>())?;
+		Some(unique(array, &mut self.version))
+	}
+
+	/// Readers only, see `unshare` before writing through it
+	pub(crate) fn get_column<C: Component>(&self) -> Option<Column<C>> {
+		let array = &**self.map.get(&TypeId::of::<C>())?;
+		Some(Column { array, _marker: PhantomData })
 	}
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{Component, component::ComponentInfo, storage::{ComponentArray, Slot}};
+    use crate::{Component, component::ComponentInfo, storage::ComponentArray};
 
 	#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 	pub struct TestComponent(usize);
 	impl Component for TestComponent {}
 	const INFO: ComponentInfo = ComponentInfo::new::<TestComponent>();
 
+	#[derive(Debug, PartialEq, Eq)]
+	pub struct Marker;
+	impl Component for Marker {}
+	const MARKER_INFO: ComponentInfo = ComponentInfo::new::<Marker>();
+
 	#[test]
 	fn delete_index() {
 		const LENGTH: usize = 32;
 		let mut array = ComponentArray::new(INFO, LENGTH);
 
-		unsafe {
-			let slice_mut = array.get_slice_mut::<TestComponent>();
-			for index in 0..LENGTH {
-				slice_mut[index] = Slot::Filled(TestComponent(index));
-			}
+		for index in 0..LENGTH {
+			unsafe { array.insert(index, TestComponent(index)) };
 		}
 
 		for index in 0..LENGTH {
 			array.delete_index(index);
 		}
 
-		unsafe {
-			let slice = array.get_slice::<TestComponent>();
-			for index in 0..LENGTH {
-				assert_eq!(slice[index], Slot::Empty);
-			}
+		for index in 0..LENGTH {
+			assert_eq!(unsafe { array.get::<TestComponent>(index) }, None);
 		}
 	}
 
@@ -172,25 +950,133 @@ mod test {
 		const RESIZED_LENGTH: usize = 128;
 		let mut array = ComponentArray::new(INFO, STARTING_LENGTH);
 
-		unsafe {
-			let slice_mut = array.get_slice_mut::<TestComponent>();
-			for index in 0..STARTING_LENGTH {
-				slice_mut[index] = Slot::Filled(TestComponent(index));
-			}
+		for index in 0..STARTING_LENGTH {
+			unsafe { array.insert(index, TestComponent(index)) };
 		}
 
 		array.resize(RESIZED_LENGTH);
 
+		for index in 0..RESIZED_LENGTH {
+			let component = if index < STARTING_LENGTH {
+				Some(TestComponent(index))
+			} else {
+				None
+			};
+			assert_eq!(unsafe { array.get::<TestComponent>(index) }.copied(), component);
+		}
+	}
+
+	#[test]
+	fn growth() {
+		let mut array = ComponentArray::new(INFO, 600);
 		unsafe {
-			let slice = array.get_slice::<TestComponent>();
-			for index in 0..RESIZED_LENGTH {
-				let component = if index < STARTING_LENGTH {
-					Slot::Filled(TestComponent(index))
-				} else {
-					Slot::Empty
-				};
-				assert_eq!(slice[index], component);
-			}
+			array.insert(10, TestComponent(10));
+			array.insert(590, TestComponent(590));
+		}
+		array.set_tick(590, 3);
+		let pointers = (array.get_ptr(10), array.get_ptr(590));
+		array.resize(5000);
+		assert_eq!((array.get_ptr(10), array.get_ptr(590)), pointers);
+		assert_eq!((array.get_tick(590), array.filled()), (3, 2));
+		assert_eq!(unsafe { array.get::<TestComponent>(590) }, Some(&TestComponent(590)));
+		assert_eq!(array.allocated_bytes(), 5000usize.div_ceil(super::PAGE_LENGTH) * super::PAGE_LENGTH * INFO.stride);
+	}
+
+	#[test]
+	fn shared_pages() {
+		#[derive(Clone, Debug, PartialEq, Eq)]
+		struct Boxed(alloc::boxed::Box<usize>);
+		impl Component for Boxed {}
+
+		let mut array = ComponentArray::new(ComponentInfo::cloneable::<Boxed>(), 4 * super::PAGE_LENGTH);
+		for index in 0..array.length {
+			unsafe { array.insert(index, Boxed(alloc::boxed::Box::new(index))) };
+		}
+		let mut copy = array.duplicate();
+		let pointer = array.get_ptr(600);
+		assert_eq!(copy.get_ptr(600), pointer);
+
+		unsafe { copy.insert(5, Boxed(alloc::boxed::Box::new(500))) };
+		copy.delete_index(900);
+		let shared = (0..4).filter(|page| alloc::rc::Rc::ptr_eq(&array.pages[*page], &copy.pages[*page])).count();
+		assert_eq!(shared, 2);
+		assert_eq!(copy.get_ptr(600), pointer);
+		let value = |array: &ComponentArray, index| unsafe { array.get::<Boxed>(index) }.map(|boxed| *boxed.0);
+		assert_eq!((value(&array, 5), value(&array, 900)), (Some(5), Some(900)));
+		assert_eq!((value(&copy, 5), value(&copy, 900)), (Some(500), None));
+
+		drop(array);
+		assert_eq!(value(&copy, 600), Some(600));
+	}
+
+	#[test]
+	fn niche() {
+		#[derive(Debug, PartialEq, Eq)]
+		struct Boxed(alloc::boxed::Box<usize>);
+		impl Component for Boxed {}
+
+		const LENGTH: usize = 16;
+		let mut array = ComponentArray::new(ComponentInfo::new::<Boxed>(), LENGTH);
+		assert_eq!(array.component_info.stride, core::mem::size_of::<Boxed>());
+
+		for index in 0..LENGTH {
+			unsafe { array.insert(index, Boxed(alloc::boxed::Box::new(index))) };
+		}
+		array.delete_index(3);
+		unsafe { array.insert(5, Boxed(alloc::boxed::Box::new(500))) };
+
+		for index in 0..LENGTH {
+			let expected = match index {
+				3 => None,
+				5 => Some(500),
+				_ => Some(index),
+			};
+			assert_eq!(unsafe { array.get::<Boxed>(index) }.map(|boxed| *boxed.0), expected);
+		}
+	}
+
+	#[test]
+	fn zero_sized() {
+		const LENGTH: usize = 100;
+		let mut array = ComponentArray::new(MARKER_INFO, LENGTH);
+		assert_eq!(array.allocated_bytes(), 0);
+
+		for index in (0..LENGTH).step_by(3) {
+			unsafe { array.insert(index, Marker) };
+		}
+		array.resize(LENGTH * 2);
+		array.delete_index(0);
+
+		for index in 0..LENGTH * 2 {
+			let filled = index != 0 && index < LENGTH && index % 3 == 0;
+			assert_eq!(array.is_filled(index), filled);
+			assert_eq!(unsafe { array.get::<Marker>(index) }.is_some(), filled);
+		}
+	}
+
+	#[test]
+	fn paged() {
+		#[derive(Debug, PartialEq, Eq)]
+		struct Boxed(alloc::boxed::Box<usize>);
+		impl Component for Boxed {}
+
+		let info = ComponentInfo::new::<Boxed>().paged();
+		let page_bytes = super::PAGE_LENGTH * info.stride;
+		let mut array = ComponentArray::new(info, 1000);
+		assert_eq!(array.allocated_bytes(), 0);
+
+		for index in [3, 700, 701] {
+			unsafe { array.insert(index, Boxed(alloc::boxed::Box::new(index))) };
 		}
+		assert_eq!(array.allocated_bytes(), 2 * page_bytes);
+		array.resize(5000);
+		array.swap(3, 4000);
+		assert_eq!(array.allocated_bytes(), 2 * page_bytes);
+		assert_eq!(unsafe { array.get::<Boxed>(4000) }.map(|boxed| *boxed.0), Some(3));
+		assert_eq!(unsafe { array.take::<Boxed>(700) }.map(|boxed| *boxed.0), Some(700));
+		assert_eq!(array.allocated_bytes(), 2 * page_bytes);
+		array.delete_index(701);
+		assert_eq!(array.allocated_bytes(), page_bytes);
+		assert_eq!((array.contiguous_end(300), array.contiguous_end(511)), (512, 512));
 	}
 }
@@ -0,0 +1,197 @@
+use crate::component::ComponentID;
+use crate::{Commands, Component, EntityID, Query, QueryIter, QueryMut, QueryMutIter, ECS};
+use alloc::vec::Vec;
+
+/// Declared set of Component types a system may read or write. Writing implies reading.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Access {
+	reads: Vec<ComponentID>,
+	writes: Vec<ComponentID>,
+}
+
+impl Access {
+	pub fn new() -> Self {
+		Access::default()
+	}
+
+	pub fn read<C: Component>(mut self) -> Self {
+		self.add_read(ComponentID::of::<C>());
+		self
+	}
+
+	pub fn write<C: Component>(mut self) -> Self {
+		self.add_write(ComponentID::of::<C>());
+		self
+	}
+
+	/// Reads of every Component `Q` accesses
+	pub fn of_query<Q: Query>() -> Self {
+		let mut access = Access::new();
+		for id in Q::component_ids() {
+			access.add_read(id);
+		}
+		access
+	}
+
+	/// Writes of every Component `Q` accesses
+	pub fn of_query_mut<Q: QueryMut>() -> Self {
+		let mut access = Access::new();
+		for id in Q::component_ids() {
+			access.add_write(id);
+		}
+		access
+	}
+
+	pub fn add_read(&mut self, id: ComponentID) {
+		if !self.reads.contains(&id) { self.reads.push(id); }
+	}
+
+	pub fn add_write(&mut self, id: ComponentID) {
+		self.add_read(id);
+		if !self.writes.contains(&id) { self.writes.push(id); }
+	}
+
+	pub fn reads(&self) -> &[ComponentID] { &self.reads }
+
+	pub fn writes(&self) -> &[ComponentID] { &self.writes }
+
+	pub fn can_read(&self, id: ComponentID) -> bool { self.reads.contains(&id) }
+
+	pub fn can_write(&self, id: ComponentID) -> bool { self.writes.contains(&id) }
+
+	/// True if both can run at the same time, meaning neither writes anything the other touches
+	pub fn is_compatible(&self, other: &Access) -> bool {
+		!self.writes.iter().any(|id| other.can_read(*id)) && !other.writes.iter().any(|id| self.can_read(*id))
+	}
+}
+
+/// View of an ECS restricted to an `Access`. Touching any undeclared Component panics.
+pub struct SubWorld<'a> {
+	ecs: &'a mut ECS,
+	access: Access,
+}
+
+impl<'a> SubWorld<'a> {
+	pub fn new(ecs: &'a mut ECS, access: Access) -> Self {
+		SubWorld { ecs, access }
+	}
+
+	pub fn get_access(&self) -> &Access { &self.access }
+
+	fn check_read(&self, id: ComponentID) {
+		assert!(self.access.can_read(id), "SubWorld has no read access to this Component");
+	}
+
+	fn check_write(&self, id: ComponentID) {
+		assert!(self.access.can_write(id), "SubWorld has no write access to this Component");
+	}
+
+	pub fn is_valid(&self, entity: &EntityID) -> bool { self.ecs.is_valid(entity) }
+
+	/// Queue structural changes of any kind, the declared `Access` doesn't limit them
+	pub fn commands(&self) -> Commands { self.ecs.commands() }
+
+	pub fn has_component<C: Component>(&self, entity: &EntityID) -> bool {
+		self.check_read(ComponentID::of::<C>());
+		self.ecs.has_component::<C>(entity)
+	}
+
+	pub fn get_component<C: Component>(&self, entity: &EntityID) -> Option<&C> {
+		self.check_read(ComponentID::of::<C>());
+		self.ecs.get_component(entity)
+	}
+
+	pub fn get_component_mut<C: Component>(&mut self, entity: &EntityID) -> Option<&mut C> {
+		self.check_write(ComponentID::of::<C>());
+		self.ecs.get_component_mut(entity)
+	}
+
+	pub fn add_component<C: Component>(&mut self, entity: &EntityID, component: C) {
+		self.check_write(ComponentID::of::<C>());
+		self.ecs.add_component(entity, component);
+	}
+
+	pub fn remove_component<C: Component>(&mut self, entity: &EntityID) {
+		self.check_write(ComponentID::of::<C>());
+		self.ecs.remove_component::<C>(entity);
+	}
+
+	pub fn query<Q: Query>(&self) -> QueryIter<'_, Q> {
+		for id in Q::component_ids() {
+			self.check_read(id);
+		}
+		self.ecs.query::<Q>()
+	}
+
+	pub fn query_mut<Q: QueryMut>(&mut self) -> QueryMutIter<'_, Q> {
+		for id in Q::component_ids() {
+			self.check_write(id);
+		}
+		self.ecs.query_mut::<Q>()
+	}
+}
+
+impl ECS {
+	pub fn sub_world(&mut self, access: Access) -> SubWorld<'_> { SubWorld::new(self, access) }
+}
+
+#[cfg(test)]
+mod test {
+	extern crate std;
+	use crate::{Access, Component, ECS};
+
+	struct Position(usize);
+	impl Component for Position {}
+	struct Velocity(usize);
+	impl Component for Velocity {}
+	struct Mesh;
+	impl Component for Mesh {}
+
+	fn world() -> (ECS, crate::EntityID) {
+		let mut ecs = ECS::new(8);
+		ecs.register::<Position>();
+		ecs.register::<Velocity>();
+		ecs.register::<Mesh>();
+		let entity = ecs.create_entity().unwrap();
+		ecs.add_component(&entity, Position(0));
+		ecs.add_component(&entity, Velocity(3));
+		ecs.add_component(&entity, Mesh);
+		(ecs, entity)
+	}
+
+	#[test]
+	fn declared() {
+		let (mut ecs, entity) = world();
+		let mut sub = ecs.sub_world(Access::new().write::<Position>().read::<Velocity>());
+		let velocity = sub.get_component::<Velocity>(&entity).unwrap().0;
+		for (_, position) in sub.query_mut::<Position>() {
+			position.0 += velocity;
+		}
+		assert_eq!(sub.query::<(Position, Velocity)>().count(), 1);
+		assert_eq!(ecs.get_component::<Position>(&entity).unwrap().0, 3);
+	}
+
+	#[test]
+	fn undeclared() {
+		let (mut ecs, entity) = world();
+		let sub = ecs.sub_world(Access::new().read::<Velocity>());
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| sub.has_component::<Mesh>(&entity)));
+		assert!(result.is_err());
+
+		let mut sub = ecs.sub_world(Access::new().read::<Mesh>());
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| { sub.query_mut::<Mesh>(); }));
+		assert!(result.is_err());
+	}
+
+	#[test]
+	fn compatibility() {
+		let physics = Access::new().write::<Position>().read::<Velocity>();
+		let render = Access::new().read::<Position>().read::<Mesh>();
+		let steering = Access::new().write::<Velocity>();
+		let animation = Access::new().write::<Mesh>();
+		assert!(!physics.is_compatible(&render));
+		assert!(!physics.is_compatible(&steering));
+		assert!(physics.is_compatible(&animation));
+		assert!(render.is_compatible(&Access::new().read::<Position>()));
+	}
+}
@@ -0,0 +1,136 @@
+use crate::{EntityID, Query, QueryIter, QueryMut, QueryMutIter, ECS};
+
+/// Tag bits an entity must carry all of and none of to be yielded, see `QueryIter::with_layers`
+#[derive(Clone, Copy)]
+pub(crate) struct Layers {
+	pub(crate) require: u64,
+	pub(crate) exclude: u64,
+}
+
+impl Layers {
+	pub(crate) const ALL: Layers = Layers { require: 0, exclude: 0 };
+
+	#[inline]
+	pub(crate) fn admits(&self, ecs: &ECS, index: usize) -> bool {
+		if self.require | self.exclude == 0 { return true; }
+		let tags = ecs.tags[index];
+		tags & self.require == self.require && tags & self.exclude == 0
+	}
+
+	/// `bits` of the indices `64 * word..64 * word + 64` with the ones not admitted cleared
+	pub(crate) fn admits_word(&self, ecs: &ECS, word: usize, bits: u64) -> u64 {
+		if self.require | self.exclude == 0 { return bits; }
+		let (mut remaining, mut admitted) = (bits, 0);
+		while remaining != 0 {
+			let bit = remaining.trailing_zeros() as usize;
+			remaining &= remaining - 1;
+			if self.admits(ecs, word * 64 + bit) { admitted |= 1 << bit; }
+		}
+		admitted
+	}
+}
+
+impl ECS {
+	/// Every entity carries 64 tag bits, cleared when it is destroyed. Filtering on them with `query_tagged`
+	/// is a mask check per entity rather than a column per marker Component.
+	pub fn set_tag(&mut self, entity: &EntityID, mask: u64) {
+		if !self.is_valid(entity) { return; }
+		self.tags[entity.index] |= mask;
+	}
+
+	pub fn clear_tag(&mut self, entity: &EntityID, mask: u64) {
+		if !self.is_valid(entity) { return; }
+		self.tags[entity.index] &= !mask;
+	}
+
+	/// 0 for an invalid entity
+	pub fn get_tags(&self, entity: &EntityID) -> u64 {
+		if !self.is_valid(entity) { return 0; }
+		self.tags[entity.index]
+	}
+
+	/// True if `entity` carries every bit of `mask`
+	pub fn has_tags(&self, entity: &EntityID, mask: u64) -> bool {
+		self.get_tags(entity) & mask == mask
+	}
+
+	/// `query` restricted to entities carrying every bit of `require` and no bit of `exclude`
+	pub fn query_tagged<Q: Query>(&self, require: u64, exclude: u64) -> QueryIter<'_, Q> {
+		self.query::<Q>().with_layers(require).without_layers(exclude)
+	}
+
+	pub fn query_tagged_mut<Q: QueryMut>(&mut self, require: u64, exclude: u64) -> QueryMutIter<'_, Q> {
+		self.query_mut::<Q>().with_layers(require).without_layers(exclude)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+
+	const ENEMY: u64 = 1 << 0;
+	const FLYING: u64 = 1 << 1;
+	const UI: u64 = 1 << 2;
+
+	struct Position(u32);
+	impl Component for Position {}
+
+	#[test]
+	fn tagged() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<Position>();
+		let tags = [ENEMY, ENEMY | FLYING, UI, 0];
+		let entities: alloc::vec::Vec<_> = tags.iter().enumerate().map(|(index, mask)| {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(index as u32));
+			ecs.set_tag(&entity, *mask);
+			entity
+		}).collect();
+
+		let ground: alloc::vec::Vec<_> = ecs.query_tagged::<Position>(ENEMY, FLYING).map(|(_, position)| position.0).collect();
+		assert_eq!(ground, [0]);
+		assert_eq!(ecs.query_tagged::<Position>(0, UI).count(), 3);
+		for (_, position) in ecs.query_tagged_mut::<Position>(ENEMY, 0) {
+			position.0 += 10;
+		}
+		assert_eq!(ecs.get_component::<Position>(&entities[1]).unwrap().0, 11);
+
+		let checkpoint = ecs.save_checkpoint();
+		ecs.clear_tag(&entities[1], FLYING);
+		assert!(ecs.has_tags(&entities[1], ENEMY) && !ecs.has_tags(&entities[1], ENEMY | FLYING));
+		ecs.destroy_entity(entities[0]);
+		let reused = ecs.create_entity().unwrap();
+		assert_eq!(ecs.get_tags(&reused), 0);
+		assert_eq!(ecs.get_tags(&entities[0]), 0);
+
+		ecs.rollback_to(checkpoint);
+		assert_eq!(ecs.get_tags(&entities[0]), ENEMY);
+		assert_eq!(ecs.get_tags(&entities[1]), ENEMY | FLYING);
+	}
+
+	#[test]
+	fn layers() {
+		const RENDER: u64 = 1 << 8;
+		const HIDDEN: u64 = 1 << 9;
+
+		let mut ecs = ECS::new(64);
+		ecs.register::<Position>();
+		for index in 0..64 {
+			let entity = ecs.create_entity().unwrap();
+			ecs.add_component(&entity, Position(index));
+			ecs.set_tag(&entity, RENDER);
+			if index % 4 == 0 {
+				ecs.set_tag(&entity, HIDDEN);
+			}
+		}
+
+		let visible = ecs.query::<Position>().with_layers(RENDER).without_layers(HIDDEN);
+		assert!(visible.map(|(_, position)| position.0).all(|index| index % 4 != 0));
+		let chunked: usize = ecs.query::<Position>().without_layers(HIDDEN).chunks(10).map(|chunk| chunk.count()).sum();
+		assert_eq!(chunked, 48);
+		for (_, position) in ecs.query_mut::<Position>().with_layers(HIDDEN) {
+			position.0 = 0;
+		}
+		assert_eq!(ecs.query::<Position>().filter(|(_, position)| position.0 == 0).count(), 16);
+	}
+}
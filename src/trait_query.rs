@@ -0,0 +1,143 @@
+use crate::component::ComponentID;
+use crate::storage::ComponentArray;
+use crate::{Component, EntityID, ECS};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use hashbrown::HashMap;
+
+type CastFn<T> = Box<dyn Fn(*const u8) -> *const T + Send + Sync>;
+
+/// Component type implementing the trait object `T`
+struct TraitImpl<T: ?Sized> {
+	id: ComponentID,
+	cast: CastFn<T>,
+}
+
+type ImplList = Box<dyn Any + Send + Sync>;
+type ForgetFn = fn(&mut ImplList, ComponentID);
+
+fn forget_impl<T: ?Sized + 'static>(impls: &mut ImplList, id: ComponentID) {
+	impls.downcast_mut::<Vec<TraitImpl<T>>>().unwrap().retain(|implementor| implementor.id != id);
+}
+
+/// Implementors per trait object type, each entry a `Vec<TraitImpl<T>>` and how to remove one from it
+#[derive(Default)]
+pub(crate) struct TraitMap {
+	map: HashMap<TypeId, (ImplList, ForgetFn)>,
+}
+
+impl TraitMap {
+	fn get<T: ?Sized + 'static>(&self) -> &[TraitImpl<T>] {
+		match self.map.get(&TypeId::of::<T>()) {
+			Some((impls, _)) => impls.downcast_ref::<Vec<TraitImpl<T>>>().unwrap(),
+			None => &[],
+		}
+	}
+
+	/// Drop `id` as an implementor of every trait, its casts no longer match what the column holds
+	pub(crate) fn forget(&mut self, id: ComponentID) {
+		for (impls, forget) in self.map.values_mut() {
+			forget(impls, id);
+		}
+	}
+}
+
+/// `(EntityID, &T)` for every Component registered as implementing `T`. An entity holding several
+/// implementors is yielded once per implementor, grouped by implementor in registration order.
+pub struct TraitIter<'a, T: ?Sized + 'static> {
+	ecs: &'a ECS,
+	impls: &'a [TraitImpl<T>],
+	array: Option<&'a ComponentArray>,
+	current: usize,
+	index: usize,
+}
+
+impl<'a, T: ?Sized + 'static> Iterator for TraitIter<'a, T> {
+	type Item = (EntityID, &'a T);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.current < self.impls.len() {
+			let implementor = &self.impls[self.current];
+			// An implementor unregistered since has nothing left to yield
+			let Some(array) = self.array.or_else(|| self.ecs.components.get_array_by_id(implementor.id)) else {
+				self.current += 1;
+				continue;
+			};
+			self.array = Some(array);
+			while self.index < self.ecs.capacity {
+				let index = self.index;
+				self.index += 1;
+				if let Some(pointer) = array.get_ptr(index) {
+					let entity = self.ecs.get_index(index).unwrap();
+					return Some((entity, unsafe { &*(implementor.cast)(pointer) }));
+				}
+			}
+			self.current += 1;
+			self.array = None;
+			self.index = 0;
+		}
+		None
+	}
+}
+
+impl ECS {
+	/// Record that `C` implements the trait object `T`, with `cast` usually just `|component| component`.
+	/// Registers `C` if needed.
+	pub fn register_trait<T: ?Sized + 'static, C: Component>(&mut self, cast: fn(&C) -> &T) {
+		if self.components.get_array::<C>().is_none() {
+			self.register::<C>();
+		}
+		let (impls, _) = self.traits.map.entry(TypeId::of::<T>()).or_insert_with(|| (Box::new(Vec::<TraitImpl<T>>::new()), forget_impl::<T>));
+		let impls = impls.downcast_mut::<Vec<TraitImpl<T>>>().unwrap();
+		if impls.iter().any(|implementor| implementor.id == ComponentID::of::<C>()) { return; }
+		let cast = move |pointer: *const u8| cast(unsafe { &*(pointer as *const C) }) as *const T;
+		impls.push(TraitImpl { id: ComponentID::of::<C>(), cast: Box::new(cast) });
+	}
+
+	/// Every Component implementing `T`, for example `query_trait::<dyn Renderable>()`
+	pub fn query_trait<T: ?Sized + 'static>(&self) -> TraitIter<'_, T> {
+		TraitIter { ecs: self, impls: self.traits.get::<T>(), array: None, current: 0, index: 0 }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, ECS};
+	use alloc::vec::Vec;
+
+	trait Renderable {
+		fn layer(&self) -> u32;
+	}
+
+	struct Sprite(u32);
+	impl Component for Sprite {}
+	impl Renderable for Sprite {
+		fn layer(&self) -> u32 { self.0 }
+	}
+
+	struct Text;
+	impl Component for Text {}
+	impl Renderable for Text {
+		fn layer(&self) -> u32 { 100 }
+	}
+
+	#[test]
+	fn query_trait() {
+		let mut ecs = ECS::new(4);
+		assert_eq!(ecs.query_trait::<dyn Renderable>().count(), 0);
+		ecs.register_trait::<dyn Renderable, Sprite>(|sprite| sprite);
+		ecs.register_trait::<dyn Renderable, Text>(|text| text);
+		ecs.register_trait::<dyn Renderable, Text>(|text| text);
+
+		let both = ecs.create_entity().unwrap();
+		ecs.add_component(&both, Sprite(1));
+		ecs.add_component(&both, Text);
+		let sprite = ecs.create_entity().unwrap();
+		ecs.add_component(&sprite, Sprite(2));
+		ecs.create_entity().unwrap();
+
+		let layers: Vec<_> = ecs.query_trait::<dyn Renderable>().map(|(entity, renderable)| (entity.index, renderable.layer())).collect();
+		assert_eq!(layers, [(0, 1), (1, 2), (0, 100)]);
+	}
+}
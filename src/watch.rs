@@ -0,0 +1,200 @@
+use crate::component::ComponentID;
+use crate::{Component, EntityID, WeakEntity, ECS};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+/// Handle to a subscription from `ECS::watch`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WatchID(usize);
+
+/// What happened to a watched Component since the last `poll_watches`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchEvent {
+	/// Inserted, overwritten, borrowed mutably, or removed
+	Changed,
+	/// The entity was destroyed, ending the subscription
+	Destroyed,
+}
+
+struct Watch {
+	entity: EntityID,
+	id: ComponentID,
+	pending: Option<WatchEvent>,
+}
+
+#[derive(Default)]
+pub(crate) struct Watches {
+	watches: Vec<Option<Watch>>,
+	by_target: HashMap<(usize, ComponentID), Vec<WatchID>>,
+	/// Watches with an event pending, in the order they were first queued
+	queue: Vec<WatchID>,
+}
+
+impl Watches {
+	fn queue(&mut self, watch: WatchID, event: WatchEvent) {
+		let Some(Some(slot)) = self.watches.get_mut(watch.0) else { return };
+		if slot.pending.is_none() {
+			self.queue.push(watch);
+		}
+		if slot.pending != Some(WatchEvent::Destroyed) {
+			slot.pending = Some(event);
+		}
+	}
+
+	pub(crate) fn on_change(&mut self, id: ComponentID, index: usize) {
+		if self.by_target.is_empty() { return; }
+		let Some(watches) = self.by_target.get(&(index, id)) else { return };
+		for watch in watches.clone() {
+			self.queue(watch, WatchEvent::Changed);
+		}
+	}
+
+	pub(crate) fn on_destroy(&mut self, index: usize) {
+		if self.by_target.is_empty() { return; }
+		let ended: Vec<WatchID> = self.by_target.iter().filter(|((target, _), _)| *target == index).flat_map(|(_, watches)| watches.clone()).collect();
+		self.by_target.retain(|(target, _), _| *target != index);
+		for watch in ended {
+			self.queue(watch, WatchEvent::Destroyed);
+		}
+	}
+
+	fn unlink(&mut self, watch: WatchID, index: usize, id: ComponentID) {
+		if let Some(watches) = self.by_target.get_mut(&(index, id)) {
+			watches.retain(|linked| *linked != watch);
+			if watches.is_empty() {
+				self.by_target.remove(&(index, id));
+			}
+		}
+	}
+
+	/// Follow entities given new ids by `compact`
+	pub(crate) fn remap(&mut self, moves: &HashMap<WeakEntity, EntityID>) {
+		if self.by_target.is_empty() { return; }
+		self.by_target.clear();
+		for (position, slot) in self.watches.iter_mut().enumerate() {
+			let Some(watch) = slot else { continue };
+			if watch.pending == Some(WatchEvent::Destroyed) { continue; }
+			if let Some(new) = moves.get(&watch.entity.downgrade()) {
+				watch.entity = *new;
+			}
+			self.by_target.entry((watch.entity.index, watch.id)).or_default().push(WatchID(position));
+		}
+	}
+}
+
+impl ECS {
+	/// Queue a `WatchEvent` whenever `C` on `entity` changes, to be collected with `poll_watches`.
+	/// `None` if `entity` is invalid.
+	pub fn watch<C: Component>(&mut self, entity: &EntityID) -> Option<WatchID> {
+		if !self.is_valid(entity) { return None; }
+		let id = ComponentID::of::<C>();
+		let watch = Some(Watch { entity: *entity, id, pending: None });
+		let watches = &mut self.watches.watches;
+		let handle = match watches.iter().position(|slot| slot.is_none()) {
+			Some(position) => {
+				watches[position] = watch;
+				WatchID(position)
+			},
+			None => {
+				watches.push(watch);
+				WatchID(watches.len() - 1)
+			},
+		};
+		self.watches.by_target.entry((entity.index, id)).or_default().push(handle);
+		Some(handle)
+	}
+
+	/// Cancel a subscription, dropping any event still pending for it
+	pub fn unwatch(&mut self, watch: WatchID) {
+		let Some(slot) = self.watches.watches.get_mut(watch.0) else { return };
+		if let Some(ended) = slot.take() {
+			self.watches.unlink(watch, ended.entity.index, ended.id);
+		}
+	}
+
+	/// Take every pending event, at most one per watch. A `Destroyed` watch is ended and its id may be reused.
+	pub fn poll_watches(&mut self) -> Vec<(WatchID, WatchEvent)> {
+		let mut events = Vec::new();
+		for watch in core::mem::take(&mut self.watches.queue) {
+			let Some(Some(slot)) = self.watches.watches.get_mut(watch.0) else { continue };
+			let Some(event) = slot.pending.take() else { continue };
+			if event == WatchEvent::Destroyed {
+				self.watches.watches[watch.0] = None;
+			}
+			events.push((watch, event));
+		}
+		events
+	}
+
+	/// End watches whose entity didn't survive a wholesale change like `rollback_to`
+	pub(crate) fn prune_watches(&mut self) {
+		let ended: Vec<(WatchID, usize, ComponentID)> = self.watches.watches.iter().enumerate()
+			.filter_map(|(position, slot)| slot.as_ref().map(|watch| (position, watch)))
+			.filter(|(_, watch)| watch.pending != Some(WatchEvent::Destroyed) && !self.is_valid(&watch.entity))
+			.map(|(position, watch)| (WatchID(position), watch.entity.index, watch.id))
+			.collect();
+		for (watch, index, id) in ended {
+			self.watches.unlink(watch, index, id);
+			self.watches.queue(watch, WatchEvent::Destroyed);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Component, WatchEvent, ECS};
+
+	struct Health(u32);
+	impl Component for Health {}
+	struct Name;
+	impl Component for Name {}
+
+	#[test]
+	fn watch() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<Health>();
+		ecs.register::<Name>();
+		let unit = ecs.create_entity().unwrap();
+		let other = ecs.create_entity().unwrap();
+		ecs.add_component(&unit, Health(10));
+		ecs.add_component(&other, Health(10));
+
+		let health = ecs.watch::<Health>(&unit).unwrap();
+		let name = ecs.watch::<Name>(&unit).unwrap();
+		assert!(ecs.poll_watches().is_empty());
+
+		ecs.get_component_mut::<Health>(&unit).unwrap().0 -= 1;
+		ecs.add_component(&unit, Health(5));
+		ecs.get_component_mut::<Health>(&other).unwrap().0 -= 1;
+		assert_eq!(ecs.poll_watches(), [(health, WatchEvent::Changed)]);
+		assert!(ecs.poll_watches().is_empty());
+
+		ecs.add_component(&unit, Name);
+		ecs.unwatch(name);
+		ecs.remove_component::<Health>(&unit);
+		ecs.destroy_entity(unit);
+		assert_eq!(ecs.poll_watches(), [(health, WatchEvent::Destroyed)]);
+
+		let reused = ecs.create_entity().unwrap();
+		assert_eq!(reused.index, unit.index);
+		ecs.add_component(&reused, Health(1));
+		assert!(ecs.poll_watches().is_empty());
+		assert!(ecs.watch::<Health>(&unit).is_none());
+	}
+
+	#[test]
+	fn follows_compaction() {
+		let mut ecs = ECS::new(8);
+		ecs.register::<Health>();
+		let first = ecs.create_entity().unwrap();
+		let unit = ecs.create_entity().unwrap();
+		ecs.add_component(&unit, Health(3));
+		let watch = ecs.watch::<Health>(&unit).unwrap();
+		ecs.destroy_entity(first);
+
+		let mut moved = None;
+		ecs.compact(|_, new| moved = Some(new));
+		ecs.get_component_mut::<Health>(&moved.unwrap()).unwrap().0 = 2;
+		assert_eq!(ecs.poll_watches(), [(watch, WatchEvent::Changed)]);
+	}
+}